@@ -1,18 +1,47 @@
 use anyhow::Result;
-use clap::{ArgAction, Parser};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 use inquire::Confirm;
-use ptree::TreeBuilder;
 
-use preamble::{bins, cargo, ci, etc, git, gitignore, libs, license, root, telemetry, utils};
+use preamble::{
+    allocator::Allocator,
+    builder::{Author, Copyright, CrateBuilder, Dependency, License, Workflow, WorkspaceBuilder},
+    check::{self, Severity},
+    ci::{self, ActionLock},
+    command::SystemRunner,
+    cross::{BuildTool, CrossConfig},
+    license,
+    lints::LintProfile,
+    pipeline::{CiConfig, CiJob, CiProvider, Toolchain},
+    profiles::ProfilePreset,
+    report::Report,
+    root,
+    telemetry::{LogFormat, Telemetry},
+    updates::UpdateBot,
+    utils, Error,
+};
 
 /// Command line arguments.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct Args {
+    /// Subcommands.
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Verbosity level (0-4). Default: 0 (ERROR).
     #[arg(long, short, action = ArgAction::Count, default_value = "0")]
     v: u8,
 
+    /// The log output format: full, compact, pretty or json.
+    #[arg(long, default_value = "full")]
+    log_format: LogFormat,
+
+    /// Writes a json report of every file, command and network fetch
+    /// to the given path once the run completes or fails, with the error.
+    /// Use `-` for stdout, which is shared with the log output.
+    #[arg(long)]
+    report: Option<String>,
+
     /// Dry run mode.
     /// If this flag is provided, the cli will not execute commands,
     /// printing the directories and files that would be created instead.
@@ -38,13 +67,101 @@ pub struct Args {
     #[arg(long, short)]
     with_ci: bool,
 
-    /// Copy the specified workflow file to the project's `.github/workflows/` directory.
-    #[arg(long, short)]
-    ci_yml: Option<String>,
+    /// Copy the specified workflow files, directories of workflow files or urls
+    /// to the project's `.github/workflows/` directory, keeping their file names.
+    /// Files ending in `.tmpl` are rendered with the project variables.
+    #[arg(long, short, value_delimiter = ',')]
+    ci_yml: Option<Vec<String>>,
+
+    /// The ci provider to generate the pipeline for: github, gitlab or forgejo.
+    /// Defaults to `github`.
+    #[arg(long)]
+    ci_provider: Option<CiProvider>,
+
+    /// Pins the generated workflow actions to commit SHAs from the bundled action
+    /// lock, overridden by the given lock file. Actions without a pin are reported.
+    #[arg(long, value_name = "LOCKFILE")]
+    pin_actions: Option<Option<std::path::PathBuf>>,
+
+    /// The comma separated toolchains the ci workflow tests on: stable, msrv and nightly.
+    /// Defaults to `stable,msrv`, where msrv is the `--rust-version`.
+    #[arg(long, value_delimiter = ',')]
+    toolchains: Option<Vec<Toolchain>>,
+
+    /// The comma separated operating systems the ci workflow tests on.
+    /// Defaults to `ubuntu-latest`.
+    #[arg(long, value_delimiter = ',')]
+    os: Option<Vec<String>>,
+
+    /// Tests every feature combination in the ci workflow with `cargo hack`.
+    #[arg(long)]
+    feature_powerset: bool,
+
+    /// The comma separated extra ci jobs: docs, coverage, miri, semver-checks and deny.
+    /// Defaults to `docs`.
+    #[arg(long, value_delimiter = ',')]
+    ci_jobs: Option<Vec<CiJob>>,
+
+    /// Sets the minimum supported rust version in the manifest and ci workflow.
+    #[arg(long)]
+    rust_version: Option<String>,
+
+    /// Only add the given comma separated github action workflows, e.g. `ci,audit`.
+    /// Run `amble list workflows` to see the available workflows.
+    #[arg(long, value_delimiter = ',')]
+    workflows: Option<Vec<Workflow>>,
+
+    /// Adds a cross-compiled release workflow for the binary, building it for each
+    /// `--targets` with the `--build-tool` on tags and uploading archives with checksums.
+    #[arg(long)]
+    cross_release: bool,
+
+    /// The comma separated target triples of the cross release.
+    /// Defaults to the linux, apple and windows gnu targets.
+    #[arg(long, value_delimiter = ',')]
+    targets: Option<Vec<String>>,
+
+    /// The cross release build file: make or just. Defaults to `make`.
+    #[arg(long)]
+    build_tool: Option<BuildTool>,
+
+    /// Keeps the dependencies and github actions current with a dependabot or
+    /// renovate config, grouping the workspace dependencies of a family.
+    #[arg(long, value_name = "BOT")]
+    dependency_updates: Option<UpdateBot>,
+
+    /// Adds a `deny.toml` allowing the licenses compatible with the project license,
+    /// banning crates and denying yanked versions, and a `cargo deny` ci job.
+    #[arg(long)]
+    deny: bool,
+
+    /// Adds a `supply-chain/` directory for `cargo vet`.
+    #[arg(long)]
+    cargo_vet: bool,
+
+    /// Adds a `rustfmt.toml`, `clippy.toml`, `rust-toolchain.toml` and `.editorconfig`,
+    /// keeping the toolchain and MSRV consistent with the ci workflow.
+    #[arg(long)]
+    tooling: bool,
+
+    /// The lint profile written to the workspace manifest and inherited by each
+    /// crate: relaxed, standard, strict or pedantic-clippy. Defaults to `standard`.
+    #[arg(long, value_name = "PROFILE")]
+    lints: Option<LintProfile>,
+
+    /// Comma-separated cargo profile presets added to the root manifest:
+    /// maxperf, debug-fast, min-size and ci.
+    #[arg(long, value_delimiter = ',', value_name = "PRESETS")]
+    profiles: Option<Vec<ProfilePreset>>,
 
-    /// Override the project authors.
+    /// Sets the global allocator of the binaries, jemalloc or mimalloc, behind a
+    /// default cargo feature.
+    #[arg(long, value_name = "ALLOCATOR")]
+    allocator: Option<Allocator>,
+
+    /// Override the project authors, formatted as `name` or `name <email>`.
     #[arg(long, short)]
-    authors: Option<Vec<String>>,
+    authors: Option<Vec<Author>>,
 
     /// Builds a cargo binary project.
     #[arg(long, short)]
@@ -86,9 +203,9 @@ pub struct Args {
     description: Option<String>,
 
     /// Adds these dependencies to the top-level `Cargo.toml` workspace
-    /// alongside the default dependencies.
+    /// alongside the default dependencies. Use `name@version` to pin a version.
     #[arg(long)]
-    dependencies: Option<Vec<String>>,
+    dependencies: Option<Vec<Dependency>>,
 
     /// Lists the default dependencies.
     #[arg(long)]
@@ -96,9 +213,17 @@ pub struct Args {
 
     /// License Override.
     /// This will override the default MIT License.
-    /// The license type must be a valid SPDX license identifier.
+    /// The license type must be a valid SPDX license expression, e.g. `MIT OR Apache-2.0`.
+    #[arg(long)]
+    with_license: Option<License>,
+
+    /// The organization named by licenses with an `<organization>` placeholder.
+    #[arg(long)]
+    organization: Option<String>,
+
+    /// Prepends a SPDX license header to the generated rust source files.
     #[arg(long)]
-    with_license: Option<String>,
+    license_headers: bool,
 
     /// The path to the project directory.
     /// By default, the current working directory is used.
@@ -110,12 +235,152 @@ pub struct Args {
     /// Create git repository with user's github username
     #[arg(long)]
     git: Option<Option<String>>,
+
+    /// Overrides the `git` binary used to initialize the repository.
+    #[arg(long)]
+    git_bin: Option<std::path::PathBuf>,
+
+    /// Overrides the `cargo` binary used for `cargo init` and the `cargo search`
+    /// dependency version lookups of every project.
+    #[arg(long)]
+    cargo_bin: Option<std::path::PathBuf>,
+}
+
+/// Returns the process exit code for an error returned by [run].
+///
+/// | Code | Error                                                        |
+/// |------|--------------------------------------------------------------|
+/// | 1    | Any other error                                              |
+/// | 2    | Invalid command line arguments (reported by clap)            |
+/// | 3    | [Error::Conflict], conflicting files in non-interactive runs |
+/// | 4    | [Error::UserAborted], a prompt was declined or cancelled     |
+/// | 5    | [Error::Network], a network request failed                   |
+/// | 6    | [Error::License], the license is unknown or invalid          |
+/// | 7    | [Error::Template], a template failed to render               |
+/// | 8    | [Error::Command], an external command failed                 |
+/// | 9    | [Error::Io], reading or writing a file failed                |
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<Error>() {
+        Some(Error::Conflict(_)) => 3,
+        Some(Error::UserAborted(_)) => 4,
+        Some(Error::Network { .. }) => 5,
+        Some(Error::License(_)) => 6,
+        Some(Error::Template(_)) => 7,
+        Some(Error::Command(_)) => 8,
+        Some(Error::Io(_)) => 9,
+        None => 1,
+    }
+}
+
+/// Amble subcommands.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Lists the available licenses, workflows or the default dependencies.
+    List {
+        /// The items to list.
+        #[arg(value_enum)]
+        item: ListItem,
+    },
+    /// Adds an item to an existing project.
+    Add {
+        /// The item to add.
+        #[command(subcommand)]
+        item: AddItem,
+    },
+    /// Maintains the ci workflows of an existing project.
+    Ci {
+        /// The ci task to run.
+        #[command(subcommand)]
+        item: CiItem,
+    },
+}
+
+/// The items added by the `add` subcommand.
+#[derive(Subcommand, Debug)]
+pub enum AddItem {
+    /// Adds SPDX license headers to every rust source file.
+    LicenseHeaders {
+        /// The path to the project directory.
+        #[arg(default_value = ".")]
+        project_dir: String,
+
+        /// The SPDX license expression to use.
+        /// Defaults to the `license` field of the project's `Cargo.toml`.
+        #[arg(long)]
+        license: Option<License>,
+
+        /// Lists the changes without writing any files.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Adds github action workflows, keeping existing workflow files.
+    Workflows {
+        /// The comma separated workflows to add, see `amble list workflows`.
+        #[arg(value_delimiter = ',', required = true)]
+        workflows: Vec<Workflow>,
+
+        /// The path to the project directory.
+        #[arg(long, default_value = ".")]
+        project_dir: String,
+
+        /// Lists the changes without writing any files.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// The tasks run by the `ci` subcommand.
+#[derive(Subcommand, Debug)]
+pub enum CiItem {
+    /// Pins the github and forgejo workflow actions to commit SHAs.
+    ///
+    /// Pins are taken from the bundled action lock, overridden by the
+    /// `--lock` file or the project's `.github/actions.lock`. Pinned
+    /// actions are re-pinned from their `# <ref>` comment, so running
+    /// it again after updating the lock refreshes the pins.
+    Pin {
+        /// The path to the project directory.
+        #[arg(default_value = ".")]
+        project_dir: String,
+
+        /// The action lock file overriding the bundled pins.
+        #[arg(long)]
+        lock: Option<std::path::PathBuf>,
+
+        /// Lists the changes without writing any files.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Checks the github workflows for invalid yaml, unknown workflow and job
+    /// references, missing write permissions and deprecated actions, and
+    /// lists the secrets they require.
+    ///
+    /// Fails if any finding is an error.
+    Check {
+        /// The path to the project directory.
+        #[arg(default_value = ".")]
+        project_dir: String,
+    },
+}
+
+/// The items listed by the `list` subcommand.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListItem {
+    /// The embedded SPDX licenses.
+    Licenses,
+    /// The bundled github action workflows.
+    Workflows,
+    /// The default workspace dependencies.
+    Dependencies,
 }
 
 /// CLI Entrypoint.
 pub fn run() -> Result<()> {
     let Args {
+        command,
         v,
+        log_format,
+        report: report_path,
         dry_run,
         mut assets,
         bare,
@@ -125,11 +390,31 @@ pub fn run() -> Result<()> {
         mut overwrite,
         mut with_ci,
         ci_yml,
+        ci_provider,
+        pin_actions,
+        workflows,
+        toolchains,
+        os,
+        feature_powerset,
+        ci_jobs,
+        rust_version,
+        cross_release,
+        targets,
+        build_tool,
+        dependency_updates,
+        deny,
+        cargo_vet,
+        tooling,
+        lints,
+        profiles,
+        allocator,
         authors,
         bin,
         lib,
         mut license,
         with_license,
+        license_headers,
+        organization,
         mut gitignore,
         full,
         description,
@@ -137,7 +422,10 @@ pub fn run() -> Result<()> {
         dependencies,
         mut etc,
         mut git,
+        git_bin,
+        cargo_bin,
     } = Args::parse();
+    let runner = SystemRunner::new(git_bin, cargo_bin);
     let project_dir_path = std::path::Path::new(&project_dir);
 
     if full {
@@ -148,24 +436,59 @@ pub fn run() -> Result<()> {
         assets = true;
         git = Some(None);
     }
+    // Choosing a ci provider implies generating its ci pipeline.
+    with_ci |= ci_provider.is_some();
 
-    if list {
-        root::list_dependencies()?;
-        return Ok(());
+    match command {
+        Some(Command::List { item: ListItem::Licenses }) => return Ok(license::list_licenses()?),
+        Some(Command::List { item: ListItem::Dependencies }) => {
+            return Ok(root::list_dependencies()?)
+        }
+        Some(Command::List { item: ListItem::Workflows }) => return Ok(ci::list_workflows()?),
+        Some(Command::Add { item: AddItem::LicenseHeaders { project_dir, license, dry_run } }) => {
+            return add_license_headers(std::path::Path::new(&project_dir), license, dry_run)
+        }
+        Some(Command::Add { item: AddItem::Workflows { workflows, project_dir, dry_run } }) => {
+            let dir = std::path::Path::new(&project_dir);
+            let mut report = Report::new(&project_dir, dry_run);
+            ci::add(dir, &workflows, dry_run, Some(&mut report))?;
+            for warning in &report.warnings {
+                eprintln!("warning: {}", warning);
+            }
+            return Ok(ptree::print_tree(&report.tree())?);
+        }
+        Some(Command::Ci { item: CiItem::Pin { project_dir, lock, dry_run } }) => {
+            let dir = std::path::Path::new(&project_dir);
+            let mut report = Report::new(&project_dir, dry_run);
+            ci::pin(dir, lock.as_deref(), dry_run, Some(&mut report))?;
+            for warning in &report.warnings {
+                eprintln!("warning: {}", warning);
+            }
+            return Ok(ptree::print_tree(&report.tree())?);
+        }
+        Some(Command::Ci { item: CiItem::Check { project_dir } }) => {
+            return check_workflows(std::path::Path::new(&project_dir))
+        }
+        None if list => return Ok(root::list_dependencies()?),
+        None => {}
     }
 
-    telemetry::init_tracing_subscriber(v)?;
+    Telemetry::new().verbosity(v).format(log_format).init()?;
 
     match overwrite {
         true => {
             tracing::warn!("Overwrite flag is set, existing files will be overwritten");
-            if !Confirm::new("[WARNING] Overwrite mode will overwrite any conflicting files. Are you sure you wish to proceed?").prompt()? {
+            if !Confirm::new("[WARNING] Overwrite mode will overwrite any conflicting files. Are you sure you wish to proceed?").prompt().map_err(Error::from)? {
                 println!("Phew, close call... aborting");
-                return Ok(());
+                return Err(Error::UserAborted("User aborted overwrite mode".to_string()).into());
             }
         }
         false => {
-            utils::check_artifacts(project_dir_path, with_ci || ci_yml.is_some(), dry_run)?;
+            utils::check_artifacts(
+                project_dir_path,
+                with_ci || ci_yml.is_some() || workflows.is_some(),
+                dry_run,
+            )?;
             overwrite = true;
         }
     }
@@ -175,80 +498,164 @@ pub fn run() -> Result<()> {
         tracing::warn!("Running in non-dry run mode.");
         tracing::warn!("This action may be destructive.");
         if !Confirm::new("Running amble in without dry mode, are you sure you wish to proceed?")
-            .prompt()?
+            .prompt()
+            .map_err(Error::from)?
         {
             println!("Phew, close call... aborting");
-            return Ok(());
+            return Err(Error::UserAborted("User aborted the non-dry run".to_string()).into());
         }
     }
 
-    let mut builder = TreeBuilder::new(project_dir.clone());
     if !dry_run {
         std::fs::create_dir_all(project_dir_path)?;
     }
 
-    if license || with_license.is_some() {
-        let license_type = with_license.as_deref().unwrap_or("mit");
-        license::create(project_dir_path, license_type, dry_run, Some(&mut builder))?;
+    let mut ci_config = CiConfig { feature_powerset, ..Default::default() };
+    if let Some(toolchains) = toolchains {
+        ci_config.toolchains = toolchains;
+    }
+    if let Some(os) = os {
+        ci_config.os = os;
+    }
+    if let Some(ci_jobs) = ci_jobs {
+        ci_config.jobs = ci_jobs;
+    }
+    if let Some(ci_provider) = ci_provider {
+        ci_config.provider = ci_provider;
+    }
+    if let Some(lock) = pin_actions {
+        let pins = match lock {
+            Some(path) => ActionLock::bundled().merge(ActionLock::load(&path)?),
+            None => ActionLock::bundled(),
+        };
+        ci_config.pins = Some(pins);
     }
 
-    if gitignore {
-        gitignore::create(project_dir_path, dry_run, Some(&mut builder))?;
+    let mut report = Report::new(&project_dir, dry_run);
+    // Applies the options shared by the workspace and crate builders.
+    macro_rules! batteries {
+        ($builder:expr) => {{
+            let mut builder = $builder
+                .authors(authors.unwrap_or_default())
+                .dependencies(dependencies.unwrap_or_default())
+                .gitignore(gitignore)
+                .etc(etc)
+                .assets(assets)
+                .ci(with_ci)
+                .ci_config(ci_config)
+                .license_headers(license_headers)
+                .deny(deny)
+                .cargo_vet(cargo_vet)
+                .tooling(tooling)
+                .lints(lints.unwrap_or_default())
+                // The conflicting files were confirmed above.
+                .overwrite(true)
+                .dry_run(dry_run)
+                .runner(runner);
+            if let Some(description) = description {
+                builder = builder.description(description);
+            }
+            if license || with_license.is_some() {
+                builder = builder.license(with_license.unwrap_or_default());
+            }
+            if let Some(organization) = organization {
+                builder = builder.organization(organization);
+            }
+            if let Some(user) = git {
+                builder = builder.git(user);
+            }
+            if let Some(rust_version) = rust_version {
+                builder = builder.rust_version(rust_version);
+            }
+            if let Some(workflows) = workflows {
+                builder = builder.workflows(workflows);
+            }
+            if cross_release || targets.is_some() || build_tool.is_some() {
+                let mut cross_config = CrossConfig::default();
+                if let Some(targets) = targets {
+                    cross_config.targets = targets;
+                }
+                if let Some(build_tool) = build_tool {
+                    cross_config.build_tool = build_tool;
+                }
+                builder = builder.cross_release(cross_config);
+            }
+            if let Some(bot) = dependency_updates {
+                builder = builder.dependency_updates(bot);
+            }
+            if let Some(allocator) = allocator {
+                builder = builder.allocator(allocator);
+            }
+            for preset in profiles.unwrap_or_default() {
+                builder = builder.profile(preset);
+            }
+            for ci_yml in ci_yml.unwrap_or_default() {
+                builder = builder.ci_yml(ci_yml);
+            }
+            builder.build_into(&mut report)
+        }};
     }
 
-    if let Some(u) = git {
-        git::create(project_dir_path, dry_run, u, Some(&mut builder))?;
+    let result = match (bin, lib) {
+        (true, _) => batteries!(CrateBuilder::bin(project_dir_path, &name).bare(bare)),
+        (_, true) => batteries!(CrateBuilder::lib(project_dir_path, &name).bare(bare)),
+        _ => batteries!(WorkspaceBuilder::new(project_dir_path, &name).readme(!without_readme)),
+    };
+
+    if let Err(e) = &result {
+        report.fail(e);
     }
 
-    if etc {
-        etc::create(project_dir_path, dry_run, assets, Some(&mut builder))?;
+    for warning in &report.warnings {
+        eprintln!("warning: {}", warning);
     }
 
-    if !bin && !lib {
-        root::create(
-            project_dir_path,
-            &name,
-            description.as_ref(),
-            dry_run,
-            without_readme,
-            authors,
-            dependencies,
-            Some(&mut builder),
-        )?;
-        bins::create(&project_dir_path.join("bin"), &name, dry_run, Some(&mut builder))?;
-        libs::create(&project_dir_path.join("crates"), "common", dry_run, Some(&mut builder))?;
-    } else if bin {
-        cargo::create_bin(
-            project_dir_path,
-            &name,
-            description.as_ref(),
-            dry_run,
-            bare,
-            authors,
-            dependencies,
-            Some(&mut builder),
-        )?;
-    } else if lib {
-        cargo::create_lib(
-            project_dir_path,
-            &name,
-            description.as_ref(),
-            dry_run,
-            bare,
-            authors,
-            dependencies,
-            Some(&mut builder),
-        )?;
+    if dry_run && result.is_ok() {
+        ptree::print_tree(&report.tree()).expect("Error printing tree");
     }
 
-    if with_ci || ci_yml.is_some() {
-        ci::create(project_dir_path, dry_run, ci_yml, Some(&mut builder))?;
+    // The report is written on failure too, recording the run up to the error.
+    match report_path.as_deref() {
+        Some("-") => println!("{}", report.to_json()?),
+        Some(path) => std::fs::write(path, report.to_json()?)?,
+        None => {}
     }
 
-    if dry_run {
-        let tree = builder.build();
-        ptree::print_tree(&tree).expect("Error printing tree");
+    Ok(result?)
+}
+
+/// Adds SPDX license headers to every rust source file in the project.
+fn add_license_headers(dir: &std::path::Path, license: Option<License>, dry: bool) -> Result<()> {
+    let license = match license {
+        Some(license) => license,
+        None => license::manifest_license(dir)?.ok_or_else(|| {
+            Error::License("No license found in Cargo.toml, use `--license`".to_string())
+        })?,
+    };
+    let copyright = Copyright::new(&license::manifest_authors(dir)?);
+    let files = license::find_rust_files(dir)?;
+    for file in license::add_license_headers(dir, &files, &license, &copyright, dry)? {
+        println!("{}", file.display());
     }
+    Ok(())
+}
 
+/// Prints the workflow findings, failing if any is an error.
+fn check_workflows(dir: &std::path::Path) -> Result<()> {
+    let findings = check::check(dir)?;
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    let errors = count(Severity::Error);
+    println!(
+        "{} errors, {} warnings, {} notes",
+        errors,
+        count(Severity::Warning),
+        count(Severity::Note)
+    );
+    if errors > 0 {
+        anyhow::bail!("Found {} errors in the workflows", errors);
+    }
     Ok(())
 }
@@ -1,6 +1,6 @@
 fn main() {
     if let Err(err) = amble::cli::run() {
         eprintln!("Error: {err:?}");
-        std::process::exit(1);
+        std::process::exit(amble::cli::exit_code(&err));
     }
 }
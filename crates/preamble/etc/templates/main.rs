@@ -0,0 +1,27 @@
+#![doc = env!("CARGO_PKG_DESCRIPTION")]
+
+use clap::{ArgAction, Parser};
+use common::telemetry::{LogFormat, Telemetry};
+
+/// Command line arguments.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Verbosity level (0-4). Default: 0 (ERROR).
+    #[arg(long, short, action = ArgAction::Count, default_value = "0")]
+    v: u8,
+
+    /// The log output format: full, compact, pretty or json.
+    #[arg(long, default_value = "full")]
+    log_format: LogFormat,
+}
+
+fn main() -> anyhow::Result<()> {
+    let Args { v, log_format } = Args::parse();
+    Telemetry::new().verbosity(v).format(log_format).init()?;
+
+    tracing::info!("Running {}", env!("CARGO_PKG_NAME"));
+    println!("2 + 2 = {}", common::add(2, 2));
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::Result,
+    root::{member_manifests, parse_manifest},
+};
+
+/// The global allocator of the generated binaries, enabled by a default
+/// cargo feature of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Allocator {
+    /// The `jemallocator` crate, which doesn't build on windows.
+    Jemalloc,
+    /// The `mimalloc` crate.
+    Mimalloc,
+}
+
+impl std::fmt::Display for Allocator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.feature())
+    }
+}
+
+impl std::str::FromStr for Allocator {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "jemalloc" => Ok(Self::Jemalloc),
+            "mimalloc" => Ok(Self::Mimalloc),
+            _ => Err(format!("unknown allocator \"{}\", expected jemalloc or mimalloc", s)),
+        }
+    }
+}
+
+impl Allocator {
+    /// Returns the cargo feature enabling the allocator.
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Self::Jemalloc => "jemalloc",
+            Self::Mimalloc => "mimalloc",
+        }
+    }
+
+    /// Returns the allocator crate and its version requirement.
+    pub fn dependency(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Jemalloc => ("jemallocator", "0.5"),
+            Self::Mimalloc => ("mimalloc", "0.1"),
+        }
+    }
+
+    /// Returns the `cfg` the allocator dependency is gated on, if any.
+    pub fn target(&self) -> Option<&'static str> {
+        match self {
+            Self::Jemalloc => Some("cfg(not(windows))"),
+            Self::Mimalloc => None,
+        }
+    }
+
+    /// Returns the `#[global_allocator]` static of the `main.rs`.
+    pub fn global_allocator(&self) -> String {
+        let (cfg, allocator) = match self {
+            Self::Jemalloc => {
+                ("all(feature = \"jemalloc\", not(windows))", "jemallocator::Jemalloc")
+            }
+            Self::Mimalloc => ("feature = \"mimalloc\"", "mimalloc::MiMalloc"),
+        };
+        format!(
+            "#[cfg({})]\n#[global_allocator]\nstatic GLOBAL: {} = {};\n",
+            cfg, allocator, allocator
+        )
+    }
+}
+
+/// Returns the `main.rs` contents with the [Allocator::global_allocator]
+/// following the leading inner attributes and imports.
+pub fn with_global_allocator(main_rs: &str, allocator: Allocator) -> String {
+    let mut offset = 0;
+    let mut end = 0;
+    let mut in_use = false;
+    for line in main_rs.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let leading = in_use ||
+            trimmed.is_empty() ||
+            trimmed.starts_with("#![") ||
+            trimmed.starts_with("//!") ||
+            trimmed.starts_with("use ") ||
+            trimmed.starts_with("extern crate ");
+        if !leading {
+            break;
+        }
+        // Multi-line imports continue until the closing semicolon.
+        in_use = (in_use || trimmed.starts_with("use ")) && !trimmed.ends_with(';');
+        offset += line.len();
+        if !trimmed.is_empty() {
+            end = offset;
+        }
+    }
+    let (head, body) = main_rs.split_at(end);
+    let head = match head.is_empty() {
+        true => String::new(),
+        false => format!("{}\n", head),
+    };
+    format!("{}{}\n{}", head, allocator.global_allocator(), body.trim_start_matches('\n'))
+}
+
+/// Adds the [Allocator] to the binary crates of the directory, the members
+/// in `bin/` of a workspace or the package itself if it has a `src/main.rs`.
+///
+/// Each binary gets the optional allocator dependency, gated on the
+/// [Allocator::target], a feature enabled by default and the
+/// `#[global_allocator]` in its `main.rs`. A workspace declares the
+/// dependency version in its `[workspace.dependencies]`.
+///
+/// Returns the manifest paths of the binary crates.
+pub fn set_allocator(dir: &Path, allocator: Allocator) -> Result<Vec<PathBuf>> {
+    let manifest_path = dir.join("Cargo.toml");
+    let mut manifest = parse_manifest(&manifest_path)?;
+    let (name, version) = allocator.dependency();
+    let mut dependency = toml_edit::InlineTable::new();
+    let bins = match manifest.get("workspace").is_some() {
+        true => {
+            manifest["workspace"]["dependencies"][name] = toml_edit::value(version);
+            std::fs::write(&manifest_path, manifest.to_string())?;
+            dependency.insert("workspace", true.into());
+            member_manifests(dir)?
+                .into_iter()
+                .filter(|m| m.starts_with(dir.join("bin")))
+                .collect::<Vec<_>>()
+        }
+        false => {
+            dependency.insert("version", version.into());
+            vec![manifest_path]
+        }
+    };
+    dependency.insert("optional", true.into());
+
+    let mut wired = Vec::new();
+    for bin_manifest_path in bins {
+        let Some(bin_dir) = bin_manifest_path.parent() else { continue };
+        let main_rs_path = bin_dir.join("src").join("main.rs");
+        if !main_rs_path.exists() {
+            continue;
+        }
+        let mut bin_manifest = parse_manifest(&bin_manifest_path)?;
+        let dependencies = match allocator.target() {
+            Some(target) => {
+                if bin_manifest.get("target").is_none() {
+                    let mut targets = toml_edit::Table::new();
+                    targets.set_implicit(true);
+                    bin_manifest["target"] = toml_edit::Item::Table(targets);
+                }
+                if bin_manifest["target"].get(target).is_none() {
+                    let mut cfg = toml_edit::Table::new();
+                    cfg.set_implicit(true);
+                    bin_manifest["target"][target] = toml_edit::Item::Table(cfg);
+                }
+                &mut bin_manifest["target"][target]
+            }
+            None => bin_manifest.as_item_mut(),
+        };
+        if dependencies.get("dependencies").is_none() {
+            dependencies["dependencies"] = toml_edit::Item::Table(toml_edit::Table::new());
+        }
+        dependencies["dependencies"][name] = toml_edit::value(dependency.clone());
+
+        if bin_manifest.get("features").is_none() {
+            bin_manifest["features"] = toml_edit::Item::Table(toml_edit::Table::new());
+        }
+        let features = &mut bin_manifest["features"];
+        if features.get("default").is_none() {
+            features["default"] = toml_edit::value(toml_edit::Array::new());
+        }
+        if let Some(default) = features["default"].as_array_mut() {
+            default.push(allocator.feature());
+        }
+        let mut enables = toml_edit::Array::new();
+        enables.push(format!("dep:{}", name));
+        features[allocator.feature()] = toml_edit::value(enables);
+        std::fs::write(&bin_manifest_path, bin_manifest.to_string())?;
+
+        let main_rs = std::fs::read_to_string(&main_rs_path)?;
+        std::fs::write(&main_rs_path, with_global_allocator(&main_rs, allocator))?;
+        wired.push(bin_manifest_path);
+    }
+    Ok(wired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_with_global_allocator() {
+        let main_rs = "#![doc = \"example\"]\n\nuse clap::Parser;\n\n/// Args.\nstruct Args;\n";
+        assert_eq!(
+            with_global_allocator(main_rs, Allocator::Mimalloc),
+            "#![doc = \"example\"]\n\nuse clap::Parser;\n\n#[cfg(feature = \"mimalloc\")]\n\
+             #[global_allocator]\nstatic GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;\n\n\
+             /// Args.\nstruct Args;\n"
+        );
+        let main_rs = "use std::{\n    fs,\n    io,\n};\nfn main() {}\n";
+        let main_rs = with_global_allocator(main_rs, Allocator::Jemalloc);
+        assert!(main_rs.starts_with("use std::{\n    fs,\n    io,\n};\n\n#[cfg(all("));
+        let main_rs = with_global_allocator("fn main() {}\n", Allocator::Jemalloc);
+        assert!(main_rs.starts_with("#[cfg(all(feature = \"jemalloc\", not(windows)))]\n"));
+        assert!(main_rs.ends_with("jemallocator::Jemalloc;\n\nfn main() {}\n"));
+    }
+
+    #[test]
+    fn test_set_allocator() {
+        let dir = tempdir().unwrap();
+        let manifest = "[workspace]\nmembers = [\"bin/*\", \"crates/*\"]\n\n\
+                        [workspace.dependencies]\nanyhow = \"1.0\"\n";
+        std::fs::write(dir.path().join("Cargo.toml"), manifest).unwrap();
+        let bin = dir.path().join("bin").join("example");
+        std::fs::create_dir_all(bin.join("src")).unwrap();
+        std::fs::write(bin.join("Cargo.toml"), "[package]\nname = \"example\"\n").unwrap();
+        std::fs::write(bin.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        let lib = dir.path().join("crates").join("common");
+        std::fs::create_dir_all(lib.join("src")).unwrap();
+        std::fs::write(lib.join("Cargo.toml"), "[package]\nname = \"common\"\n").unwrap();
+
+        let wired = set_allocator(dir.path(), Allocator::Jemalloc).unwrap();
+        assert_eq!(wired, vec![bin.join("Cargo.toml")]);
+        let manifest = std::fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        assert!(manifest.ends_with("anyhow = \"1.0\"\njemallocator = \"0.5\"\n"));
+        let bin_manifest = std::fs::read_to_string(bin.join("Cargo.toml")).unwrap();
+        assert!(bin_manifest.ends_with(
+            "[target.\"cfg(not(windows))\".dependencies]\n\
+             jemallocator = { workspace = true, optional = true }\n\n\
+             [features]\ndefault = [\"jemalloc\"]\njemalloc = [\"dep:jemallocator\"]\n"
+        ));
+        let main_rs = std::fs::read_to_string(bin.join("src").join("main.rs")).unwrap();
+        assert!(main_rs.contains("#[global_allocator]\n"));
+
+        let package = tempdir().unwrap();
+        std::fs::write(package.path().join("Cargo.toml"), "[package]\nname = \"example\"\n")
+            .unwrap();
+        assert!(set_allocator(package.path(), Allocator::Mimalloc).unwrap().is_empty());
+        std::fs::create_dir_all(package.path().join("src")).unwrap();
+        std::fs::write(package.path().join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        set_allocator(package.path(), Allocator::Mimalloc).unwrap();
+        let manifest = std::fs::read_to_string(package.path().join("Cargo.toml")).unwrap();
+        let manifest = manifest.parse::<toml_edit::Document>().unwrap();
+        let mimalloc = &manifest["dependencies"]["mimalloc"];
+        assert_eq!(mimalloc["version"].as_str(), Some("0.1"));
+        assert_eq!(mimalloc["optional"].as_bool(), Some(true));
+    }
+}
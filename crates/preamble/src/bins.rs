@@ -1,16 +1,22 @@
 use std::{io::Write, path::Path};
 
-use anyhow::Result;
-use ptree::TreeBuilder;
+use crate::{error::Result, report::Report};
 use tracing::instrument;
 
+/// The template `main.rs` for workspace binary crates.
+///
+/// Parses a clap-derive cli with a `-v` verbosity count flag and a `--log-format`
+/// flag, initializes tracing through the `amble-telemetry` crate re-exported by
+/// `common` and calls into `common`.
+pub const MAIN_RS: &str = include_str!("../etc/templates/main.rs");
+
 /// Creates a new bin crate.
-#[instrument(name = "bin", skip(dir, name, dry, tree))]
+#[instrument(name = "bin", skip(dir, name, dry, report))]
 pub fn create(
     dir: &Path,
     name: impl AsRef<str>,
     dry: bool,
-    mut tree: Option<&mut TreeBuilder>,
+    mut report: Option<&mut Report>,
 ) -> Result<()> {
     tracing::info!("Creating binary crate");
 
@@ -21,27 +27,26 @@ pub fn create(
 
     crate::utils::create_dir_gracefully!(src_path_buf, dry);
 
-    tree.as_deref_mut().map(|t| t.begin_child("bin".to_string()));
-    tree.as_deref_mut().map(|t| t.begin_child(name.as_ref().to_string()));
+    report.as_deref_mut().map(|r| r.begin_dir("bin"));
+    report.as_deref_mut().map(|r| r.begin_dir(name.as_ref()));
 
     if !dry {
         tracing::debug!("Writing {:?}", cargo_toml_path_buf);
         fill_cargo(&cargo_toml_path_buf, name.as_ref())?;
     }
-    tree.as_deref_mut().map(|t| t.add_empty_child("Cargo.toml".to_string()));
-    tree.as_deref_mut().map(|t| t.begin_child("src".to_string()));
+    report.as_deref_mut().map(|r| r.created("Cargo.toml"));
+    report.as_deref_mut().map(|r| r.begin_dir("src"));
 
     if !dry {
         tracing::debug!("Writing {:?}", main_rs_path_buf);
         let mut main_rs = std::fs::File::create(&main_rs_path_buf)?;
-        let main_contents = "fn main() {\n    println!(\"Hello World!\");\n}\n";
-        main_rs.write_all(main_contents.as_bytes())?;
+        main_rs.write_all(MAIN_RS.as_bytes())?;
     }
-    tree.as_deref_mut().map(|t| t.add_empty_child("main.rs".to_string()));
+    report.as_deref_mut().map(|r| r.created("main.rs"));
 
-    tree.as_deref_mut().map(|t| t.end_child()); // <- src/
-    tree.as_deref_mut().map(|t| t.end_child()); // <- <name>/
-    tree.map(|t| t.end_child()); // <- bin/
+    report.as_deref_mut().map(|r| r.end_dir()); // <- src/
+    report.as_deref_mut().map(|r| r.end_dir()); // <- <name>/
+    report.map(|r| r.end_dir()); // <- bin/
 
     Ok(())
 }
@@ -70,16 +75,14 @@ pub fn fill_cargo(file: &Path, name: &str) -> Result<()> {
     manifest["dependencies"] = toml_edit::Item::Table(toml_edit::Table::new());
     manifest["dependencies"]["common"] = inline.clone();
     manifest["dependencies"]["common"]["path"] = toml_edit::value("../../crates/common");
+    // crates.io rejects path dependencies without a version.
+    manifest["dependencies"]["common"]["version"] = toml_edit::value("0.1.0");
     manifest["dependencies"]["clap"] = inline.clone();
     manifest["dependencies"]["clap"]["workspace"] = toml_edit::value(true);
     manifest["dependencies"]["anyhow"] = inline.clone();
     manifest["dependencies"]["anyhow"]["workspace"] = toml_edit::value(true);
-    manifest["dependencies"]["inquire"] = inline.clone();
-    manifest["dependencies"]["inquire"]["workspace"] = toml_edit::value(true);
     manifest["dependencies"]["tracing"] = inline.clone();
     manifest["dependencies"]["tracing"]["workspace"] = toml_edit::value(true);
-    manifest["dependencies"]["tracing-subscriber"] = inline.clone();
-    manifest["dependencies"]["tracing-subscriber"]["workspace"] = toml_edit::value(true);
 
     let mut file = std::fs::File::create(file)?;
     file.write_all(manifest.to_string().as_bytes())?;
@@ -117,12 +120,10 @@ repository = { workspace = true }
 homepage = { workspace = true }
 
 [dependencies]
-common = { path = "../../crates/common" }
+common = { path = "../../crates/common", version = "0.1.0" }
 clap = { workspace = true }
 anyhow = { workspace = true }
-inquire = { workspace = true }
 tracing = { workspace = true }
-tracing-subscriber = { workspace = true }
 "#;
         assert_eq!(cargo_toml_contents, expected_contents);
     }
@@ -144,8 +145,8 @@ tracing-subscriber = { workspace = true }
         let mut main_rs = File::open(project_path.join("src").join("main.rs")).unwrap();
         let mut main_rs_contents = String::new();
         main_rs.read_to_string(&mut main_rs_contents).unwrap();
-        let expected_contents = "fn main() {\n    println!(\"Hello World!\");\n}\n";
-        assert_eq!(main_rs_contents, expected_contents);
+        assert_eq!(main_rs_contents, MAIN_RS);
+        assert!(main_rs_contents.contains("Telemetry::new().verbosity(v).format(log_format)"));
     }
 
     #[test]
@@ -0,0 +1,992 @@
+use crate::{
+    allocator, bins, cargo, ci,
+    command::{CommandRunner, SystemRunner},
+    cross,
+    error::Result,
+    etc, git, gitignore, libs, license, lints, profiles,
+    report::{FileAction, Report},
+    root, supply_chain, tooling, updates,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+pub use crate::{
+    allocator::Allocator,
+    ci::Workflow,
+    cross::CrossConfig,
+    license::{Copyright, License},
+    lints::LintProfile,
+    pipeline::{CiConfig, CiJob},
+    profiles::ProfilePreset,
+    updates::UpdateBot,
+};
+
+/// A package author, formatted as `name <email>` in manifests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Author {
+    /// The author name or username.
+    pub name: String,
+    /// The author email.
+    pub email: Option<String>,
+}
+
+impl Author {
+    /// Creates a new [Author] without an email.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), email: None }
+    }
+
+    /// Sets the author email.
+    pub fn email(mut self, email: impl Into<String>) -> Self {
+        self.email = Some(email.into());
+        self
+    }
+}
+
+impl std::str::FromStr for Author {
+    type Err = String;
+
+    /// Parses an author from `name` or `name <email>`.
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        let (name, email) = match s.trim().split_once('<') {
+            Some((name, email)) => (name.trim(), Some(email.trim_end_matches('>').trim())),
+            None => (s.trim(), None),
+        };
+        if name.is_empty() {
+            return Err(format!("Invalid author \"{}\", expected `name` or `name <email>`", s));
+        }
+        Ok(Self { name: name.to_string(), email: email.map(|e| e.to_string()) })
+    }
+}
+
+impl std::fmt::Display for Author {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.email {
+            Some(email) => write!(f, "{} <{}>", self.name, email),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// A dependency added to the generated manifest.
+///
+/// Unpinned dependencies are resolved to their latest version with
+/// [root::resolve_version].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The package name.
+    pub name: String,
+    /// The pinned version requirement.
+    pub version: Option<String>,
+    /// The enabled features.
+    pub features: Vec<String>,
+}
+
+impl Dependency {
+    /// Creates a new, unpinned [Dependency].
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), version: None, features: Vec::new() }
+    }
+
+    /// Pins the version requirement.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Sets the enabled features.
+    pub fn features(mut self, features: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.features = features.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns the manifest item for the dependency, resolving its version
+    /// if it isn't pinned.
+    pub fn resolve(
+        &self,
+        runner: &dyn CommandRunner,
+        report: Option<&mut Report>,
+    ) -> toml_edit::Item {
+        let version = match &self.version {
+            Some(version) => version.clone(),
+            None => root::resolve_version(&self.name, "0.0.0", runner, report),
+        };
+        if self.features.is_empty() {
+            return toml_edit::value(version);
+        }
+        let mut table = toml_edit::InlineTable::new();
+        table.insert("version", version.into());
+        table.insert("features", self.features.iter().collect::<toml_edit::Array>().into());
+        toml_edit::value(table)
+    }
+}
+
+impl std::str::FromStr for Dependency {
+    type Err = String;
+
+    /// Parses a dependency from `name` or `name@version`.
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s.trim().split_once('@') {
+            Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+                Ok(Self::new(name).version(version))
+            }
+            None if !s.trim().is_empty() => Ok(Self::new(s.trim())),
+            _ => Err(format!("Invalid dependency \"{}\", expected `name` or `name@version`", s)),
+        }
+    }
+}
+
+/// Optional project files shared by the [WorkspaceBuilder] and [CrateBuilder].
+#[derive(Debug, Default)]
+struct Batteries {
+    license: Option<License>,
+    gitignore: bool,
+    git: Option<Option<String>>,
+    etc: bool,
+    assets: bool,
+    ci: bool,
+    ci_yml: Vec<String>,
+    workflows: Option<Vec<Workflow>>,
+    ci_config: CiConfig,
+    rust_version: Option<String>,
+    license_headers: bool,
+    organization: Option<String>,
+    cross_release: Option<CrossConfig>,
+    dependency_updates: Option<UpdateBot>,
+    deny: bool,
+    cargo_vet: bool,
+    tooling: bool,
+    lints: LintProfile,
+    profiles: Vec<ProfilePreset>,
+    allocator: Option<Allocator>,
+}
+
+impl Batteries {
+    /// Returns the [Copyright] held by the authors and organization.
+    fn copyright(&self, authors: &Option<Vec<String>>, runner: &dyn CommandRunner) -> Copyright {
+        let holders =
+            authors.clone().unwrap_or_else(|| vec![root::get_current_username(&None, runner)]);
+        Copyright::new(&Some(holders)).organization(self.organization.clone())
+    }
+
+    /// Returns if any github action workflows are written.
+    fn has_ci(&self) -> bool {
+        self.ci || !self.ci_yml.is_empty() || self.workflows.is_some()
+    }
+
+    /// Returns the manifest license, defaulting to MIT without a license file.
+    fn manifest_license(&self) -> License {
+        self.license.clone().unwrap_or_default()
+    }
+
+    /// Creates the files that precede the project scaffold, updating the
+    /// license to the one that was written.
+    fn create_before(
+        &mut self,
+        dir: &Path,
+        copyright: &Copyright,
+        dry: bool,
+        runner: &dyn CommandRunner,
+        report: &mut Report,
+    ) -> Result<()> {
+        if let Some(l) = &self.license {
+            // Keep the manifest in sync with the license that was written.
+            self.license = Some(license::create(dir, l, copyright, dry, Some(report))?);
+        }
+        if self.gitignore {
+            gitignore::create(dir, dry, Some(report))?;
+        }
+        if let Some(user) = &self.git {
+            git::create(dir, dry, user.clone(), runner, Some(report))?;
+        }
+        if self.etc {
+            etc::create(dir, dry, self.assets, Some(report))?;
+        }
+        Ok(())
+    }
+
+    /// Sets the rust version, lints, profiles and allocator in the scaffolded
+    /// manifests.
+    fn set_manifest(&self, dir: &Path, dry: bool, report: &mut Report) -> Result<()> {
+        if let Some(rust_version) = &self.rust_version {
+            if !lints::supported(rust_version) {
+                let warning = format!(
+                    "The [lints] manifest table needs rust 1.74, rust {} ignores the {} lints",
+                    rust_version, self.lints
+                );
+                tracing::warn!("{}", warning);
+                report.warn(warning);
+            }
+        }
+        if dry {
+            return Ok(());
+        }
+        if let Some(rust_version) = &self.rust_version {
+            root::set_rust_version(dir, rust_version)?;
+        }
+        // A bare crate's manifest is written by the `cargo init` command.
+        if dir.join("Cargo.toml").exists() {
+            lints::set_lints(dir, self.lints)?;
+            if !self.profiles.is_empty() {
+                profiles::set_profiles(dir, &self.profiles)?;
+            }
+            if let Some(allocator) = self.allocator {
+                if allocator::set_allocator(dir, allocator)?.is_empty() {
+                    let warning =
+                        format!("There is no binary crate to use the {} allocator", allocator);
+                    tracing::warn!("{}", warning);
+                    report.warn(warning);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the files that follow the project scaffold.
+    fn create_after(
+        &self,
+        dir: &Path,
+        bins: &[String],
+        copyright: &Copyright,
+        context: &HashMap<&str, String>,
+        dry: bool,
+        report: &mut Report,
+    ) -> Result<()> {
+        let mut config = match dry {
+            true => self.ci_config.clone(),
+            false => self.ci_config.clone().workspace(dir)?,
+        };
+        if let Some(rust_version) = &self.rust_version {
+            config.msrv = Some(rust_version.clone());
+        }
+        if self.deny && !config.jobs.contains(&CiJob::Deny) {
+            config.jobs.push(CiJob::Deny);
+        }
+        if self.tooling {
+            // The ci workflow overrides the pinned toolchain and formats on nightly.
+            config.toolchain_file = true;
+            config.nightly_rustfmt = true;
+            tooling::create(dir, &config, dry, Some(report))?;
+        }
+        if self.has_ci() {
+            let workflows = self.workflows.as_deref();
+            ci::create(dir, dry, &self.ci_yml, workflows, &config, context, Some(report))?;
+        }
+        if let Some(cross_release) = &self.cross_release {
+            let pins = self.ci_config.pins.as_ref();
+            let provider = self.ci_config.provider;
+            cross::create(dir, cross_release, bins, pins, provider, dry, Some(report))?;
+        }
+        if self.deny {
+            supply_chain::create_deny(dir, &self.manifest_license(), dry, Some(report))?;
+        }
+        if self.cargo_vet {
+            supply_chain::create_vet(dir, dry, Some(report))?;
+        }
+        if let Some(bot) = self.dependency_updates {
+            updates::create(dir, bot, dry, Some(report))?;
+        }
+        if self.license_headers && !dry {
+            // Only the generated sources get headers, existing files are left as is.
+            let files = report
+                .files
+                .iter()
+                .filter(|f| f.action == FileAction::Created && !f.directory)
+                .filter(|f| f.path.extension().is_some_and(|e| e == "rs"))
+                .map(|f| f.path.clone())
+                .collect::<Vec<_>>();
+            license::add_license_headers(dir, &files, &self.manifest_license(), copyright, dry)?;
+        }
+        Ok(())
+    }
+}
+
+/// Expands the setters shared by the [WorkspaceBuilder] and [CrateBuilder],
+/// most of which set their [Batteries].
+macro_rules! shared_setters {
+    () => {
+        /// Sets the project description.
+        pub fn description(mut self, description: impl Into<String>) -> Self {
+            self.description = Some(description.into());
+            self
+        }
+
+        /// Adds an author. Defaults to the git or system username if none are added.
+        pub fn author(mut self, author: Author) -> Self {
+            self.authors.push(author);
+            self
+        }
+
+        /// Adds the given authors.
+        pub fn authors(mut self, authors: impl IntoIterator<Item = Author>) -> Self {
+            self.authors.extend(authors);
+            self
+        }
+
+        /// Adds a dependency alongside the default dependencies.
+        pub fn dependency(mut self, dependency: Dependency) -> Self {
+            self.dependencies.push(dependency);
+            self
+        }
+
+        /// Adds the given dependencies.
+        pub fn dependencies(mut self, dependencies: impl IntoIterator<Item = Dependency>) -> Self {
+            self.dependencies.extend(dependencies);
+            self
+        }
+
+        /// Adds license files for the given SPDX license expression and sets the
+        /// manifest `license` field. The manifest defaults to MIT otherwise.
+        pub fn license(mut self, license: License) -> Self {
+            self.batteries.license = Some(license);
+            self
+        }
+
+        /// Whether to add a rust `.gitignore` file.
+        pub fn gitignore(mut self, gitignore: bool) -> Self {
+            self.batteries.gitignore = gitignore;
+            self
+        }
+
+        /// Initializes a git repository with a github remote for the given user,
+        /// falling back to the git username if `None`.
+        pub fn git(mut self, user: Option<String>) -> Self {
+            self.batteries.git = Some(user);
+            self
+        }
+
+        /// Whether to add an `etc/` directory.
+        pub fn etc(mut self, etc: bool) -> Self {
+            self.batteries.etc = etc;
+            self
+        }
+
+        /// Whether to add template assets to the `etc/` directory, if enabled.
+        pub fn assets(mut self, assets: bool) -> Self {
+            self.batteries.assets = assets;
+            self
+        }
+
+        /// Whether to add the default github action workflows.
+        pub fn ci(mut self, ci: bool) -> Self {
+            self.batteries.ci = ci;
+            self
+        }
+
+        /// Adds a custom workflow file, directory of workflow files or url, written
+        /// under its own name in place of the bundled workflows unless some are
+        /// selected with `workflows`. Files ending in `.tmpl` are rendered with
+        /// the `{projectname}`, `{projectdescription}` and `{projectowner}` variables.
+        pub fn ci_yml(mut self, ci_yml: impl Into<String>) -> Self {
+            self.batteries.ci_yml.push(ci_yml.into());
+            self
+        }
+
+        /// Sets the toolchains, operating systems and jobs of the generated ci workflow.
+        pub fn ci_config(mut self, ci_config: CiConfig) -> Self {
+            self.batteries.ci_config = ci_config;
+            self
+        }
+
+        /// Sets the manifest `rust-version`, which the ci workflow tests as the MSRV.
+        pub fn rust_version(mut self, rust_version: impl Into<String>) -> Self {
+            self.batteries.rust_version = Some(rust_version.into());
+            self
+        }
+
+        /// Writes only the given workflows instead of all [ci::WORKFLOWS].
+        pub fn workflows(mut self, workflows: impl IntoIterator<Item = Workflow>) -> Self {
+            self.batteries.workflows = Some(workflows.into_iter().collect());
+            self
+        }
+
+        /// Sets the organization named by licenses with an `<organization>` placeholder.
+        pub fn organization(mut self, organization: impl Into<String>) -> Self {
+            self.batteries.organization = Some(organization.into());
+            self
+        }
+
+        /// Whether to prepend a SPDX license header to the generated `.rs` files.
+        pub fn license_headers(mut self, license_headers: bool) -> Self {
+            self.batteries.license_headers = license_headers;
+            self
+        }
+
+        /// Adds a cross-compiled release workflow and `Makefile` or `justfile`
+        /// for the binary crate.
+        pub fn cross_release(mut self, cross_release: CrossConfig) -> Self {
+            self.batteries.cross_release = Some(cross_release);
+            self
+        }
+
+        /// Adds a dependabot or renovate config keeping the dependencies and
+        /// github actions current, grouping the dependencies of the manifest.
+        pub fn dependency_updates(mut self, bot: UpdateBot) -> Self {
+            self.batteries.dependency_updates = Some(bot);
+            self
+        }
+
+        /// Whether to add a `deny.toml` allowing the licenses compatible with the
+        /// project license, and a `cargo deny` job to the ci workflow.
+        pub fn deny(mut self, deny: bool) -> Self {
+            self.batteries.deny = deny;
+            self
+        }
+
+        /// Whether to add a `supply-chain/` directory for `cargo vet`.
+        pub fn cargo_vet(mut self, cargo_vet: bool) -> Self {
+            self.batteries.cargo_vet = cargo_vet;
+            self
+        }
+
+        /// Whether to add a `rustfmt.toml`, `clippy.toml`, `rust-toolchain.toml`
+        /// and `.editorconfig` matching the toolchain and MSRV of the ci workflow.
+        pub fn tooling(mut self, tooling: bool) -> Self {
+            self.batteries.tooling = tooling;
+            self
+        }
+
+        /// Sets the [LintProfile] written to the manifest.
+        pub fn lints(mut self, lints: LintProfile) -> Self {
+            self.batteries.lints = lints;
+            self
+        }
+
+        /// Sets the global [Allocator] of the binaries, enabled by a default feature.
+        pub fn allocator(mut self, allocator: Allocator) -> Self {
+            self.batteries.allocator = Some(allocator);
+            self
+        }
+
+        /// Adds a [ProfilePreset] to the root manifest.
+        pub fn profile(mut self, preset: ProfilePreset) -> Self {
+            if !self.batteries.profiles.contains(&preset) {
+                self.batteries.profiles.push(preset);
+            }
+            self
+        }
+
+        /// Overwrites conflicting files without the [crate::utils::check_artifacts]
+        /// confirmation.
+        pub fn overwrite(mut self, overwrite: bool) -> Self {
+            self.overwrite = overwrite;
+            self
+        }
+
+        /// Plans the project without writing files or executing commands.
+        pub fn dry_run(mut self, dry_run: bool) -> Self {
+            self.dry_run = dry_run;
+            self
+        }
+
+        /// Sets the [CommandRunner] used to execute `cargo` and `git`.
+        pub fn runner(mut self, runner: impl CommandRunner + 'static) -> Self {
+            self.runner = Box::new(runner);
+            self
+        }
+    };
+}
+
+/// Builds a cargo workspace with a binary crate in `bin/` and a
+/// `common` library crate in `crates/`.
+///
+/// ```no_run
+/// use preamble::builder::{Author, Dependency, License, WorkspaceBuilder};
+///
+/// let report = WorkspaceBuilder::new("my-project", "example")
+///     .description("An example workspace")
+///     .author(Author::new("refcell"))
+///     .license(License::new("Apache-2.0"))
+///     .dependency(Dependency::new("serde").version("1.0").features(["derive"]))
+///     .ci(true)
+///     .dry_run(true)
+///     .build()
+///     .unwrap();
+/// ptree::print_tree(&report.tree()).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct WorkspaceBuilder {
+    dir: PathBuf,
+    name: String,
+    description: Option<String>,
+    authors: Vec<Author>,
+    dependencies: Vec<Dependency>,
+    readme: bool,
+    overwrite: bool,
+    dry_run: bool,
+    batteries: Batteries,
+    runner: Box<dyn CommandRunner>,
+}
+
+impl WorkspaceBuilder {
+    /// Creates a new [WorkspaceBuilder] for the given project directory and binary name.
+    pub fn new(dir: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            name: name.into(),
+            description: None,
+            authors: Vec::new(),
+            dependencies: Vec::new(),
+            readme: true,
+            overwrite: false,
+            dry_run: false,
+            batteries: Batteries::default(),
+            runner: Box::<SystemRunner>::default(),
+        }
+    }
+
+    /// Whether to generate a readme. Defaults to `true`.
+    pub fn readme(mut self, readme: bool) -> Self {
+        self.readme = readme;
+        self
+    }
+
+    shared_setters!();
+
+    /// Builds the workspace, returning the [Report] of planned files
+    /// in dry run mode or applied files otherwise.
+    pub fn build(self) -> Result<Report> {
+        let mut report = Report::new(self.dir.display().to_string(), self.dry_run);
+        self.build_into(&mut report)?;
+        Ok(report)
+    }
+
+    /// Builds the workspace, recording the planned or applied files into the
+    /// given [Report], which keeps everything recorded before an error.
+    pub fn build_into(mut self, report: &mut Report) -> Result<()> {
+        let dir = self.dir.as_path();
+        if !self.overwrite {
+            crate::utils::check_artifacts(dir, self.batteries.has_ci(), self.dry_run)?;
+        }
+        crate::utils::create_dir_gracefully!(dir, self.dry_run);
+        let authors = authors(self.authors);
+        let copyright = self.batteries.copyright(&authors, self.runner.as_ref());
+        let context =
+            template_context(&self.name, &self.description, &authors, self.runner.as_ref());
+        self.batteries.create_before(
+            dir,
+            &copyright,
+            self.dry_run,
+            self.runner.as_ref(),
+            report,
+        )?;
+        root::create(
+            dir,
+            &self.name,
+            self.description.as_ref(),
+            self.dry_run,
+            !self.readme,
+            authors,
+            &self.batteries.manifest_license(),
+            dependencies(self.dependencies),
+            self.runner.as_ref(),
+            Some(report),
+        )?;
+        bins::create(&dir.join("bin"), &self.name, self.dry_run, Some(report))?;
+        libs::create(&dir.join("crates"), "common", self.dry_run, Some(report))?;
+        self.batteries.set_manifest(dir, self.dry_run, report)?;
+        let bins = [self.name.clone()];
+        self.batteries.create_after(dir, &bins, &copyright, &context, self.dry_run, report)?;
+        Ok(())
+    }
+}
+
+/// The kind of crate built by a [CrateBuilder].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrateKind {
+    /// A binary crate, initialized with `cargo init --bin`.
+    Bin,
+    /// A library crate, initialized with `cargo init --lib`.
+    Lib,
+}
+
+/// Builds a standalone binary or library crate with `cargo init`.
+///
+/// ```no_run
+/// use preamble::builder::{CrateBuilder, License};
+///
+/// let report = CrateBuilder::lib("my-lib", "my-lib")
+///     .description("An example library")
+///     .license(License::default())
+///     .gitignore(true)
+///     .build()
+///     .unwrap();
+/// println!("{}", report.to_json().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct CrateBuilder {
+    kind: CrateKind,
+    dir: PathBuf,
+    name: String,
+    description: Option<String>,
+    authors: Vec<Author>,
+    dependencies: Vec<Dependency>,
+    bare: bool,
+    overwrite: bool,
+    dry_run: bool,
+    batteries: Batteries,
+    runner: Box<dyn CommandRunner>,
+}
+
+impl CrateBuilder {
+    /// Creates a new [CrateBuilder] for the given [CrateKind], directory and crate name.
+    pub fn new(kind: CrateKind, dir: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self {
+            kind,
+            dir: dir.into(),
+            name: name.into(),
+            description: None,
+            authors: Vec::new(),
+            dependencies: Vec::new(),
+            bare: false,
+            overwrite: false,
+            dry_run: false,
+            batteries: Batteries::default(),
+            runner: Box::<SystemRunner>::default(),
+        }
+    }
+
+    /// Creates a new [CrateBuilder] for a binary crate.
+    pub fn bin(dir: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self::new(CrateKind::Bin, dir, name)
+    }
+
+    /// Creates a new [CrateBuilder] for a library crate.
+    pub fn lib(dir: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self::new(CrateKind::Lib, dir, name)
+    }
+
+    /// Only keeps the files generated by `cargo init`.
+    pub fn bare(mut self, bare: bool) -> Self {
+        self.bare = bare;
+        self
+    }
+
+    shared_setters!();
+
+    /// Builds the crate, returning the [Report] of planned files
+    /// in dry run mode or applied files otherwise.
+    pub fn build(self) -> Result<Report> {
+        let mut report = Report::new(self.dir.display().to_string(), self.dry_run);
+        self.build_into(&mut report)?;
+        Ok(report)
+    }
+
+    /// Builds the crate, recording the planned or applied files into the
+    /// given [Report], which keeps everything recorded before an error.
+    pub fn build_into(mut self, report: &mut Report) -> Result<()> {
+        let dir = self.dir.as_path();
+        if !self.overwrite {
+            crate::utils::check_artifacts(dir, self.batteries.has_ci(), self.dry_run)?;
+        }
+        crate::utils::create_dir_gracefully!(dir, self.dry_run);
+        let authors = authors(self.authors);
+        let copyright = self.batteries.copyright(&authors, self.runner.as_ref());
+        let context =
+            template_context(&self.name, &self.description, &authors, self.runner.as_ref());
+        self.batteries.create_before(
+            dir,
+            &copyright,
+            self.dry_run,
+            self.runner.as_ref(),
+            report,
+        )?;
+        let create = match self.kind {
+            CrateKind::Bin => cargo::create_bin,
+            CrateKind::Lib => cargo::create_lib,
+        };
+        create(
+            dir,
+            &self.name,
+            self.description.as_ref(),
+            self.dry_run,
+            self.bare,
+            authors,
+            &self.batteries.manifest_license(),
+            dependencies(self.dependencies),
+            self.runner.as_ref(),
+            Some(report),
+        )?;
+        self.batteries.set_manifest(dir, self.dry_run, report)?;
+        let bins = match self.kind {
+            CrateKind::Bin => vec![self.name.clone()],
+            CrateKind::Lib => Vec::new(),
+        };
+        self.batteries.create_after(dir, &bins, &copyright, &context, self.dry_run, report)?;
+        Ok(())
+    }
+}
+
+/// Returns the project variables custom workflow templates are rendered with.
+fn template_context(
+    name: &str,
+    description: &Option<String>,
+    authors: &Option<Vec<String>>,
+    runner: &dyn CommandRunner,
+) -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("projectname", name.to_string()),
+        ("projectdescription", description.clone().unwrap_or_default()),
+        ("projectowner", root::get_current_username(authors, runner)),
+    ])
+}
+
+/// Converts typed authors to the manifest author strings, `None` if empty.
+fn authors(authors: Vec<Author>) -> Option<Vec<String>> {
+    match authors.is_empty() {
+        true => None,
+        false => Some(authors.iter().map(|a| a.to_string()).collect()),
+    }
+}
+
+/// Returns the dependencies, `None` if empty.
+fn dependencies(dependencies: Vec<Dependency>) -> Option<Vec<Dependency>> {
+    match dependencies.is_empty() {
+        true => None,
+        false => Some(dependencies),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{tests::MockRunner, Tool};
+    use chrono::Datelike;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_author() {
+        let author = "refcell <refcell@example.com>".parse::<Author>().unwrap();
+        assert_eq!(author, Author::new("refcell").email("refcell@example.com"));
+        assert_eq!(author.to_string(), "refcell <refcell@example.com>");
+        assert_eq!("refcell".parse::<Author>().unwrap(), Author::new("refcell"));
+        assert!(" <refcell@example.com>".parse::<Author>().is_err());
+    }
+
+    #[test]
+    fn test_parse_dependency() {
+        assert_eq!("serde".parse::<Dependency>().unwrap(), Dependency::new("serde"));
+        assert_eq!(
+            "serde@1.0".parse::<Dependency>().unwrap(),
+            Dependency::new("serde").version("1.0")
+        );
+        assert!("serde@".parse::<Dependency>().is_err());
+        assert!("".parse::<Dependency>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_pinned_dependency() {
+        let mut report = Report::new("example", true);
+        let item = Dependency::new("serde").version("1.0").features(["derive"]);
+        let item = item.resolve(&MockRunner::default(), Some(&mut report));
+        assert_eq!(item.to_string(), r#"{ version = "1.0", features = ["derive"] }"#);
+        // Pinned dependencies never query crates.io.
+        assert!(report.fetches.is_empty());
+    }
+
+    #[test]
+    fn test_workspace_builder_dry_run() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        let report = WorkspaceBuilder::new(&project, "example")
+            .readme(false)
+            .gitignore(true)
+            .ci(true)
+            .dry_run(true)
+            .build()
+            .unwrap();
+
+        assert!(report.dry_run);
+        assert!(!project.exists());
+        let paths = report.files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths[0], PathBuf::from(".gitignore"));
+        assert_eq!(report.files[1].path, PathBuf::from("README.md"));
+        assert_eq!(report.files[1].action, FileAction::Skipped);
+        assert!(paths.contains(&PathBuf::from("bin/example/src/main.rs")));
+        assert!(paths.contains(&PathBuf::from("crates/common/src/lib.rs")));
+        assert!(paths.contains(&PathBuf::from(".github/workflows/ci.yml")));
+    }
+
+    #[test]
+    fn test_crate_builder() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        let report = CrateBuilder::bin(&project, "example")
+            .bare(true)
+            .git(Some("refcell".to_string()))
+            .runner(MockRunner::default())
+            .build()
+            .unwrap();
+
+        assert!(project.exists());
+        let commands = report.commands.iter().map(|c| c.args.join(" ")).collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            vec![
+                "init -b main",
+                "remote add origin https://github.com/refcell/example.git",
+                "init --bin",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_crate_builder_into_failed_report() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        let mut report = Report::new("example", false);
+        let result = CrateBuilder::bin(&project, "example")
+            .bare(true)
+            .git(Some("refcell".to_string()))
+            .runner(MockRunner { fail: Some(Tool::Cargo), ..Default::default() })
+            .build_into(&mut report);
+
+        assert!(result.is_err());
+        // The git commands run before the failed `cargo init` are kept.
+        let commands = report.commands.iter().map(|c| c.args.join(" ")).collect::<Vec<_>>();
+        assert_eq!(
+            commands[..2],
+            ["init -b main", "remote add origin https://github.com/refcell/example.git"]
+        );
+        assert_eq!(report.commands.len(), 3);
+    }
+
+    #[test]
+    fn test_workspace_builder_dual_license() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        WorkspaceBuilder::new(&project, "example")
+            .author(Author::new("refcell"))
+            .license("mit or apache-2.0".parse().unwrap())
+            .build()
+            .unwrap();
+
+        assert!(project.join("LICENSE-MIT").exists());
+        assert!(project.join("LICENSE-APACHE").exists());
+        let manifest = std::fs::read_to_string(project.join("Cargo.toml")).unwrap();
+        assert!(manifest.contains(r#"license = "MIT OR Apache-2.0""#));
+        let readme = std::fs::read_to_string(project.join("README.md")).unwrap();
+        assert!(readme.contains("[license-link]: #license"));
+        assert!(readme.contains("- [MIT License](LICENSE-MIT)"));
+    }
+
+    #[test]
+    fn test_workspace_builder_license_headers() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        WorkspaceBuilder::new(&project, "example")
+            .license(License::new("Apache-2.0"))
+            .license_headers(true)
+            .build()
+            .unwrap();
+
+        let main_rs = std::fs::read_to_string(project.join("bin/example/src/main.rs")).unwrap();
+        assert!(main_rs.starts_with("// SPDX-License-Identifier: Apache-2.0\n"));
+        let lib_rs = std::fs::read_to_string(project.join("crates/common/src/lib.rs")).unwrap();
+        assert!(lib_rs.starts_with("// SPDX-License-Identifier: Apache-2.0\n"));
+    }
+
+    #[test]
+    fn test_crate_builder_copyright_authors() {
+        let dir = tempdir().unwrap();
+        let report = CrateBuilder::bin(dir.path(), "example")
+            .author("refcell <r@example.com>".parse().unwrap())
+            .license(License::new("bsd-3-clause"))
+            .runner(MockRunner::default())
+            .bare(true)
+            .build()
+            .unwrap();
+
+        let license = std::fs::read_to_string(dir.path().join("LICENSE")).unwrap();
+        let year = chrono::Utc::now().year();
+        assert!(license.starts_with(&format!("Copyright (c) {} refcell.", year)));
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_workspace_builder_rust_version() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        WorkspaceBuilder::new(&project, "example")
+            .rust_version("1.70")
+            .workflows(["ci".parse().unwrap()])
+            .build()
+            .unwrap();
+
+        let manifest = std::fs::read_to_string(project.join("Cargo.toml")).unwrap();
+        assert!(manifest.contains("rust-version = \"1.70\""));
+        let ci_yml = std::fs::read_to_string(project.join(".github/workflows/ci.yml")).unwrap();
+        let ci_yml = serde_yaml::from_str::<serde_yaml::Value>(&ci_yml).unwrap();
+        let toolchains = &ci_yml["jobs"]["test"]["strategy"]["matrix"]["toolchain"];
+        assert_eq!(
+            *toolchains,
+            serde_yaml::from_str::<serde_yaml::Value>("[stable, '1.70']").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_workspace_builder_lints() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        let report = WorkspaceBuilder::new(&project, "example")
+            .lints(LintProfile::Strict)
+            .rust_version("1.70")
+            .build()
+            .unwrap();
+
+        let manifest = std::fs::read_to_string(project.join("Cargo.toml")).unwrap();
+        let manifest = manifest.parse::<toml_edit::Document>().unwrap();
+        let lints = &manifest["workspace"]["lints"];
+        assert_eq!(lints["rust"]["missing_docs"].as_str(), Some("deny"));
+        assert_eq!(lints["clippy"]["todo"].as_str(), Some("deny"));
+        for member in ["bin/example", "crates/common"] {
+            let member = std::fs::read_to_string(project.join(member).join("Cargo.toml")).unwrap();
+            assert!(member.ends_with("[lints]\nworkspace = true\n"));
+        }
+        let lib_rs = std::fs::read_to_string(project.join("crates/common/src/lib.rs")).unwrap();
+        assert!(!lib_rs.contains("#![warn"));
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_workspace_builder_profiles() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        WorkspaceBuilder::new(&project, "example")
+            .profile(ProfilePreset::MaxPerf)
+            .profile(ProfilePreset::Ci)
+            .profile(ProfilePreset::MaxPerf)
+            .build()
+            .unwrap();
+
+        let manifest = std::fs::read_to_string(project.join("Cargo.toml")).unwrap();
+        assert!(manifest.contains("[profile.dev]\nopt-level = 1\n"));
+        assert_eq!(manifest.matches("[profile.maxperf]").count(), 1);
+        let manifest = manifest.parse::<toml_edit::Document>().unwrap();
+        assert_eq!(manifest["profile"]["ci"]["incremental"].as_bool(), Some(false));
+    }
+
+    #[test]
+    fn test_crate_builder_allocator() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("example");
+        // The mocked `cargo init` leaves the manifest to the test.
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"example\"\n").unwrap();
+        let report = CrateBuilder::lib(&project, "example")
+            .bare(true)
+            .overwrite(true)
+            .allocator(Allocator::Mimalloc)
+            .runner(MockRunner::default())
+            .build()
+            .unwrap();
+        assert_eq!(report.warnings, ["There is no binary crate to use the mimalloc allocator"]);
+
+        let project = dir.path().join("workspace");
+        WorkspaceBuilder::new(&project, "example").allocator(Allocator::Jemalloc).build().unwrap();
+        let main_rs = std::fs::read_to_string(project.join("bin/example/src/main.rs")).unwrap();
+        assert!(main_rs.contains("static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;"));
+    }
+}
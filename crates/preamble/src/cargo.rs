@@ -1,11 +1,16 @@
-use anyhow::Result;
-use ptree::TreeBuilder;
+use crate::{
+    builder::Dependency,
+    command::{CommandRunner, Tool},
+    error::Result,
+    license::License,
+    report::Report,
+};
 use std::{io::Write, path::Path};
 use tracing::instrument;
 
 /// Creates a new cargo binary project in the specified directory.
 #[allow(clippy::too_many_arguments)]
-#[instrument(name = "bin", skip(dir, name, description, dry, bare, author, tree))]
+#[instrument(name = "bin", skip(dir, name, description, dry, bare, author, runner, report))]
 pub fn create_bin(
     dir: &Path,
     name: impl AsRef<str> + std::fmt::Display,
@@ -13,19 +18,15 @@ pub fn create_bin(
     dry: bool,
     bare: bool,
     author: Option<Vec<String>>,
-    overrides: Option<Vec<String>>,
-    mut tree: Option<&mut TreeBuilder>,
+    license: &License,
+    overrides: Option<Vec<Dependency>>,
+    runner: &dyn CommandRunner,
+    mut report: Option<&mut Report>,
 ) -> Result<()> {
     crate::utils::create_dir_gracefully!(dir, dry);
     if !dry {
         // Execute the `cargo init --bin` command in the given directory.
-        tracing::debug!("Executing `cargo init --bin` in {:?}", dir);
-        let output = std::process::Command::new("cargo")
-            .arg("init")
-            .arg("--bin")
-            .current_dir(dir)
-            .output()?;
-        tracing::debug!("cargo init --bin output: {:?}", output);
+        runner.run(Tool::Cargo, &["init", "--bin"], dir, report.as_deref_mut())?;
     }
     if !dry && !bare {
         tracing::debug!("Filling cargo contents in {:?}", dir);
@@ -34,24 +35,31 @@ pub fn create_bin(
             author,
             name.as_ref(),
             &description.map(|d| d.to_string()).unwrap_or_else(|| "A new binary crate".to_string()),
+            license,
             overrides,
+            runner,
+            report.as_deref_mut(),
         )?;
         tracing::debug!("Finished filling cargo contents in {:?}", dir);
     }
-    tree.as_deref_mut().map(|t| t.add_empty_child("Cargo.toml".to_string()));
-    tree.as_deref_mut().map(|t| t.begin_child("src".to_string()));
-    tree.as_deref_mut().map(|t| t.add_empty_child("main.rs".to_string()));
-    tree.map(|t| t.end_child());
+    report.as_deref_mut().map(|r| r.created("Cargo.toml"));
+    report.as_deref_mut().map(|r| r.begin_dir("src"));
+    report.as_deref_mut().map(|r| r.created("main.rs"));
+    report.map(|r| r.end_dir());
     Ok(())
 }
 
 /// Writes to the binary `Cargo.toml` file located at [file].
+#[allow(clippy::too_many_arguments)]
 pub fn write_cargo_bin(
     file: &Path,
     author: Option<Vec<String>>,
     name: &str,
     description: &str,
-    overrides: Option<Vec<String>>,
+    license: &License,
+    overrides: Option<Vec<Dependency>>,
+    runner: &dyn CommandRunner,
+    report: Option<&mut Report>,
 ) -> Result<()> {
     let mut manifest = toml_edit::Document::new();
     manifest["package"] = toml_edit::Item::Table(toml_edit::Table::new());
@@ -59,15 +67,15 @@ pub fn write_cargo_bin(
     manifest["package"]["description"] = toml_edit::value(description);
     manifest["package"]["version"] = toml_edit::value("0.1.0");
     manifest["package"]["edition"] = toml_edit::value("2021");
-    manifest["package"]["license"] = toml_edit::value("MIT");
-    let user = crate::root::get_current_username(&author);
-    manifest["package"]["authors"] = crate::root::get_authors(author);
+    manifest["package"]["license"] = toml_edit::value(license.expression());
+    let user = crate::root::get_current_username(&author, runner);
+    manifest["package"]["authors"] = crate::root::get_authors(author, runner);
     manifest["package"]["repository"] =
         toml_edit::value(format!("https://github.com/{}/{}", user, name));
     manifest["package"]["homepage"] =
         toml_edit::value(format!("https://github.com/{}/{}", user, name));
 
-    add_inline_deps(&mut manifest, overrides);
+    add_inline_deps(&mut manifest, overrides, runner, report);
 
     let mut file = std::fs::File::create(file)?;
     file.write_all(manifest.to_string().as_bytes())?;
@@ -76,7 +84,12 @@ pub fn write_cargo_bin(
 }
 
 /// Add dependencies to the manifest.
-pub fn add_inline_deps(manifest: &mut toml_edit::Document, overrides: Option<Vec<String>>) {
+pub fn add_inline_deps(
+    manifest: &mut toml_edit::Document,
+    overrides: Option<Vec<Dependency>>,
+    runner: &dyn CommandRunner,
+    mut report: Option<&mut Report>,
+) {
     let default_inline_dependencies = vec![
         ("anyhow".to_string(), "1.0".to_string()),
         ("inquire".to_string(), "0.6".to_string()),
@@ -86,25 +99,19 @@ pub fn add_inline_deps(manifest: &mut toml_edit::Document, overrides: Option<Vec
         ("tracing-subscriber".to_string(), "0.3".to_string()),
         ("clap".to_string(), "4.4".to_string()),
     ];
-    let combined = match overrides {
-        Some(v) => {
-            let mut combined = default_inline_dependencies;
-            let override_deps = v.into_iter().map(|s| (s, "0.0.0".to_string()));
-            combined.extend(override_deps);
-            combined
-        }
-        None => default_inline_dependencies,
-    };
     manifest["dependencies"] = toml_edit::Item::Table(toml_edit::Table::new());
     let deps_table = manifest["dependencies"].as_table_mut().unwrap();
-    for (dep, default_version) in combined {
+    for (dep, default_version) in default_inline_dependencies {
         let version =
-            crate::root::fetch_version(&dep).unwrap_or_else(|| default_version.to_string());
+            crate::root::resolve_version(&dep, &default_version, runner, report.as_deref_mut());
         deps_table[&dep] = toml_edit::value(version);
     }
+    for dep in overrides.unwrap_or_default() {
+        deps_table[&dep.name] = dep.resolve(runner, report.as_deref_mut());
+    }
     manifest["dependencies"]["clap"] =
         toml_edit::Item::Value(toml_edit::Value::InlineTable(toml_edit::InlineTable::new()));
-    let version = crate::root::fetch_version("clap").unwrap_or_else(|| "4.4.3".to_string());
+    let version = crate::root::resolve_version("clap", "4.4.3", runner, report);
     manifest["dependencies"]["clap"]["version"] = toml_edit::value(version);
     let mut array = toml_edit::Array::default();
     array.push("derive".to_string());
@@ -113,7 +120,7 @@ pub fn add_inline_deps(manifest: &mut toml_edit::Document, overrides: Option<Vec
 
 /// Creates a new cargo library project in the given directory.
 #[allow(clippy::too_many_arguments)]
-#[instrument(name = "lib", skip(dir, name, description, dry, bare, author, tree))]
+#[instrument(name = "lib", skip(dir, name, description, dry, bare, author, runner, report))]
 pub fn create_lib(
     dir: &Path,
     name: impl AsRef<str> + std::fmt::Display,
@@ -121,19 +128,15 @@ pub fn create_lib(
     dry: bool,
     bare: bool,
     author: Option<Vec<String>>,
-    overrides: Option<Vec<String>>,
-    mut tree: Option<&mut TreeBuilder>,
+    license: &License,
+    overrides: Option<Vec<Dependency>>,
+    runner: &dyn CommandRunner,
+    mut report: Option<&mut Report>,
 ) -> Result<()> {
     crate::utils::create_dir_gracefully!(dir, dry);
     if !dry {
         // Execute the `cargo init --lib` command in the given directory.
-        tracing::debug!("Executing `cargo init --lib` in {:?}", dir);
-        let output = std::process::Command::new("cargo")
-            .arg("init")
-            .arg("--lib")
-            .current_dir(dir)
-            .output()?;
-        tracing::debug!("cargo init --lib output: {:?}", output);
+        runner.run(Tool::Cargo, &["init", "--lib"], dir, report.as_deref_mut())?;
     }
     if !dry && !bare {
         let readme_path_buf = dir.join("README.md");
@@ -153,31 +156,33 @@ pub fn create_lib(
             &description
                 .map(|d| d.to_string())
                 .unwrap_or_else(|| format!("{} library crate", name.as_ref())),
+            license,
             overrides,
+            runner,
+            report.as_deref_mut(),
         )?;
         tracing::debug!("Finished filling cargo contents in {:?}", dir);
 
         tracing::debug!("Writing {:?}", readme_path_buf);
         std::fs::write(&readme_path_buf, format!("# {}", name.as_ref()))?;
     }
-    tree.as_deref_mut().map(|t| t.add_empty_child("README.md".to_string()));
-    tree.as_deref_mut().map(|t| t.add_empty_child("Cargo.toml".to_string()));
-    tree.as_deref_mut().map(|t| t.begin_child("src".to_string()));
-    tree.as_deref_mut().map(|t| t.add_empty_child("lib.rs".to_string()));
-    tree.map(|t| t.end_child());
+    report.as_deref_mut().map(|r| r.created("README.md"));
+    report.as_deref_mut().map(|r| r.created("Cargo.toml"));
+    report.as_deref_mut().map(|r| r.begin_dir("src"));
+    report.as_deref_mut().map(|r| r.created("lib.rs"));
+    report.map(|r| r.end_dir());
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::command::{tests::MockRunner, SystemRunner};
     use std::{fs::File, io::Read};
     use tempfile::tempdir;
 
     #[test]
     fn test_write_cargo_bin() {
-        use crate::root::fetch_version;
-
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
         let proj_name = "example";
@@ -187,6 +192,9 @@ mod tests {
             Some(vec!["refcell".to_string()]),
             proj_name,
             "example binary",
+            &License::new("MIT OR Apache-2.0"),
+            None,
+            &MockRunner::default(),
             None,
         )
         .unwrap();
@@ -196,43 +204,25 @@ mod tests {
         let mut cargo_toml = File::open(cargo_toml_path_buf).unwrap();
         let mut cargo_toml_contents = String::new();
         cargo_toml.read_to_string(&mut cargo_toml_contents).unwrap();
-        let anyhow_version = fetch_version("anyhow").unwrap_or_else(|| "1.0".to_string());
-        let inquire_version = fetch_version("inquire").unwrap_or_else(|| "0.6.2".to_string());
-        let tracing_version = fetch_version("tracing").unwrap_or_else(|| "0.1.39".to_string());
-        let serde_version = fetch_version("serde").unwrap_or_else(|| "1.0.189".to_string());
-        let serde_json_version =
-            fetch_version("serde_json").unwrap_or_else(|| "1.0.107".to_string());
-        let tracing_subscriber_version =
-            fetch_version("tracing-subscriber").unwrap_or_else(|| "0.3.17".to_string());
-        let clap_version = fetch_version("clap").unwrap_or_else(|| "4.4.3".to_string());
-        let expected_contents = format!(
-            r#"[package]
+        let expected_contents = r#"[package]
 name = "example"
 description = "example binary"
 version = "0.1.0"
 edition = "2021"
-license = "MIT"
+license = "MIT OR Apache-2.0"
 authors = ["refcell"]
 repository = "https://github.com/refcell/example"
 homepage = "https://github.com/refcell/example"
 
 [dependencies]
-anyhow = "{}"
-inquire = "{}"
-tracing = "{}"
-serde = "{}"
-serde_json = "{}"
-tracing-subscriber = "{}"
-clap = {{ version = "{}", features = ["derive"] }}
-"#,
-            anyhow_version,
-            inquire_version,
-            tracing_version,
-            serde_version,
-            serde_json_version,
-            tracing_subscriber_version,
-            clap_version
-        );
+anyhow = "1.0"
+inquire = "0.6"
+tracing = "0.1"
+serde = "1.0"
+serde_json = "1.0"
+tracing-subscriber = "0.3"
+clap = { version = "4.4.3", features = ["derive"] }
+"#;
         assert_eq!(cargo_toml_contents, expected_contents);
     }
 
@@ -241,8 +231,19 @@ clap = {{ version = "{}", features = ["derive"] }}
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
         let package_dir = dir_path_buf.join("example");
-        create_bin(&package_dir, "example", Some("example binary"), false, false, None, None, None)
-            .unwrap();
+        create_bin(
+            &package_dir,
+            "example",
+            Some("example binary"),
+            false,
+            false,
+            None,
+            &License::default(),
+            None,
+            &SystemRunner::default(),
+            None,
+        )
+        .unwrap();
 
         assert!(package_dir.exists());
         assert!(package_dir.join("src").exists());
@@ -255,8 +256,19 @@ clap = {{ version = "{}", features = ["derive"] }}
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
         let package_dir = dir_path_buf.join("example");
-        create_lib(&package_dir, "example", Some("example lib"), false, false, None, None, None)
-            .unwrap();
+        create_lib(
+            &package_dir,
+            "example",
+            Some("example lib"),
+            false,
+            false,
+            None,
+            &License::default(),
+            None,
+            &SystemRunner::default(),
+            None,
+        )
+        .unwrap();
 
         assert!(package_dir.exists());
         assert!(package_dir.join("src").exists());
@@ -264,4 +276,57 @@ clap = {{ version = "{}", features = ["derive"] }}
         assert!(package_dir.join("Cargo.toml").exists());
         assert!(package_dir.join("README.md").exists());
     }
+
+    #[test]
+    fn test_create_bin_failed_cargo_init() {
+        let dir = tempdir().unwrap();
+        let package_dir = dir.path().join("example");
+        let runner = MockRunner { fail: Some(Tool::Cargo), ..Default::default() };
+        let mut report = Report::new("example", false);
+        let err = create_bin(
+            &package_dir,
+            "example",
+            Some("example binary"),
+            false,
+            false,
+            None,
+            &License::default(),
+            None,
+            &runner,
+            Some(&mut report),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("cargo init --bin` exited with status 101"));
+        assert_eq!(
+            *runner.calls.borrow(),
+            vec![(Tool::Cargo, vec!["init".into(), "--bin".into()])]
+        );
+        assert!(!package_dir.join("Cargo.toml").exists());
+        assert!(!report.commands[0].success);
+    }
+
+    #[test]
+    fn test_create_lib_mock_runner() {
+        let dir = tempdir().unwrap();
+        let package_dir = dir.path().join("example");
+        let runner = MockRunner::default();
+        create_lib(
+            &package_dir,
+            "example",
+            Some("example lib"),
+            false,
+            true,
+            None,
+            &License::default(),
+            None,
+            &runner,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            *runner.calls.borrow(),
+            vec![(Tool::Cargo, vec!["init".into(), "--lib".into()])]
+        );
+    }
 }
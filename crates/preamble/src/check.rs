@@ -0,0 +1,430 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use crate::error::Result;
+use serde_yaml::Value;
+use tracing::instrument;
+
+/// Deprecated actions, the refs they're deprecated at, every ref if empty,
+/// and their replacement.
+pub const DEPRECATED_ACTIONS: [(&str, &[&str], &str); 11] = [
+    ("actions/checkout", &["v1", "v2", "v3"], "actions/checkout@v4"),
+    ("actions/upload-artifact", &["v1", "v2", "v3"], "actions/upload-artifact@v4"),
+    ("actions/download-artifact", &["v1", "v2", "v3"], "actions/download-artifact@v4"),
+    ("actions/cache", &["v1", "v2"], "actions/cache@v4"),
+    ("actions/create-release", &[], "ncipollo/release-action"),
+    ("actions/upload-release-asset", &[], "ncipollo/release-action"),
+    ("actions-rs/toolchain", &[], "dtolnay/rust-toolchain"),
+    ("actions-rs/cargo", &[], "a `run: cargo ...` step"),
+    ("actions-rs/audit-check", &[], "rustsec/audit-check"),
+    ("actions-rs/clippy-check", &[], "a `run: cargo clippy` step"),
+    ("actions-rs/tarpaulin", &[], "a `run: cargo tarpaulin` step"),
+];
+
+/// Actions and commands, and the permission scope they need write access to.
+pub const WRITE_PERMISSIONS: [(&str, &str); 7] = [
+    ("ncipollo/release-action", "contents"),
+    ("softprops/action-gh-release", "contents"),
+    ("anothrNick/github-tag-action", "contents"),
+    ("rustsec/audit-check", "checks"),
+    ("rustsec/audit-check", "issues"),
+    ("git push", "contents"),
+    ("gh release", "contents"),
+];
+
+/// The severity of a workflow [Finding].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something to know about the workflow, e.g. a secret it requires.
+    Note,
+    /// A likely problem, e.g. a deprecated action.
+    Warning,
+    /// The workflow is invalid or won't run as intended.
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Note => write!(f, "note"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a workflow file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// The workflow path, relative to the project directory.
+    pub file: PathBuf,
+    /// The line the finding is on, starting at 1.
+    pub line: usize,
+    /// The finding severity.
+    pub severity: Severity,
+    /// A description of the finding.
+    pub message: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}: {}", self.file.display(), self.line, self.severity, self.message)
+    }
+}
+
+/// Checks the project's `.github/workflows/` files.
+///
+/// See [check_workflows] for the checks.
+#[instrument(name = "check", skip(dir))]
+pub fn check(dir: &Path) -> Result<Vec<Finding>> {
+    tracing::info!("Checking workflows");
+    let workflows_dir = Path::new(".github").join("workflows");
+    let mut workflows = Vec::new();
+    if dir.join(&workflows_dir).is_dir() {
+        for entry in std::fs::read_dir(dir.join(&workflows_dir))? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if path.is_file() && (name.ends_with(".yml") || name.ends_with(".yaml")) {
+                workflows.push((workflows_dir.join(name), std::fs::read_to_string(&path)?));
+            }
+        }
+    }
+    workflows.sort();
+    Ok(check_workflows(&workflows))
+}
+
+/// Checks the `(path, contents)` workflows, returning the findings sorted by
+/// file and line.
+///
+/// A workflow must be valid yaml with `on` and `jobs`, every job must set
+/// `runs-on` or `uses` and only need jobs of its workflow, and `workflow_run`
+/// triggers must name one of the workflows. Deprecated actions and workflow
+/// commands are warned about, as are jobs that push or release without write
+/// permissions and `write-all` permissions. The repository secrets the
+/// workflows use are noted.
+pub fn check_workflows(workflows: &[(PathBuf, String)]) -> Vec<Finding> {
+    let parsed = workflows
+        .iter()
+        .map(|(path, contents)| (path, contents, serde_yaml::from_str::<Value>(contents)))
+        .collect::<Vec<_>>();
+    // Github names workflows without a `name` after their path.
+    let names = parsed
+        .iter()
+        .map(|(path, _, value)| match value.as_ref().ok().and_then(|v| v["name"].as_str()) {
+            Some(name) => name.to_string(),
+            None => path.display().to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    let secret_re = regex::Regex::new(r"secrets\.([A-Za-z_][A-Za-z0-9_]*)").unwrap();
+    let mut findings = Vec::new();
+    for (path, contents, value) in parsed {
+        let mut finding = |line: usize, severity: Severity, message: String| {
+            findings.push(Finding { file: path.clone(), line, severity, message })
+        };
+        let value = match value {
+            Ok(value) if value.is_mapping() => value,
+            Ok(_) => {
+                finding(1, Severity::Error, "The workflow is not a yaml mapping".to_string());
+                continue;
+            }
+            Err(e) => {
+                let line = e.location().map_or(1, |l| l.line());
+                finding(line, Severity::Error, format!("Invalid yaml: {}", e));
+                continue;
+            }
+        };
+        let lines = contents.lines().collect::<Vec<_>>();
+
+        for key in ["on", "jobs"] {
+            if value.get(key).is_none() {
+                finding(1, Severity::Error, format!("The workflow has no `{}` key", key));
+            }
+        }
+
+        // The workflows triggering this workflow must exist.
+        let workflow_run = value.get("on").and_then(|on| on.get("workflow_run"));
+        let triggers = match workflow_run.map(|w| &w["workflows"]) {
+            Some(Value::String(name)) => vec![name.as_str()],
+            Some(Value::Sequence(names)) => names.iter().filter_map(|n| n.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        let workflow_run_line =
+            find_line(&lines, 0, |l| l.trim_start().starts_with("workflow_run:"));
+        for trigger in triggers.into_iter().filter(|t| !names.iter().any(|n| n == t)) {
+            let start = workflow_run_line.unwrap_or(0);
+            let line = find_line(&lines, start, |l| l.contains(trigger)).or(workflow_run_line);
+            finding(
+                line.map_or(1, |l| l + 1),
+                Severity::Error,
+                format!(
+                    "The workflow is triggered by the unknown workflow \"{}\", expected one of {}",
+                    trigger,
+                    names.join(", ")
+                ),
+            );
+        }
+
+        if value.get("permissions").and_then(Value::as_str) == Some("write-all") {
+            let line = find_line(&lines, 0, |l| l.starts_with("permissions:"));
+            finding(line.map_or(1, |l| l + 1), Severity::Warning, write_all_message());
+        }
+
+        let jobs = value.get("jobs").and_then(Value::as_mapping).cloned().unwrap_or_default();
+        let jobs_line = find_line(&lines, 0, |l| l.starts_with("jobs:")).unwrap_or(0);
+        // Job headers are the lines indented like the first line under `jobs:`.
+        let indent = find_line(&lines, jobs_line + 1, |l| {
+            !l.trim().is_empty() && !l.trim_start().starts_with('#')
+        })
+        .map_or(2, |l| lines[l].len() - lines[l].trim_start().len());
+        let mut job_lines = jobs
+            .keys()
+            .filter_map(Value::as_str)
+            .map(|id| {
+                let header = find_line(&lines, jobs_line, |l| {
+                    let trimmed = l.trim_start();
+                    l.len() - trimmed.len() == indent &&
+                        [format!("{}:", id), format!("\"{}\":", id), format!("'{}':", id)]
+                            .iter()
+                            .any(|h| trimmed.starts_with(h.as_str()))
+                });
+                (id, header.unwrap_or(jobs_line))
+            })
+            .collect::<Vec<_>>();
+        job_lines.sort_by_key(|(_, line)| *line);
+        for (index, (id, start)) in job_lines.iter().enumerate() {
+            let end = job_lines.get(index + 1).map_or(lines.len(), |(_, line)| *line);
+            let job = &jobs[*id];
+            if job.get("runs-on").is_none() && job.get("uses").is_none() {
+                finding(
+                    start + 1,
+                    Severity::Error,
+                    format!("The {} job sets neither `runs-on` nor `uses`", id),
+                );
+            }
+            let needs = match job.get("needs") {
+                Some(Value::String(need)) => vec![need.as_str()],
+                Some(Value::Sequence(needs)) => needs.iter().filter_map(|n| n.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            for need in needs.into_iter().filter(|n| !jobs.contains_key(*n)) {
+                finding(
+                    start + 1,
+                    Severity::Error,
+                    format!("The {} job needs the unknown job \"{}\"", id, need),
+                );
+            }
+
+            if job.get("permissions").and_then(Value::as_str) == Some("write-all") {
+                let line =
+                    find_line(&lines, *start, |l| l.trim_start().starts_with("permissions:"));
+                finding(line.map_or(start + 1, |l| l + 1), Severity::Warning, write_all_message());
+            }
+            let permissions = job.get("permissions").or(value.get("permissions"));
+            let mut scopes = BTreeSet::new();
+            for (index, line) in lines.iter().enumerate().take(end).skip(*start) {
+                let code = line.trim_start();
+                if code.starts_with('#') {
+                    continue;
+                }
+                for (pattern, scope) in WRITE_PERMISSIONS {
+                    if code.contains(pattern) && scopes.insert(scope) {
+                        match permissions.map(|p| grants_write(p, scope)) {
+                            Some(true) => {}
+                            Some(false) => finding(
+                                index + 1,
+                                Severity::Error,
+                                format!(
+                                    "The {} job needs the `{}: write` permission for {}",
+                                    id, scope, pattern
+                                ),
+                            ),
+                            None => finding(
+                                index + 1,
+                                Severity::Warning,
+                                format!(
+                                    "The {} job needs the `{}: write` permission for {}, which the default token may not grant",
+                                    id, scope, pattern
+                                ),
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut secrets = BTreeSet::new();
+        for (index, line) in lines.iter().enumerate() {
+            let code = line.trim_start();
+            if code.starts_with('#') {
+                continue;
+            }
+            if let Some((action, reference)) = uses(code) {
+                let deprecated = DEPRECATED_ACTIONS.iter().find(|(name, refs, _)| {
+                    *name == action &&
+                        (refs.is_empty() || refs.contains(&reference.split('.').next().unwrap()))
+                });
+                if let Some((_, _, replacement)) = deprecated {
+                    finding(
+                        index + 1,
+                        Severity::Warning,
+                        format!(
+                            "{}@{} is deprecated, use {} instead",
+                            action, reference, replacement
+                        ),
+                    );
+                }
+            }
+            for command in ["::set-output", "::save-state"] {
+                if code.contains(command) {
+                    finding(
+                        index + 1,
+                        Severity::Warning,
+                        format!(
+                            "The `{}` workflow command is deprecated, write to `$GITHUB_OUTPUT` or `$GITHUB_STATE` instead",
+                            command
+                        ),
+                    );
+                }
+            }
+            for caps in secret_re.captures_iter(code) {
+                let secret = caps[1].to_string();
+                if secret != "GITHUB_TOKEN" && secrets.insert(secret.clone()) {
+                    finding(
+                        index + 1,
+                        Severity::Note,
+                        format!("The workflow requires the `{}` repository secret", secret),
+                    );
+                }
+            }
+        }
+    }
+    findings.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    findings
+}
+
+fn write_all_message() -> String {
+    "`write-all` grants every permission, scope it to the permissions the jobs need".to_string()
+}
+
+/// Returns whether the permissions grant write access to the scope.
+fn grants_write(permissions: &Value, scope: &str) -> bool {
+    match permissions {
+        Value::String(all) => all == "write-all",
+        Value::Mapping(scopes) => scopes.get(scope).and_then(Value::as_str) == Some("write"),
+        _ => false,
+    }
+}
+
+/// Returns the index of the first line from `start` matching the predicate.
+fn find_line(lines: &[&str], start: usize, predicate: impl Fn(&str) -> bool) -> Option<usize> {
+    lines.iter().skip(start).position(|l| predicate(l)).map(|i| i + start)
+}
+
+/// Parses the action and ref of a `uses:` line, taking the ref of a pinned
+/// action from its `# <ref>` comment.
+fn uses(line: &str) -> Option<(&str, &str)> {
+    let value = line.trim_start_matches("- ").trim_start().strip_prefix("uses:")?;
+    let (value, comment) = match value.split_once('#') {
+        Some((value, comment)) => (value, Some(comment.trim())),
+        None => (value, None),
+    };
+    let value = value.trim().trim_matches(['"', '\'']);
+    let value = value.strip_prefix("https://github.com/").unwrap_or(value);
+    let (action, reference) = value.split_once('@')?;
+    let pinned = reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit());
+    match (pinned, comment) {
+        (true, Some(comment)) if !comment.is_empty() => Some((action, comment)),
+        _ => Some((action, reference)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ci, pipeline::CiConfig, report::Report};
+    use tempfile::tempdir;
+
+    fn workflow(name: &str, contents: &str) -> (PathBuf, String) {
+        (Path::new(".github").join("workflows").join(name), contents.to_string())
+    }
+
+    #[test]
+    fn test_check_workflows() {
+        let ci = workflow(
+            "ci.yml",
+            r#"name: CI
+on: [push]
+permissions:
+  contents: read
+jobs:
+  test:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v3
+      - uses: actions-rs/toolchain@f43a0e5ff2bd294095638e18286ca9a3d1956744 # v1
+      - run: echo "::set-output name=version::1"
+  release:
+    needs: [test, build]
+    runs-on: ubuntu-latest
+    steps:
+      - run: git push --tags
+"#,
+        );
+        let tag = workflow(
+            "tag.yml",
+            r#"name: Tag
+on:
+  workflow_run:
+    workflows: ["Continuous Integration"]
+    types: [completed]
+jobs:
+  tag:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: ncipollo/release-action@v1
+        with:
+          token: ${{ secrets.RELEASE_TOKEN }}
+      - run: echo ${{ secrets.RELEASE_TOKEN }} ${{ secrets.GITHUB_TOKEN }}
+  broken:
+    steps: []
+"#,
+        );
+        let invalid = workflow("invalid.yml", "name: Invalid\njobs:\n  test: [\n");
+        let findings = check_workflows(&[ci, tag, invalid]);
+        let findings = findings
+            .iter()
+            .map(|f| (f.file.file_name().unwrap().to_str().unwrap(), f.line, f.severity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            vec![
+                ("ci.yml", 9, Severity::Warning),
+                ("ci.yml", 10, Severity::Warning),
+                ("ci.yml", 11, Severity::Warning),
+                ("ci.yml", 12, Severity::Error),
+                ("ci.yml", 16, Severity::Error),
+                ("invalid.yml", 4, Severity::Error),
+                ("tag.yml", 4, Severity::Error),
+                ("tag.yml", 10, Severity::Warning),
+                ("tag.yml", 12, Severity::Note),
+                ("tag.yml", 14, Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_generated_workflows() {
+        let dir = tempdir().unwrap();
+        let config = CiConfig::default();
+        let mut report = Report::new("example", false);
+        ci::create(dir.path(), false, &[], None, &config, &Default::default(), Some(&mut report))
+            .unwrap();
+        let findings = check(dir.path()).unwrap();
+        let problems = findings.iter().filter(|f| f.severity > Severity::Note).collect::<Vec<_>>();
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert!(findings.iter().any(|f| f.message.contains("`CARGO_REGISTRY_TOKEN`")));
+    }
+}
@@ -1,108 +1,686 @@
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    path::Path,
+    sync::OnceLock,
+};
 
-use anyhow::Result;
-use ptree::TreeBuilder;
+use crate::{
+    error::{Error, Result},
+    pipeline::{CiConfig, CiProvider, Pipeline},
+    release::ReleasePlan,
+    report::{FileAction, Report},
+};
 use tracing::instrument;
 
-/// CI Github Action Workflow String Literal.
-pub const CI_YML: &str = include_str!("../etc/workflows/ci.yml");
-
 /// Audit Github Action Workflow String Literal.
 pub const AUDIT_YML: &str = include_str!("../etc/workflows/audit.yml");
 
-/// Github Release Github Action Workflow String Literal.
-pub const GITHUB_RELEASE_YML: &str = include_str!("../etc/workflows/github-release.yml");
-
 /// Manual Tag Github Action Workflow String Literal.
 pub const MANUAL_TAG_YML: &str = include_str!("../etc/workflows/manual-tag.yml");
 
-/// Tag Github Action Workflow String Literal.
-pub const TAG_YML: &str = include_str!("../etc/workflows/tag.yml");
+/// The gitlab ci pipeline file name.
+pub const GITLAB_CI_YML: &str = ".gitlab-ci.yml";
+
+/// A bundled Github Action Workflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Workflow {
+    /// The workflow name, e.g. `ci`.
+    pub name: &'static str,
+    /// The workflow file name in `.github/workflows/`.
+    pub file: &'static str,
+    /// A short description of the workflow.
+    pub description: &'static str,
+    /// The workflow contents, `None` for the workflows generated from the
+    /// [CiConfig] and the workspace [ReleasePlan].
+    pub contents: Option<&'static str>,
+    /// The names of the workflows this workflow is triggered by.
+    pub requires: &'static [&'static str],
+}
+
+impl Workflow {
+    /// Returns the workflow contents, generating the ci workflow from the
+    /// [CiConfig] and the release workflows from the [ReleasePlan].
+    pub fn render(&self, config: &CiConfig, plan: &ReleasePlan) -> String {
+        match (self.contents, self.name) {
+            (Some(contents), _) => contents.to_string(),
+            (None, "github-release") => plan.github_release_workflow(),
+            (None, "tag") => plan.tag_workflow(),
+            (None, "release") => plan.publish_workflow(),
+            (None, "validate-version") => plan.validate_version_workflow(),
+            (None, _) => Pipeline::ci(config).github(),
+        }
+    }
+}
+
+impl std::fmt::Display for Workflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
 
-/// Release Github Action Workflow String Literal.
-pub const RELEASE_YML: &str = include_str!("../etc/workflows/release.yml");
+impl std::str::FromStr for Workflow {
+    type Err = String;
 
-/// Validate Version Github Action Workflow String Literal.
-pub const VALIDATE_VERSION_YML: &str = include_str!("../etc/workflows/validate-version.yml");
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        find_workflow(s).copied().ok_or_else(|| {
+            let names = WORKFLOWS.iter().map(|w| w.name).collect::<Vec<_>>();
+            format!("unknown workflow \"{}\", expected one of {}", s, names.join(", "))
+        })
+    }
+}
 
-/// An array of Github Action Workflow String Literals.
-pub const WORKFLOWS: [(&str, &str); 7] = [
-    ("ci.yml", CI_YML),
-    ("audit.yml", AUDIT_YML),
-    ("github-release.yml", GITHUB_RELEASE_YML),
-    ("manual-tag.yml", MANUAL_TAG_YML),
-    ("tag.yml", TAG_YML),
-    ("release.yml", RELEASE_YML),
-    ("validate-version.yml", VALIDATE_VERSION_YML),
+/// An array of the bundled Github Action Workflows.
+pub const WORKFLOWS: [Workflow; 7] = [
+    Workflow {
+        name: "ci",
+        file: "ci.yml",
+        description: "Builds, tests, lints and checks formatting on pushes and pull requests",
+        contents: None,
+        requires: &[],
+    },
+    Workflow {
+        name: "audit",
+        file: "audit.yml",
+        description: "Audits dependencies for security advisories when manifests change",
+        contents: Some(AUDIT_YML),
+        requires: &[],
+    },
+    Workflow {
+        name: "github-release",
+        file: "github-release.yml",
+        description: "Creates a github release with the release binary after CI passes on main",
+        contents: None,
+        requires: &["ci"],
+    },
+    Workflow {
+        name: "manual-tag",
+        file: "manual-tag.yml",
+        description: "Tags a version manually from the actions tab",
+        contents: Some(MANUAL_TAG_YML),
+        requires: &[],
+    },
+    Workflow {
+        name: "tag",
+        file: "tag.yml",
+        description: "Tags the workspace or each crate version after CI passes on main",
+        contents: None,
+        requires: &["ci"],
+    },
+    Workflow {
+        name: "release",
+        file: "release.yml",
+        description: "Publishes the crates to crates.io, libraries before binaries, after CI passes on main",
+        contents: None,
+        requires: &["ci"],
+    },
+    Workflow {
+        name: "validate-version",
+        file: "validate-version.yml",
+        description: "Checks pull requests bump the workspace or changed crate versions past their latest tag",
+        contents: None,
+        requires: &[],
+    },
 ];
 
-/// Copy all [WORKFLOWS] to the project `.github/workflows/` directory.
-#[instrument(name = "workflows", skip(dir, dry, tree))]
+/// Finds a bundled workflow by name or file name.
+pub fn find_workflow(name: &str) -> Option<&'static Workflow> {
+    WORKFLOWS.iter().find(|w| w.name == name.trim() || w.file == name.trim())
+}
+
+/// Lists the bundled workflows.
+pub fn list_workflows() -> Result<()> {
+    let mut table = prettytable::Table::new();
+    table.add_row(prettytable::Row::new(vec![
+        prettytable::Cell::new("Workflow"),
+        prettytable::Cell::new("Description"),
+    ]));
+    for workflow in WORKFLOWS.iter() {
+        table.add_row(prettytable::Row::new(vec![
+            prettytable::Cell::new(workflow.name),
+            prettytable::Cell::new(workflow.description),
+        ]));
+    }
+    table.printstd();
+    Ok(())
+}
+
+/// Copy the given workflows to the project `.github/workflows/` directory.
+///
+/// Existing workflow files are overwritten, unless `keep_existing` is set,
+/// in which case they are recorded as skipped in the [Report]. The release
+/// workflows publish and tag the packages of the [ReleasePlan].
+#[instrument(name = "workflows", skip(dir, workflows, config, plan, keep_existing, dry, report))]
 pub fn write_github_workflows(
     dir: &Path,
+    workflows: &[Workflow],
+    config: &CiConfig,
+    plan: &ReleasePlan,
+    keep_existing: bool,
     dry: bool,
-    tree: &mut Option<&mut TreeBuilder>,
+    report: &mut Option<&mut Report>,
 ) -> Result<()> {
     tracing::info!("Writing github workflows");
-    for (workflow_name, workflow_contents) in WORKFLOWS.iter() {
-        let workflow_path_buf = dir.join(workflow_name);
+    for workflow in workflows {
+        let workflow_path_buf = dir.join(workflow.file);
+        if keep_existing && workflow_path_buf.exists() {
+            tracing::warn!("Skipping existing workflow {:?}", workflow_path_buf);
+            report.as_deref_mut().map(|r| r.file(workflow.file, FileAction::Skipped));
+            continue;
+        }
         if !dry {
             tracing::debug!("Writing {:?}", workflow_path_buf);
-            std::fs::write(&workflow_path_buf, workflow_contents)?;
+            let contents = workflow.render(config, plan);
+            let contents =
+                pin_contents(workflow.file, contents, config.pins.as_ref(), report.as_deref_mut());
+            std::fs::write(&workflow_path_buf, contents)?;
+        }
+        report.as_deref_mut().map(|r| r.created(workflow.file));
+        if workflow.name == "release" {
+            warn_release_plan(plan, report.as_deref_mut());
+        }
+    }
+    // Warn about workflows triggered by workflows that won't exist.
+    for workflow in workflows {
+        for required in workflow.requires {
+            let selected = workflows.iter().any(|w| w.name == *required);
+            let existing = find_workflow(required).is_some_and(|w| dir.join(w.file).exists());
+            if !selected && !existing {
+                let warning = format!(
+                    "The {} workflow is triggered by the {} workflow, which is missing",
+                    workflow, required
+                );
+                tracing::warn!("{}", warning);
+                report.as_deref_mut().map(|r| r.warn(warning));
+            }
         }
-        tree.as_deref_mut().map(|t| t.add_empty_child(workflow_name.to_string()));
     }
     Ok(())
 }
 
-/// Creates github action ci workflows.
-#[instrument(name = "ci", skip(dir, dry, ci, tree))]
+/// The suffix of custom workflow files templated with the project variables.
+pub const TEMPLATE_SUFFIX: &str = ".tmpl";
+
+/// Expands the custom workflow sources to `(file name, source)` pairs.
+///
+/// A source is a yaml file, a directory of yaml files or a remote url. The
+/// file name is the source's own name, without the [TEMPLATE_SUFFIX].
+pub fn custom_workflow_files(sources: &[String]) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for source in sources {
+        if source.starts_with("https://") || source.starts_with("http://") {
+            let path = source.split(['?', '#']).next().unwrap_or_default();
+            let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+            files.push((name.to_string(), source.clone()));
+        } else if Path::new(source).is_dir() {
+            let mut entries = std::fs::read_dir(source)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            entries.sort();
+            for path in entries {
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let yaml = name.trim_end_matches(TEMPLATE_SUFFIX);
+                if path.is_file() && (yaml.ends_with(".yml") || yaml.ends_with(".yaml")) {
+                    files.push((name, path.display().to_string()));
+                }
+            }
+        } else {
+            let name = Path::new(source).file_name().unwrap_or_default();
+            files.push((name.to_string_lossy().to_string(), source.clone()));
+        }
+    }
+    Ok(files
+        .into_iter()
+        .map(|(name, source)| (name.trim_end_matches(TEMPLATE_SUFFIX).to_string(), source))
+        .collect())
+}
+
+/// Reads the custom workflow source, fetching remote urls and rendering
+/// [TEMPLATE_SUFFIX] files with the template context.
+fn read_custom_workflow(
+    source: &str,
+    context: &HashMap<&str, String>,
+    report: Option<&mut Report>,
+) -> Result<String> {
+    let contents = if source.starts_with("https://") || source.starts_with("http://") {
+        let contents = reqwest::blocking::get(source)
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text());
+        report.map(|r| r.fetch(source, contents.is_ok()));
+        contents.map_err(|e| Error::Network { url: source.to_string(), source: e.into() })?
+    } else {
+        std::fs::read_to_string(source)?
+    };
+    let path = source.split(['?', '#']).next().unwrap_or_default();
+    match path.ends_with(TEMPLATE_SUFFIX) {
+        true => Ok(render_template(&contents, context)),
+        false => Ok(contents),
+    }
+}
+
+/// Replaces the `{key}` variables of the template context in the contents.
+///
+/// Unlike the readme template, other braces are left as is, so workflow
+/// expressions like `${{ github.sha }}` don't need escaping.
+pub fn render_template(contents: &str, context: &HashMap<&str, String>) -> String {
+    let mut keys = context.keys().collect::<Vec<_>>();
+    keys.sort();
+    let patterns = keys.iter().map(|k| format!("{{{}}}", k)).collect::<Vec<_>>();
+    let ac = aho_corasick::AhoCorasick::new(patterns).unwrap();
+    let mut result = String::new();
+    ac.replace_all_with(contents, &mut result, |mat, _, dst| {
+        dst.push_str(&context[keys[mat.pattern().as_usize()]]);
+        true
+    });
+    result
+}
+
+/// The bundled action lock, pinning the actions of the bundled workflows.
+pub const ACTIONS_LOCK: &str = include_str!("../etc/actions.lock");
+
+/// The default user action lock path, relative to the project directory.
+pub const ACTIONS_LOCK_FILE: &str = ".github/actions.lock";
+
+/// The commit SHAs the `uses:` references of the workflows are pinned to.
+///
+/// A lock file has an `[actions]` table mapping `owner/repo@ref` references
+/// to full commit SHAs, see [ACTIONS_LOCK].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionLock {
+    /// The commit SHAs keyed by the `owner/repo@ref` reference.
+    pub pins: BTreeMap<String, String>,
+}
+
+impl ActionLock {
+    /// Parses an action lock, rejecting pins that aren't full commit SHAs.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let invalid = |reason: String| -> Error {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, reason).into()
+        };
+        let doc = contents
+            .parse::<toml_edit::Document>()
+            .map_err(|e| invalid(format!("Invalid action lock: {}", e)))?;
+        let mut pins = BTreeMap::new();
+        if let Some(actions) = doc.get("actions").and_then(|a| a.as_table_like()) {
+            for (action, sha) in actions.iter() {
+                match sha.as_str().filter(|sha| is_commit_sha(sha)) {
+                    Some(sha) => pins.insert(action.to_string(), sha.to_string()),
+                    None => return Err(invalid(format!("Invalid commit SHA for {}", action))),
+                };
+            }
+        }
+        Ok(Self { pins })
+    }
+
+    /// Returns the bundled [ACTIONS_LOCK].
+    pub fn bundled() -> Self {
+        Self::parse(ACTIONS_LOCK).expect("the bundled action lock is valid")
+    }
+
+    /// Reads the action lock file.
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Returns the lock with the pins of the other lock taking precedence.
+    pub fn merge(mut self, other: Self) -> Self {
+        self.pins.extend(other.pins);
+        self
+    }
+
+    /// Returns the commit SHA the `owner/repo@ref` reference is pinned to.
+    pub fn get(&self, action: &str) -> Option<&str> {
+        self.pins.get(action).map(String::as_str)
+    }
+}
+
+/// Returns whether the ref is a full commit SHA.
+fn is_commit_sha(reference: &str) -> bool {
+    reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Pins the `uses:` references of the workflow to the commit SHAs of the lock.
+///
+/// References are rewritten to `owner/repo@<sha> # <ref>`, replacing any
+/// trailing comment. References that are already pinned are re-pinned from a
+/// one word `# <ref>` comment, so updating the lock and pinning again
+/// refreshes them. Local and docker actions are left as is. Returns the
+/// pinned workflow and the references missing from the lock, including the
+/// `uses:` values that can't be parsed.
+pub fn pin_workflow(contents: &str, lock: &ActionLock) -> (String, Vec<String>) {
+    static USES: OnceLock<(regex::Regex, regex::Regex)> = OnceLock::new();
+    let (uses, reference) = USES.get_or_init(|| {
+        (
+            regex::Regex::new(r#"^\s*(?:-\s+)?uses:\s*(.*)$"#).unwrap(),
+            regex::Regex::new(
+                r##"^(['"]?)(https://github\.com/)?([^\s'"#@]+)@([^\s'"#]+)['"]?\s*(?:#(.*))?$"##,
+            )
+            .unwrap(),
+        )
+    });
+    let mut unpinned = Vec::new();
+    let mut pinned = contents
+        .lines()
+        .map(|line| {
+            let Some(value) = uses.captures(line).and_then(|c| c.get(1)) else {
+                return line.to_string();
+            };
+            let prefix = &line[..value.start()];
+            let value = value.as_str();
+            let action = value.trim_start_matches(['\'', '"']);
+            if action.starts_with("./") || action.starts_with("docker://") {
+                return line.to_string();
+            }
+            let Some(caps) = reference.captures(value) else {
+                let value = value.split(" #").next().unwrap_or_default().trim().to_string();
+                if !unpinned.contains(&value) {
+                    unpinned.push(value);
+                }
+                return line.to_string();
+            };
+            let action = &caps[3];
+            // Only a one word comment names the ref of a pinned action.
+            let comment = caps
+                .get(5)
+                .map(|c| c.as_str().trim())
+                .filter(|c| !c.is_empty() && !c.contains(char::is_whitespace));
+            let reference = match (is_commit_sha(&caps[4]), comment) {
+                (true, Some(comment)) => comment,
+                (true, None) => return line.to_string(),
+                (false, _) => &caps[4],
+            };
+            let key = format!("{}@{}", action, reference);
+            match lock.get(&key) {
+                Some(sha) => format!(
+                    "{}{}{}{}@{}{} # {}",
+                    prefix,
+                    &caps[1],
+                    caps.get(2).map_or("", |m| m.as_str()),
+                    action,
+                    sha,
+                    &caps[1],
+                    reference
+                ),
+                None => {
+                    if !is_commit_sha(&caps[4]) && !unpinned.contains(&key) {
+                        unpinned.push(key);
+                    }
+                    line.to_string()
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if contents.ends_with('\n') {
+        pinned.push('\n');
+    }
+    (pinned, unpinned)
+}
+
+/// Pins the workflow with the lock, if any, warning about the unpinned actions.
+pub(crate) fn pin_contents(
+    file: &str,
+    contents: String,
+    lock: Option<&ActionLock>,
+    report: Option<&mut Report>,
+) -> String {
+    let Some(lock) = lock else {
+        return contents;
+    };
+    let (pinned, unpinned) = pin_workflow(&contents, lock);
+    if !unpinned.is_empty() {
+        let warning =
+            format!("The {} workflow has actions without a pin: {}", file, unpinned.join(", "));
+        tracing::warn!("{}", warning);
+        if let Some(report) = report {
+            report.warn(warning);
+        }
+    }
+    pinned
+}
+
+/// Pins the `uses:` references of an existing project's github and forgejo
+/// workflows to commit SHAs.
+///
+/// The pins are taken from the bundled [ACTIONS_LOCK], overridden by the
+/// given lock file or, without one, the project's [ACTIONS_LOCK_FILE] if it
+/// exists.
+#[instrument(name = "pin", skip(dir, lock, dry, report))]
+pub fn pin(
+    dir: &Path,
+    lock: Option<&Path>,
+    dry: bool,
+    mut report: Option<&mut Report>,
+) -> Result<()> {
+    tracing::info!("Pinning workflow actions");
+    let user_lock = match lock {
+        Some(path) => Some(ActionLock::load(path)?),
+        None if dir.join(ACTIONS_LOCK_FILE).exists() => {
+            Some(ActionLock::load(&dir.join(ACTIONS_LOCK_FILE))?)
+        }
+        None => None,
+    };
+    let lock = ActionLock::bundled().merge(user_lock.unwrap_or_default());
+    for root in [".github", ".forgejo"] {
+        let workflows_dir = dir.join(root).join("workflows");
+        if !workflows_dir.is_dir() {
+            continue;
+        }
+        let mut entries = std::fs::read_dir(&workflows_dir)?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        entries.sort();
+        report.as_deref_mut().map(|r| r.begin_dir(root));
+        report.as_deref_mut().map(|r| r.begin_dir("workflows"));
+        for path in entries {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if !path.is_file() || !(name.ends_with(".yml") || name.ends_with(".yaml")) {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path)?;
+            let pinned = pin_contents(&name, contents.clone(), Some(&lock), report.as_deref_mut());
+            if pinned == contents {
+                report.as_deref_mut().map(|r| r.file(&name, FileAction::Skipped));
+                continue;
+            }
+            if !dry {
+                tracing::debug!("Writing {:?}", path);
+                std::fs::write(&path, pinned)?;
+            }
+            report.as_deref_mut().map(|r| r.created(&name));
+        }
+        report.as_deref_mut().map(|r| r.end_dir()); // <- workflows/
+        report.as_deref_mut().map(|r| r.end_dir()); // <- .github/ or .forgejo/
+    }
+    Ok(())
+}
+
+/// Creates the ci workflows for the [CiConfig] provider.
+///
+/// Writes the given workflows, or all [WORKFLOWS] if `None`, generating the
+/// ci workflow from the [CiConfig]. Custom workflow files, directories and
+/// urls are written under their own names in place of the bundled workflows,
+/// and [TEMPLATE_SUFFIX] files are rendered with the template context, e.g.
+/// `{projectname}`.
+///
+/// Only the ci workflow is available for gitlab and forgejo, where it also
+/// publishes the crate on `v*` tags. Gitlab custom files are written to
+/// `.gitlab/ci/` and included from the `.gitlab-ci.yml`.
+#[instrument(name = "ci", skip(dir, dry, custom, workflows, config, context, report))]
 pub fn create(
     dir: &Path,
     dry: bool,
-    ci: Option<String>,
-    mut tree: Option<&mut TreeBuilder>,
+    custom: &[String],
+    workflows: Option<&[Workflow]>,
+    config: &CiConfig,
+    context: &HashMap<&str, String>,
+    mut report: Option<&mut Report>,
 ) -> Result<()> {
     tracing::info!("Creating ci");
 
-    let workflows_dir = dir.join(".github").join("workflows");
-    let ci_yml_path_buf = workflows_dir.join("ci.yml");
-    crate::utils::create_dir_gracefully!(&workflows_dir, dry);
-
-    tree.as_deref_mut().map(|t| t.begin_child(".github".to_string()));
-    tree.as_deref_mut().map(|t| t.begin_child("workflows".to_string()));
+    let custom = custom_workflow_files(custom)?;
+    let selected = workflows.is_some();
+    // Custom workflows replace the bundled workflows unless some are selected.
+    let workflows = match (workflows, custom.is_empty()) {
+        (Some(workflows), _) => workflows
+            .iter()
+            .filter(|w| !custom.iter().any(|(name, _)| name == w.file))
+            .copied()
+            .collect(),
+        (None, false) => Vec::new(),
+        (None, true) => WORKFLOWS.to_vec(),
+    };
+    if selected && config.provider != CiProvider::Github {
+        for workflow in workflows.iter().filter(|w| w.name != "ci") {
+            let warning = format!(
+                "The {} workflow is only available for github, skipping it for {}",
+                workflow, config.provider
+            );
+            tracing::warn!("{}", warning);
+            report.as_deref_mut().map(|r| r.warn(warning));
+        }
+    }
+    let with_ci = workflows.iter().any(|w| w.name == "ci");
+    let plan = ReleasePlan::workspace(dir)?;
 
-    if ci.is_none() {
-        tracing::debug!("Writing {:?}", ci_yml_path_buf);
-        write_github_workflows(&workflows_dir, dry, &mut tree)?;
+    let (root, subdir) = match config.provider {
+        CiProvider::Github => (".github", "workflows"),
+        CiProvider::Gitlab => (".gitlab", "ci"),
+        CiProvider::Forgejo => (".forgejo", "workflows"),
+    };
+    let workflows_dir = dir.join(root).join(subdir);
+    if config.provider != CiProvider::Gitlab || !custom.is_empty() {
+        crate::utils::create_dir_gracefully!(&workflows_dir, dry);
+        report.as_deref_mut().map(|r| r.begin_dir(root));
+        report.as_deref_mut().map(|r| r.begin_dir(subdir));
+        for (name, source) in &custom {
+            if !dry {
+                tracing::debug!("Copying {:?} to {:?}", source, workflows_dir.join(name));
+                let contents = read_custom_workflow(source, context, report.as_deref_mut())?;
+                let contents = match config.provider {
+                    CiProvider::Gitlab => contents,
+                    _ => pin_contents(name, contents, config.pins.as_ref(), report.as_deref_mut()),
+                };
+                std::fs::write(workflows_dir.join(name), contents)?;
+            }
+            report.as_deref_mut().map(|r| r.created(name));
+        }
+        match config.provider {
+            CiProvider::Github => {
+                let workflows_dir = workflows_dir.as_path();
+                write_github_workflows(
+                    workflows_dir,
+                    &workflows,
+                    config,
+                    &plan,
+                    false,
+                    dry,
+                    &mut report,
+                )?
+            }
+            CiProvider::Forgejo if with_ci => {
+                if !dry {
+                    let contents = Pipeline::ci(config).release(config, &plan).forgejo();
+                    let contents = pin_contents(
+                        "ci.yml",
+                        contents,
+                        config.pins.as_ref(),
+                        report.as_deref_mut(),
+                    );
+                    std::fs::write(workflows_dir.join("ci.yml"), contents)?;
+                }
+                report.as_deref_mut().map(|r| r.created("ci.yml"));
+                warn_release_plan(&plan, report.as_deref_mut());
+            }
+            _ => {}
+        }
+        report.as_deref_mut().map(|r| r.end_dir()); // <- workflows/
+        report.as_deref_mut().map(|r| r.end_dir()); // <- .github/, .gitlab/ or .forgejo/
     }
-    if !dry && ci.is_some() {
-        tracing::debug!("Copying {:?} to {:?}", ci.as_ref().unwrap(), ci_yml_path_buf);
-        std::fs::copy(ci.as_ref().unwrap(), ci_yml_path_buf)?;
-        tree.as_deref_mut().map(|t| t.add_empty_child(ci.unwrap()));
+
+    if config.provider == CiProvider::Gitlab {
+        let mut contents = match with_ci {
+            true => {
+                warn_release_plan(&plan, report.as_deref_mut());
+                Pipeline::ci(config).release(config, &plan).gitlab()
+            }
+            false => String::new(),
+        };
+        if !custom.is_empty() {
+            contents.push_str(if contents.is_empty() { "include:\n" } else { "\ninclude:\n" });
+            for (name, _) in &custom {
+                let _ = writeln!(contents, "  - local: {}/{}/{}", root, subdir, name);
+            }
+        }
+        if !dry {
+            tracing::debug!("Writing {:?}", dir.join(GITLAB_CI_YML));
+            std::fs::write(dir.join(GITLAB_CI_YML), contents)?;
+        }
+        report.map(|r| r.created(GITLAB_CI_YML));
     }
 
-    tree.as_deref_mut().map(|t| t.end_child()); // <- workflows/
-    tree.map(|t| t.end_child()); // <- .github/
+    Ok(())
+}
+
+/// Warns about the packages of the [ReleasePlan] that can't be published.
+fn warn_release_plan(plan: &ReleasePlan, report: Option<&mut Report>) {
+    let warnings = plan.warnings();
+    for warning in &warnings {
+        tracing::warn!("{}", warning);
+    }
+    if let Some(report) = report {
+        report.warnings.extend(warnings);
+    }
+}
 
+/// Adds the given workflows to an existing project, keeping existing workflow files.
+///
+/// The ci workflow is generated from the default [CiConfig] with the
+/// project's toolchain channel and minimum supported rust version, and the
+/// release workflows from the project's [ReleasePlan].
+#[instrument(name = "ci", skip(dir, workflows, dry, report))]
+pub fn add(
+    dir: &Path,
+    workflows: &[Workflow],
+    dry: bool,
+    mut report: Option<&mut Report>,
+) -> Result<()> {
+    tracing::info!("Adding workflows");
+    let workflows_dir = dir.join(".github").join("workflows");
+    crate::utils::create_dir_gracefully!(&workflows_dir, dry);
+    report.as_deref_mut().map(|r| r.begin_dir(".github"));
+    report.as_deref_mut().map(|r| r.begin_dir("workflows"));
+    let config = CiConfig::default().workspace(dir)?;
+    let plan = ReleasePlan::workspace(dir)?;
+    write_github_workflows(&workflows_dir, workflows, &config, &plan, true, dry, &mut report)?;
+    report.as_deref_mut().map(|r| r.end_dir()); // <- workflows/
+    report.map(|r| r.end_dir()); // <- .github/
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        cross::{BuildTool, CrossConfig},
+        pipeline::{CiJob, Toolchain},
+        release::Package,
+    };
     use tempfile::tempdir;
 
     #[test]
     fn test_write_github_workflows() {
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
-        write_github_workflows(&dir_path_buf, false, &mut None).unwrap();
-        for (workflow_name, workflow_contents) in WORKFLOWS.iter() {
-            let workflow_path_buf = dir_path_buf.join(workflow_name);
+        let config = CiConfig::default();
+        let plan = ReleasePlan::default();
+        write_github_workflows(&dir_path_buf, &WORKFLOWS, &config, &plan, false, false, &mut None)
+            .unwrap();
+        for workflow in WORKFLOWS.iter() {
+            let workflow_path_buf = dir_path_buf.join(workflow.file);
             assert!(workflow_path_buf.exists());
             let workflow_contents_string = std::fs::read_to_string(&workflow_path_buf).unwrap();
-            assert_eq!(workflow_contents_string, *workflow_contents);
+            assert_eq!(workflow_contents_string, workflow.render(&config, &plan));
         }
     }
 
@@ -110,12 +688,359 @@ mod tests {
     fn test_create() {
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
-        create(&dir_path_buf, false, None, None).unwrap();
+        create(&dir_path_buf, false, &[], None, &CiConfig::default(), &HashMap::new(), None)
+            .unwrap();
         let workflows_dir = dir_path_buf.join(".github").join("workflows");
         assert!(workflows_dir.exists());
         let ci_yml_path_buf = workflows_dir.join("ci.yml");
         assert!(ci_yml_path_buf.exists());
         let ci_yml_contents = std::fs::read_to_string(&ci_yml_path_buf).unwrap();
-        assert_eq!(ci_yml_contents, CI_YML);
+        assert_eq!(ci_yml_contents, Pipeline::ci(&CiConfig::default()).github());
+    }
+
+    #[test]
+    fn test_create_selected_workflows() {
+        let dir = tempdir().unwrap();
+        let workflows = ["ci".parse().unwrap(), "audit.yml".parse().unwrap()];
+        let mut report = Report::new("example", false);
+        create(
+            dir.path(),
+            false,
+            &[],
+            Some(&workflows),
+            &CiConfig::default(),
+            &HashMap::new(),
+            Some(&mut report),
+        )
+        .unwrap();
+        let workflows_dir = dir.path().join(".github").join("workflows");
+        let mut files = std::fs::read_dir(&workflows_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec!["audit.yml", "ci.yml"]);
+        assert!(report.warnings.is_empty());
+        assert!("lint".parse::<Workflow>().unwrap_err().contains("expected one of ci, audit"));
+    }
+
+    #[test]
+    fn test_add_keeps_existing_workflows() {
+        let dir = tempdir().unwrap();
+        let workflows_dir = dir.path().join(".github").join("workflows");
+        std::fs::create_dir_all(&workflows_dir).unwrap();
+        std::fs::write(workflows_dir.join("ci.yml"), "name: Custom CI\n").unwrap();
+
+        let workflows = ["ci".parse().unwrap(), "tag".parse().unwrap()];
+        let mut report = Report::new("example", false);
+        add(dir.path(), &workflows, false, Some(&mut report)).unwrap();
+        let ci_yml = std::fs::read_to_string(workflows_dir.join("ci.yml")).unwrap();
+        assert_eq!(ci_yml, "name: Custom CI\n");
+        assert_eq!(
+            std::fs::read_to_string(workflows_dir.join("tag.yml")).unwrap(),
+            ReleasePlan::default().tag_workflow()
+        );
+        let actions = report.files.iter().filter(|f| !f.directory).map(|f| f.action);
+        assert_eq!(actions.collect::<Vec<_>>(), vec![FileAction::Skipped, FileAction::Created]);
+        // The existing ci workflow triggers the tag workflow.
+        assert!(report.warnings.is_empty());
+
+        add(dir.path(), &["release".parse().unwrap()], false, None).unwrap();
+        let mut report = Report::new("example", false);
+        add(tempdir().unwrap().path(), &["release".parse().unwrap()], false, Some(&mut report))
+            .unwrap();
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_create_custom_workflows() {
+        let dir = tempdir().unwrap();
+        let custom = dir.path().join("custom");
+        std::fs::create_dir_all(&custom).unwrap();
+        std::fs::write(custom.join("lint.yml"), "name: Lint\n").unwrap();
+        std::fs::write(custom.join("notes.txt"), "not a workflow").unwrap();
+        std::fs::write(
+            custom.join("deploy.yml.tmpl"),
+            "name: Deploy {projectname}\nrun: echo ${{ github.sha }} {unknown}\n",
+        )
+        .unwrap();
+        let release = dir.path().join("release.yml");
+        std::fs::write(&release, "name: Custom Release\n").unwrap();
+
+        let sources = vec![custom.display().to_string(), release.display().to_string()];
+        let files = custom_workflow_files(&sources).unwrap();
+        let names = files.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["deploy.yml", "lint.yml", "release.yml"]);
+
+        let project = dir.path().join("project");
+        let context = HashMap::from([("projectname", "example".to_string())]);
+        let workflows = ["ci".parse().unwrap(), "release".parse().unwrap()];
+        let mut report = Report::new("project", false);
+        create(
+            &project,
+            false,
+            &sources,
+            Some(&workflows),
+            &CiConfig::default(),
+            &context,
+            Some(&mut report),
+        )
+        .unwrap();
+        let workflows_dir = project.join(".github").join("workflows");
+        let deploy = std::fs::read_to_string(workflows_dir.join("deploy.yml")).unwrap();
+        assert_eq!(deploy, "name: Deploy example\nrun: echo ${{ github.sha }} {unknown}\n");
+        // The custom release workflow replaces the bundled one.
+        let release = std::fs::read_to_string(workflows_dir.join("release.yml")).unwrap();
+        assert_eq!(release, "name: Custom Release\n");
+        assert!(workflows_dir.join("ci.yml").exists());
+        let files = report.files.iter().filter(|f| !f.directory).map(|f| f.path.clone());
+        assert_eq!(
+            files.collect::<Vec<_>>(),
+            vec![
+                Path::new(".github/workflows/deploy.yml"),
+                Path::new(".github/workflows/lint.yml"),
+                Path::new(".github/workflows/release.yml"),
+                Path::new(".github/workflows/ci.yml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_provider_pipelines() {
+        let dir = tempdir().unwrap();
+        let config = CiConfig { provider: CiProvider::Forgejo, ..Default::default() };
+        let mut report = Report::new("example", false);
+        create(dir.path(), false, &[], None, &config, &HashMap::new(), Some(&mut report)).unwrap();
+        let ci_yml = dir.path().join(".forgejo").join("workflows").join("ci.yml");
+        assert_eq!(
+            std::fs::read_to_string(ci_yml).unwrap(),
+            Pipeline::ci(&config).release(&config, &ReleasePlan::default()).forgejo()
+        );
+        assert!(!dir.path().join(".github").exists());
+        assert!(report.warnings.is_empty());
+
+        let dir = tempdir().unwrap();
+        let lint = dir.path().join("lint.yml");
+        std::fs::write(&lint, "lint:\n  script: [cargo clippy]\n").unwrap();
+        let config = CiConfig { provider: CiProvider::Gitlab, ..Default::default() };
+        let workflows = ["ci".parse().unwrap(), "audit".parse().unwrap()];
+        let mut report = Report::new("example", false);
+        create(
+            dir.path(),
+            false,
+            &[lint.display().to_string()],
+            Some(&workflows),
+            &config,
+            &HashMap::new(),
+            Some(&mut report),
+        )
+        .unwrap();
+        let gitlab_ci = std::fs::read_to_string(dir.path().join(GITLAB_CI_YML)).unwrap();
+        assert!(gitlab_ci.starts_with(
+            &Pipeline::ci(&config).release(&config, &ReleasePlan::default()).gitlab()
+        ));
+        assert!(gitlab_ci.ends_with("\ninclude:\n  - local: .gitlab/ci/lint.yml\n"));
+        assert!(dir.path().join(".gitlab").join("ci").join("lint.yml").exists());
+        // The audit workflow is github only.
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_custom_workflow_url_file_name() {
+        let sources = vec!["https://example.com/workflows/deploy.yml.tmpl?ref=main".to_string()];
+        let files = custom_workflow_files(&sources).unwrap();
+        assert_eq!(files, vec![("deploy.yml".to_string(), sources[0].clone())]);
+    }
+
+    const FIXTURE_LOCK: &str = include_str!("../tests/fixtures/actions.lock");
+
+    #[test]
+    fn test_action_lock() {
+        let lock = ActionLock::parse(FIXTURE_LOCK).unwrap();
+        assert_eq!(lock.pins.len(), 3);
+        assert_eq!(
+            lock.get("actions/checkout@v4"),
+            Some("0000000000000000000000000000000000000004")
+        );
+        assert!(ActionLock::parse("[actions]\n\"actions/checkout@v4\" = \"v4\"\n").is_err());
+        let bundled = ActionLock::bundled();
+        assert!(bundled.get("actions/checkout@v4").is_some());
+        // The user lock takes precedence over the bundled lock.
+        let merged = bundled.merge(lock);
+        assert_eq!(
+            merged.get("actions/checkout@v4"),
+            Some("0000000000000000000000000000000000000004")
+        );
+        assert!(merged.get("actions/checkout@v3").is_some());
+    }
+
+    #[test]
+    fn test_pin_workflow() {
+        let lock = ActionLock::parse(FIXTURE_LOCK).unwrap();
+        let workflow = r#"jobs:
+  test:
+    steps:
+      - uses: actions/checkout@v4
+      - uses: "dtolnay/rust-toolchain@stable"
+        with:
+          components: clippy
+      - name: Cache
+        uses: https://github.com/Swatinem/rust-cache@v2
+      - uses: ./.github/actions/local
+      - uses: docker://alpine:3.18
+      - uses: taiki-e/install-action@cargo-hack
+      - uses: taiki-e/install-action@cargo-hack
+      - run: echo "uses: actions/checkout@v4"
+"#;
+        let (pinned, unpinned) = pin_workflow(workflow, &lock);
+        assert_eq!(
+            pinned,
+            r#"jobs:
+  test:
+    steps:
+      - uses: actions/checkout@0000000000000000000000000000000000000004 # v4
+      - uses: "dtolnay/rust-toolchain@1111111111111111111111111111111111111111" # stable
+        with:
+          components: clippy
+      - name: Cache
+        uses: https://github.com/Swatinem/rust-cache@2222222222222222222222222222222222222222 # v2
+      - uses: ./.github/actions/local
+      - uses: docker://alpine:3.18
+      - uses: taiki-e/install-action@cargo-hack
+      - uses: taiki-e/install-action@cargo-hack
+      - run: echo "uses: actions/checkout@v4"
+"#
+        );
+        assert_eq!(unpinned, vec!["taiki-e/install-action@cargo-hack".to_string()]);
+
+        // Pins are refreshed from the ref comment.
+        let mut updated = lock.clone();
+        updated.pins.insert(
+            "actions/checkout@v4".to_string(),
+            "4444444444444444444444444444444444444444".to_string(),
+        );
+        let (repinned, _) = pin_workflow(&pinned, &updated);
+        assert!(repinned.contains(
+            "      - uses: actions/checkout@4444444444444444444444444444444444444444 # v4\n"
+        ));
+        assert_eq!(pin_workflow(&repinned, &updated).0, repinned);
+    }
+
+    #[test]
+    fn test_pin_workflow_comments() {
+        let lock = ActionLock::parse(FIXTURE_LOCK).unwrap();
+        let workflow = "steps:\n  - uses: actions/checkout@v4 # checkout the repo\n  \
+                        - uses: actions/checkout@4444444444444444444444444444444444444444 # latest checkout\n  \
+                        - uses: actions/checkout\n  - uses: ${{ matrix.action }}\n";
+        let (pinned, unpinned) = pin_workflow(workflow, &lock);
+        assert!(pinned.starts_with(
+            "steps:\n  - uses: actions/checkout@0000000000000000000000000000000000000004 # v4\n"
+        ));
+        // A free text comment doesn't name the ref of a pinned action.
+        assert!(pinned.contains("@4444444444444444444444444444444444444444 # latest checkout\n"));
+        assert_eq!(unpinned, vec!["actions/checkout", "${{ matrix.action }}"]);
+    }
+
+    #[test]
+    fn test_bundled_lock_pins_every_action() {
+        let lock = ActionLock::bundled();
+        let plan = ReleasePlan {
+            packages: vec![
+                Package {
+                    name: "common".to_string(),
+                    version: Some("0.1.0".to_string()),
+                    publish: true,
+                    ..Default::default()
+                },
+                Package {
+                    name: "example".to_string(),
+                    version: Some("0.1.0".to_string()),
+                    publish: true,
+                    bin: true,
+                    dependencies: vec!["common".to_string()],
+                    ..Default::default()
+                },
+            ],
+            workspace_version: Some("0.1.0".to_string()),
+        };
+        let config = CiConfig {
+            toolchains: vec![Toolchain::Stable, Toolchain::Msrv, Toolchain::Nightly],
+            msrv: Some("1.74".to_string()),
+            os: vec!["ubuntu-latest".to_string(), "windows-latest".to_string()],
+            feature_powerset: true,
+            jobs: vec![CiJob::Docs, CiJob::Coverage, CiJob::Miri, CiJob::SemverChecks, CiJob::Deny],
+            toolchain_file: true,
+            nightly_rustfmt: true,
+            ..Default::default()
+        };
+        let mut workflows =
+            WORKFLOWS.iter().map(|w| (w.file, w.render(&config, &plan))).collect::<Vec<_>>();
+        let pipeline = Pipeline::ci(&config).release(&config, &plan);
+        workflows.push(("forgejo", pipeline.forgejo()));
+        let bins = ["example".to_string()];
+        for build_tool in [BuildTool::Make, BuildTool::Just] {
+            let cross = CrossConfig { build_tool, ..Default::default() };
+            workflows.push(("cross-release.yml", cross.workflow(&bins)));
+        }
+        for (file, workflow) in workflows {
+            let (_, unpinned) = pin_workflow(&workflow, &lock);
+            assert!(unpinned.is_empty(), "{} has actions without a pin: {:?}", file, unpinned);
+        }
+    }
+
+    #[test]
+    fn test_create_pinned_workflows() {
+        let dir = tempdir().unwrap();
+        let pins = ActionLock::parse(FIXTURE_LOCK).unwrap();
+        let config = CiConfig { pins: Some(pins), ..Default::default() };
+        let workflows = ["ci".parse().unwrap()];
+        let mut report = Report::new("example", false);
+        create(
+            dir.path(),
+            false,
+            &[],
+            Some(&workflows),
+            &config,
+            &HashMap::new(),
+            Some(&mut report),
+        )
+        .unwrap();
+        let ci_yml = dir.path().join(".github").join("workflows").join("ci.yml");
+        let ci_yml = std::fs::read_to_string(ci_yml).unwrap();
+        assert!(
+            ci_yml.contains("uses: actions/checkout@0000000000000000000000000000000000000004 # v4")
+        );
+        assert!(!ci_yml.contains("actions/checkout@v4"));
+        // The toolchain action tag is missing from the fixture.
+        assert!(report.warnings.iter().any(|w| w.contains("dtolnay/rust-toolchain@v1")));
+    }
+
+    #[test]
+    fn test_pin_existing_workflows() {
+        let dir = tempdir().unwrap();
+        let workflows_dir = dir.path().join(".github").join("workflows");
+        std::fs::create_dir_all(&workflows_dir).unwrap();
+        std::fs::write(workflows_dir.join("ci.yml"), "steps:\n  - uses: actions/checkout@v4\n")
+            .unwrap();
+        std::fs::write(workflows_dir.join("local.yml"), "steps:\n  - uses: ./local\n").unwrap();
+        let lock = dir.path().join("actions.lock");
+        std::fs::write(&lock, FIXTURE_LOCK).unwrap();
+
+        let mut report = Report::new("example", true);
+        pin(dir.path(), Some(&lock), true, Some(&mut report)).unwrap();
+        let ci_yml = std::fs::read_to_string(workflows_dir.join("ci.yml")).unwrap();
+        assert_eq!(ci_yml, "steps:\n  - uses: actions/checkout@v4\n");
+
+        let mut report = Report::new("example", false);
+        pin(dir.path(), Some(&lock), false, Some(&mut report)).unwrap();
+        let ci_yml = std::fs::read_to_string(workflows_dir.join("ci.yml")).unwrap();
+        assert_eq!(
+            ci_yml,
+            "steps:\n  - uses: actions/checkout@0000000000000000000000000000000000000004 # v4\n"
+        );
+        let actions = report.files.iter().map(|f| f.action).collect::<Vec<_>>();
+        assert!(actions.contains(&FileAction::Created));
+        assert!(actions.contains(&FileAction::Skipped));
+        assert!(report.warnings.is_empty());
     }
 }
@@ -0,0 +1,257 @@
+use crate::report::{CommandRecord, Report};
+use std::path::{Path, PathBuf};
+
+/// The external tools shelled out to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// The `git` binary.
+    Git,
+    /// The `cargo` binary.
+    Cargo,
+}
+
+/// The captured output of an executed command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// The exit code, if the command exited normally.
+    pub status: Option<i32>,
+    /// Whether the command exited successfully.
+    pub success: bool,
+    /// The captured standard output.
+    pub stdout: String,
+    /// The captured standard error.
+    pub stderr: String,
+}
+
+/// An error executing a command.
+#[derive(Debug)]
+pub enum CommandError {
+    /// The program could not be spawned, e.g. because it is not installed.
+    Spawn {
+        /// The program that failed to spawn.
+        program: String,
+        /// The underlying io error.
+        source: std::io::Error,
+    },
+    /// The program exited with a non-zero status.
+    Failed {
+        /// The executed program.
+        program: String,
+        /// The program arguments.
+        args: Vec<String>,
+        /// The exit code, if the command exited normally.
+        status: Option<i32>,
+        /// The captured standard error.
+        stderr: String,
+    },
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Spawn { program, source } => {
+                write!(f, "Failed to execute `{}`: {}", program, source)
+            }
+            Self::Failed { program, args, status, stderr } => {
+                let status = status.map(|s| s.to_string()).unwrap_or_else(|| "signal".into());
+                write!(f, "`{} {}` exited with status {}", program, args.join(" "), status)?;
+                match stderr.trim() {
+                    "" => Ok(()),
+                    stderr => write!(f, ": {}", stderr),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Spawn { source, .. } => Some(source),
+            Self::Failed { .. } => None,
+        }
+    }
+}
+
+/// Executes external commands.
+///
+/// Implementors only need to provide [CommandRunner::program] and
+/// [CommandRunner::output]. The provided [CommandRunner::run] method checks
+/// the exit status and records the command in the [Report], so tests can
+/// swap in a mock runner without depending on the host's toolchain.
+pub trait CommandRunner: std::fmt::Debug {
+    /// Returns the program executed for the given [Tool].
+    fn program(&self, tool: Tool) -> String;
+
+    /// Executes the tool with the given arguments in the given directory,
+    /// capturing its output without checking the exit status.
+    fn output(&self, tool: Tool, args: &[&str], dir: &Path) -> std::io::Result<CommandOutput>;
+
+    /// Executes the tool, turning a spawn failure or a non-zero exit status
+    /// into a [CommandError]. The command is recorded in the [Report].
+    fn run(
+        &self,
+        tool: Tool,
+        args: &[&str],
+        dir: &Path,
+        report: Option<&mut Report>,
+    ) -> Result<CommandOutput, CommandError> {
+        let program = self.program(tool);
+        tracing::debug!("Executing `{} {}` in {:?}", program, args.join(" "), dir);
+        let output = self.output(tool, args, dir);
+        let (status, success) = match &output {
+            Ok(o) => (o.status, o.success),
+            Err(_) => (None, false),
+        };
+        report.map(|r| {
+            r.command(CommandRecord {
+                program: program.clone(),
+                args: args.iter().map(|a| a.to_string()).collect(),
+                dir: dir.to_path_buf(),
+                status,
+                success,
+            })
+        });
+        let output =
+            output.map_err(|source| CommandError::Spawn { program: program.clone(), source })?;
+        tracing::debug!("`{} {}` output: {:?}", program, args.join(" "), output);
+        if !output.success {
+            return Err(CommandError::Failed {
+                program,
+                args: args.iter().map(|a| a.to_string()).collect(),
+                status: output.status,
+                stderr: output.stderr,
+            });
+        }
+        Ok(output)
+    }
+}
+
+/// Executes commands as subprocesses of the current process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemRunner {
+    /// The `git` binary to execute.
+    pub git: PathBuf,
+    /// The `cargo` binary to execute.
+    pub cargo: PathBuf,
+}
+
+impl Default for SystemRunner {
+    fn default() -> Self {
+        Self { git: PathBuf::from("git"), cargo: PathBuf::from("cargo") }
+    }
+}
+
+impl SystemRunner {
+    /// Creates a new [SystemRunner], overriding the default `git` and `cargo` binaries.
+    pub fn new(git: Option<PathBuf>, cargo: Option<PathBuf>) -> Self {
+        let default = Self::default();
+        Self { git: git.unwrap_or(default.git), cargo: cargo.unwrap_or(default.cargo) }
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn program(&self, tool: Tool) -> String {
+        match tool {
+            Tool::Git => self.git.display().to_string(),
+            Tool::Cargo => self.cargo.display().to_string(),
+        }
+    }
+
+    fn output(&self, tool: Tool, args: &[&str], dir: &Path) -> std::io::Result<CommandOutput> {
+        let program = match tool {
+            Tool::Git => &self.git,
+            Tool::Cargo => &self.cargo,
+        };
+        let output = std::process::Command::new(program).args(args).current_dir(dir).output()?;
+        Ok(CommandOutput {
+            status: output.status.code(),
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A [CommandRunner] that records invocations instead of executing them.
+    #[derive(Debug, Default)]
+    pub(crate) struct MockRunner {
+        pub(crate) calls: RefCell<Vec<(Tool, Vec<String>)>>,
+        pub(crate) fail: Option<Tool>,
+        pub(crate) stdout: String,
+    }
+
+    impl CommandRunner for MockRunner {
+        fn program(&self, tool: Tool) -> String {
+            format!("mock-{:?}", tool).to_lowercase()
+        }
+
+        fn output(&self, tool: Tool, args: &[&str], _: &Path) -> std::io::Result<CommandOutput> {
+            self.calls.borrow_mut().push((tool, args.iter().map(|a| a.to_string()).collect()));
+            match self.fail == Some(tool) {
+                true => Ok(CommandOutput {
+                    status: Some(101),
+                    success: false,
+                    stderr: "error: mock failure\n".to_string(),
+                    ..Default::default()
+                }),
+                false => Ok(CommandOutput {
+                    status: Some(0),
+                    success: true,
+                    stdout: self.stdout.clone(),
+                    ..Default::default()
+                }),
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_failure() {
+        let runner = MockRunner { fail: Some(Tool::Cargo), ..Default::default() };
+        let mut report = Report::new("example", false);
+        let err = runner
+            .run(Tool::Cargo, &["init", "--bin"], Path::new("."), Some(&mut report))
+            .unwrap_err();
+        assert!(matches!(err, CommandError::Failed { status: Some(101), .. }));
+        assert_eq!(
+            err.to_string(),
+            "`mock-cargo init --bin` exited with status 101: error: mock failure"
+        );
+        assert_eq!(report.commands.len(), 1);
+        assert_eq!(report.commands[0].program, "mock-cargo");
+        assert_eq!(report.commands[0].status, Some(101));
+        assert!(!report.commands[0].success);
+    }
+
+    #[test]
+    fn test_run_success() {
+        let runner = MockRunner::default();
+        let output = runner.run(Tool::Git, &["init"], Path::new("."), None).unwrap();
+        assert!(output.success);
+        assert_eq!(*runner.calls.borrow(), vec![(Tool::Git, vec!["init".to_string()])]);
+    }
+
+    #[test]
+    fn test_system_runner_missing_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = SystemRunner::new(Some(PathBuf::from("/nonexistent/git")), None);
+        let mut report = Report::new("example", false);
+        let err = runner.run(Tool::Git, &["init"], dir.path(), Some(&mut report)).unwrap_err();
+        assert!(matches!(err, CommandError::Spawn { .. }));
+        assert!(err.to_string().starts_with("Failed to execute `/nonexistent/git`"));
+        assert_eq!(report.commands[0].status, None);
+        assert!(!report.commands[0].success);
+    }
+
+    #[test]
+    fn test_system_runner_program() {
+        let runner = SystemRunner::new(None, Some(PathBuf::from("/opt/cargo")));
+        assert_eq!(runner.program(Tool::Git), "git");
+        assert_eq!(runner.program(Tool::Cargo), "/opt/cargo");
+    }
+}
@@ -0,0 +1,256 @@
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use crate::{
+    ci::{pin_contents, render_template, ActionLock},
+    error::Result,
+    pipeline::CiProvider,
+    report::Report,
+};
+use tracing::instrument;
+
+/// Cross Release Github Action Workflow Template.
+pub const CROSS_RELEASE_YML: &str = include_str!("../etc/cross/cross-release.yml");
+
+/// Cross Release Makefile Template.
+pub const MAKEFILE: &str = include_str!("../etc/cross/Makefile");
+
+/// Cross Release Justfile Template.
+pub const JUSTFILE: &str = include_str!("../etc/cross/justfile");
+
+/// The default release targets, the linux and windows gnu targets built with
+/// `cross` and the apple targets built with `cargo` on a macos runner.
+pub const DEFAULT_TARGETS: [&str; 5] = [
+    "x86_64-unknown-linux-gnu",
+    "aarch64-unknown-linux-gnu",
+    "x86_64-apple-darwin",
+    "aarch64-apple-darwin",
+    "x86_64-pc-windows-gnu",
+];
+
+/// The command runner the cross release workflow builds the targets with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuildTool {
+    /// A `Makefile` with `build-<target>` and `dist-<target>` rules.
+    #[default]
+    Make,
+    /// A `justfile` with `build <target>` and `dist <target>` recipes.
+    Just,
+}
+
+impl std::str::FromStr for BuildTool {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "make" | "makefile" => Ok(Self::Make),
+            "just" | "justfile" => Ok(Self::Just),
+            _ => Err(format!("unknown build tool \"{}\", expected make or just", s)),
+        }
+    }
+}
+
+/// The parameters the cross release workflow is generated from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossConfig {
+    /// The target triples the binaries are released for.
+    pub targets: Vec<String>,
+    /// The command runner the targets are built with.
+    pub build_tool: BuildTool,
+}
+
+impl Default for CrossConfig {
+    fn default() -> Self {
+        Self {
+            targets: DEFAULT_TARGETS.iter().map(|t| t.to_string()).collect(),
+            build_tool: BuildTool::default(),
+        }
+    }
+}
+
+impl CrossConfig {
+    /// Returns the file name and contents of the `Makefile` or `justfile`
+    /// building and archiving the binaries.
+    pub fn build_file(&self, bins: &[String]) -> (&'static str, String) {
+        let context = self.context(bins);
+        match self.build_tool {
+            BuildTool::Make => ("Makefile", render_template(MAKEFILE, &context)),
+            BuildTool::Just => ("justfile", render_template(JUSTFILE, &context)),
+        }
+    }
+
+    /// Renders the github workflow building the targets on tags and uploading
+    /// the archives and checksums to the github release.
+    pub fn workflow(&self, bins: &[String]) -> String {
+        render_template(CROSS_RELEASE_YML, &self.context(bins))
+    }
+
+    fn context(&self, bins: &[String]) -> HashMap<&'static str, String> {
+        let mut matrix = String::new();
+        for target in &self.targets {
+            let _ = writeln!(
+                matrix,
+                "          - target: {}\n            os: {}",
+                target,
+                runner(target)
+            );
+        }
+        let (setup, dist) = match self.build_tool {
+            BuildTool::Make => (String::new(), "make dist-${{ matrix.target }}"),
+            BuildTool::Just => (
+                "      - uses: taiki-e/install-action@v2\n        with:\n          tool: just\n"
+                    .to_string(),
+                "just dist ${{ matrix.target }}",
+            ),
+        };
+        HashMap::from([
+            ("binaries", bins.join(" ")),
+            ("target_triples", self.targets.join(" ")),
+            ("matrix", matrix.trim_end().to_string()),
+            ("setup", setup),
+            ("dist", dist.to_string()),
+        ])
+    }
+}
+
+/// Returns the github runner that builds the target, the host for apple and
+/// windows msvc targets, which `cross` can't build.
+pub fn runner(target: &str) -> &'static str {
+    if target.contains("-apple-") {
+        "macos-latest"
+    } else if target.ends_with("-windows-msvc") {
+        "windows-latest"
+    } else {
+        "ubuntu-latest"
+    }
+}
+
+/// Creates the `.github/workflows/cross-release.yml` workflow and the
+/// `Makefile` or `justfile` releasing the given binaries for each target,
+/// pinning the workflow actions with the lock, if any.
+///
+/// The workflow is only available for github, other providers only get the
+/// build file.
+#[instrument(name = "cross", skip(dir, config, bins, pins, dry, report))]
+pub fn create(
+    dir: &Path,
+    config: &CrossConfig,
+    bins: &[String],
+    pins: Option<&ActionLock>,
+    provider: CiProvider,
+    dry: bool,
+    mut report: Option<&mut Report>,
+) -> Result<()> {
+    tracing::info!("Creating cross release workflow");
+    if bins.is_empty() {
+        let warning = "The cross release workflow has no binaries to release";
+        tracing::warn!("{}", warning);
+        report.as_deref_mut().map(|r| r.warn(warning));
+    }
+
+    let (build_file, contents) = config.build_file(bins);
+    if !dry {
+        tracing::debug!("Writing {:?}", dir.join(build_file));
+        std::fs::write(dir.join(build_file), contents)?;
+    }
+    report.as_deref_mut().map(|r| r.created(build_file));
+
+    if provider != CiProvider::Github {
+        let warning = format!(
+            "The cross-release workflow is only available for github, skipping it for {}",
+            provider
+        );
+        tracing::warn!("{}", warning);
+        report.map(|r| r.warn(warning));
+        return Ok(());
+    }
+    let workflows_dir = dir.join(".github").join("workflows");
+    crate::utils::create_dir_gracefully!(&workflows_dir, dry);
+    report.as_deref_mut().map(|r| r.begin_dir(".github"));
+    report.as_deref_mut().map(|r| r.begin_dir("workflows"));
+    if !dry {
+        tracing::debug!("Writing {:?}", workflows_dir.join("cross-release.yml"));
+        let workflow = config.workflow(bins);
+        let workflow = pin_contents("cross-release.yml", workflow, pins, report.as_deref_mut());
+        std::fs::write(workflows_dir.join("cross-release.yml"), workflow)?;
+    }
+    report.as_deref_mut().map(|r| r.created("cross-release.yml"));
+    report.as_deref_mut().map(|r| r.end_dir()); // <- workflows/
+    report.map(|r| r.end_dir()); // <- .github/
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cross_release_workflow() {
+        let config = CrossConfig {
+            targets: vec![
+                "x86_64-unknown-linux-gnu".to_string(),
+                "aarch64-apple-darwin".to_string(),
+            ],
+            build_tool: BuildTool::Just,
+        };
+        let bins = vec!["app".to_string(), "tool".to_string()];
+        let workflow: serde_yaml::Value = serde_yaml::from_str(&config.workflow(&bins)).unwrap();
+        let include = &workflow["jobs"]["build"]["strategy"]["matrix"]["include"];
+        assert_eq!(
+            include,
+            &serde_yaml::from_str::<serde_yaml::Value>(
+                "[{target: x86_64-unknown-linux-gnu, os: ubuntu-latest}, {target: aarch64-apple-darwin, os: macos-latest}]"
+            )
+            .unwrap()
+        );
+        let steps = workflow["jobs"]["build"]["steps"].as_sequence().unwrap();
+        assert_eq!(steps[4]["uses"], "taiki-e/install-action@v2");
+        assert_eq!(steps[4]["with"]["tool"], "just");
+        assert_eq!(steps[5]["run"], "just dist ${{ matrix.target }}");
+        assert_eq!(workflow["jobs"]["release"]["needs"], "build");
+
+        let (name, justfile) = config.build_file(&bins);
+        assert_eq!(name, "justfile");
+        assert!(justfile.contains("bins := \"app tool\"\n"));
+        assert!(justfile.contains("targets := \"x86_64-unknown-linux-gnu aarch64-apple-darwin\"\n"));
+        // The just interpolations are left as is.
+        assert!(justfile.contains("for target in {{targets}}; do"));
+    }
+
+    #[test]
+    fn test_create_cross_release() {
+        let dir = tempdir().unwrap();
+        let mut report = Report::new("example", false);
+        let bins = vec!["example".to_string()];
+        let config = CrossConfig::default();
+        create(dir.path(), &config, &bins, None, CiProvider::Github, false, Some(&mut report))
+            .unwrap();
+        let makefile = std::fs::read_to_string(dir.path().join("Makefile")).unwrap();
+        assert!(makefile.contains("BINS ?= example\n"));
+        assert!(makefile.contains(&format!("TARGETS ?= {}\n", DEFAULT_TARGETS.join(" "))));
+        let workflow = dir.path().join(".github").join("workflows").join("cross-release.yml");
+        let workflow = std::fs::read_to_string(workflow).unwrap();
+        assert!(workflow.contains("        run: make dist-${{ matrix.target }}\n"));
+        assert!(report.warnings.is_empty());
+
+        let mut report = Report::new("example", true);
+        create(dir.path(), &config, &[], None, CiProvider::Github, true, Some(&mut report))
+            .unwrap();
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_create_cross_release_gitlab() {
+        let dir = tempdir().unwrap();
+        let mut report = Report::new("example", false);
+        let bins = vec!["example".to_string()];
+        let config = CrossConfig::default();
+        create(dir.path(), &config, &bins, None, CiProvider::Gitlab, false, Some(&mut report))
+            .unwrap();
+        assert!(dir.path().join("Makefile").exists());
+        assert!(!dir.path().join(".github").exists());
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].contains("skipping it for gitlab"));
+        assert!(!report.files.iter().any(|f| f.path.ends_with("cross-release.yml")));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::command::CommandError;
+use std::path::PathBuf;
+
+/// A [std::result::Result] defaulting to the preamble [Error].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error returned by preamble.
+#[derive(Debug)]
+pub enum Error {
+    /// A conflicting file was found in the project directory and the user
+    /// could not be prompted to proceed, e.g. because stdin is not a TTY.
+    Conflict(PathBuf),
+    /// The user declined or cancelled a prompt.
+    UserAborted(String),
+    /// A network request failed.
+    Network {
+        /// The requested url.
+        url: String,
+        /// The underlying error.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The license is unknown or could not be rendered.
+    License(String),
+    /// A template failed to parse or render.
+    Template(String),
+    /// An external command failed.
+    Command(CommandError),
+    /// An io error.
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Conflict(path) => {
+                write!(f, "Found conflicting file {:?} in the project directory", path)
+            }
+            Self::UserAborted(reason) => write!(f, "{}", reason),
+            Self::Network { url, source } => write!(f, "Failed to fetch {}: {}", url, source),
+            Self::License(reason) => write!(f, "License error: {}", reason),
+            Self::Template(reason) => write!(f, "Template error: {}", reason),
+            Self::Command(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Network { source, .. } => Some(source.as_ref()),
+            Self::Command(e) => Some(e),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<CommandError> for Error {
+    fn from(e: CommandError) -> Self {
+        Self::Command(e)
+    }
+}
+
+impl From<leon::ParseError> for Error {
+    fn from(e: leon::ParseError) -> Self {
+        Self::Template(e.to_string())
+    }
+}
+
+impl From<leon::RenderError> for Error {
+    fn from(e: leon::RenderError) -> Self {
+        Self::Template(e.to_string())
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => Self::Io(e),
+            e => Self::Io(std::io::Error::new(std::io::ErrorKind::Other, e)),
+        }
+    }
+}
+
+impl From<inquire::InquireError> for Error {
+    fn from(e: inquire::InquireError) -> Self {
+        match e {
+            inquire::InquireError::OperationCanceled |
+            inquire::InquireError::OperationInterrupted => {
+                Self::UserAborted("User cancelled the prompt".to_string())
+            }
+            inquire::InquireError::IO(e) => Self::Io(e),
+            e => Self::Io(std::io::Error::new(std::io::ErrorKind::Other, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_from_inquire_error() {
+        let err = Error::from(inquire::InquireError::OperationCanceled);
+        assert!(matches!(err, Error::UserAborted(_)));
+        let err = Error::from(inquire::InquireError::NotTTY);
+        assert!(matches!(err, Error::Io(_)));
+        assert_eq!(err.to_string(), "IO error: The input device is not a TTY");
+    }
+
+    #[test]
+    fn test_from_image_error() {
+        let format = image::ImageFormat::Png;
+        let err = image::load_from_memory_with_format(b"not a png", format).unwrap_err();
+        assert!(matches!(Error::from(err), Error::Io(_)));
+    }
+
+    #[test]
+    fn test_source() {
+        let err = Error::Network {
+            url: "https://example.com".to_string(),
+            source: "connection refused".into(),
+        };
+        assert_eq!(err.to_string(), "Failed to fetch https://example.com: connection refused");
+        assert_eq!(err.source().unwrap().to_string(), "connection refused");
+        assert!(Error::License("unknown".to_string()).source().is_none());
+    }
+}
@@ -1,6 +1,8 @@
-use anyhow::Result;
+use crate::{
+    error::{Error, Result},
+    report::Report,
+};
 use image::ImageFormat;
-use ptree::TreeBuilder;
 use std::path::Path;
 use tracing::instrument;
 
@@ -16,33 +18,32 @@ pub const LOGO_URL: &str =
 pub const FAVICON_URL: &str =
     "https://raw.githubusercontent.com/refcell/amble/main/etc/template/favicon.ico";
 
+/// The template assets as `(file name, url, image format)` tuples.
+pub const ASSETS: [(&str, &str, ImageFormat); 3] = [
+    ("banner.png", BANNER_URL, ImageFormat::Png),
+    ("logo.png", LOGO_URL, ImageFormat::Png),
+    ("favicon.ico", FAVICON_URL, ImageFormat::Ico),
+];
+
 /// Creates a new etc directory in the  specified directory.
-#[instrument(name = "etc", skip(dir, dry, assets, tree))]
-pub fn create(
-    dir: &Path,
-    dry: bool,
-    assets: bool,
-    mut tree: Option<&mut TreeBuilder>,
-) -> Result<()> {
+#[instrument(name = "etc", skip(dir, dry, assets, report))]
+pub fn create(dir: &Path, dry: bool, assets: bool, mut report: Option<&mut Report>) -> Result<()> {
     tracing::info!("Creating etc directory");
     crate::utils::create_dir_gracefully!(dir.join("etc"), dry);
-    tree.as_deref_mut().map(|t| t.begin_child("etc".to_string()));
+    report.as_deref_mut().map(|r| r.begin_dir("etc"));
     if !dry && assets {
         tracing::debug!("Copying assets to etc directory");
-        let banner_img_bytes = reqwest::blocking::get(BANNER_URL)?.bytes()?;
-        let image = image::load_from_memory_with_format(&banner_img_bytes, ImageFormat::Png)?;
-        image.save_with_format(dir.join("etc").join("banner.png"), ImageFormat::Png)?;
-        let logo_img_bytes = reqwest::blocking::get(LOGO_URL)?.bytes()?;
-        let image = image::load_from_memory_with_format(&logo_img_bytes, ImageFormat::Png)?;
-        image.save_with_format(dir.join("etc").join("logo.png"), ImageFormat::Png)?;
-        let favicon_img_bytes = reqwest::blocking::get(FAVICON_URL)?.bytes()?;
-        let image = image::load_from_memory_with_format(&favicon_img_bytes, ImageFormat::Ico)?;
-        image.save_with_format(dir.join("etc").join("favicon.ico"), ImageFormat::Ico)?;
-        tree.as_deref_mut().map(|t| t.add_empty_child("banner.png".to_string()));
-        tree.as_deref_mut().map(|t| t.add_empty_child("logo.png".to_string()));
-        tree.as_deref_mut().map(|t| t.add_empty_child("favicon.ico".to_string()));
+        for (file_name, url, format) in ASSETS {
+            let bytes = reqwest::blocking::get(url).and_then(|r| r.bytes());
+            report.as_deref_mut().map(|r| r.fetch(url, bytes.is_ok()));
+            let bytes =
+                bytes.map_err(|e| Error::Network { url: url.to_string(), source: e.into() })?;
+            let image = image::load_from_memory_with_format(&bytes, format)?;
+            image.save_with_format(dir.join("etc").join(file_name), format)?;
+            report.as_deref_mut().map(|r| r.created(file_name));
+        }
     }
-    tree.map(|t| t.end_child());
+    report.map(|r| r.end_dir());
     Ok(())
 }
 
@@ -1,6 +1,9 @@
-use anyhow::Result;
-use ptree::TreeBuilder;
-use std::{path::Path, process::Command};
+use crate::{
+    command::{CommandRunner, Tool},
+    error::Result,
+    report::Report,
+};
+use std::path::Path;
 use tracing::instrument;
 
 /// Constructs the git repository url from the given github username and repository name.
@@ -13,60 +16,38 @@ pub fn build_repository_url(github_username: &str, repository_name: &str) -> Str
 pub fn build_git_remote_target(github_username: &str, repository_name: &str) -> String {
     format!("{}.git", build_repository_url(github_username, repository_name))
 }
-/// Attempts to retrieve the current git username.
-pub fn try_git_username() -> Option<String> {
-    match Command::new("git").arg("config").arg("--get").arg("user.name").output() {
-        Ok(output) => {
-            if output.status.success() {
-                let name = String::from_utf8(output.stdout).ok()?;
-                Some(name.trim().to_string())
-            } else {
-                None
-            }
-        }
-        Err(_) => None,
-    }
+/// Attempts to retrieve the current git username with `git config --get user.name`.
+pub fn try_git_username(runner: &dyn CommandRunner) -> Option<String> {
+    let output = runner.run(Tool::Git, &["config", "--get", "user.name"], Path::new("."), None);
+    let name = output.ok()?.stdout.trim().to_string();
+    (!name.is_empty()).then_some(name)
 }
 
 /// Create git repository with given github username. If github username is not specified, try to
 /// grab one
-#[instrument(name = "git", skip(dir, dry, user, tree))]
+#[instrument(name = "git", skip(dir, dry, user, runner, report))]
 pub fn create(
     dir: &Path,
     dry: bool,
     user: Option<String>,
-    tree: Option<&mut TreeBuilder>,
+    runner: &dyn CommandRunner,
+    mut report: Option<&mut Report>,
 ) -> Result<()> {
     crate::utils::create_dir_gracefully!(dir, dry);
 
     if !dry {
-        // Execute the `cargo init --bin` command in the given directory.
-        tracing::debug!("Executing `git init -b main` in {:?}", dir);
-        let output = std::process::Command::new("git")
-            .arg("init")
-            .arg("-b")
-            .arg("main")
-            .current_dir(dir)
-            .output()?;
-        tracing::debug!("`git init -b main` output: {:?}", output);
+        // Execute the `git init -b main` command in the given directory.
+        runner.run(Tool::Git, &["init", "-b", "main"], dir, report.as_deref_mut())?;
     }
-    tree.map(|t| t.add_empty_child(".git".to_string()));
+    report.as_deref_mut().map(|r| r.begin_dir(".git").end_dir());
 
     if !dry {
         // Setting the remote origin for the git repository.
         let origin = build_git_remote_target(
-            &user.unwrap_or_else(|| try_git_username().unwrap_or_default()),
+            &user.unwrap_or_else(|| try_git_username(runner).unwrap_or_default()),
             dir.file_name().unwrap().to_str().unwrap(),
         );
-        tracing::debug!("Executing `git remote add origin {}` in {:?}", origin, dir);
-        let output = std::process::Command::new("git")
-            .arg("remote")
-            .arg("add")
-            .arg("origin")
-            .arg(&origin)
-            .current_dir(dir)
-            .output()?;
-        tracing::debug!("`git remote add origin` output: {:?}", output);
+        runner.run(Tool::Git, &["remote", "add", "origin", &origin], dir, report)?;
     }
     Ok(())
 }
@@ -76,15 +57,61 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
+    use crate::command::{tests::MockRunner, SystemRunner};
 
     #[test]
     fn test_create() {
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
         let package_dir = dir_path_buf.join("example");
-        create(&package_dir, false, None, None).unwrap();
+        create(&package_dir, false, None, &SystemRunner::default(), None).unwrap();
 
         assert!(package_dir.exists());
         assert!(package_dir.join(".git").exists());
     }
+
+    #[test]
+    fn test_create_report() {
+        let dir = tempdir().unwrap();
+        let package_dir = dir.path().join("example");
+        let mut report = Report::new("example", false);
+        let runner = MockRunner::default();
+        create(&package_dir, false, Some("refcell".to_string()), &runner, Some(&mut report))
+            .unwrap();
+
+        assert_eq!(report.commands.len(), 2);
+        assert_eq!(report.commands[0].program, "mock-git");
+        assert_eq!(report.commands[0].args, vec!["init", "-b", "main"]);
+        assert!(report.commands[0].success);
+        assert_eq!(
+            report.commands[1].args,
+            vec!["remote", "add", "origin", "https://github.com/refcell/example.git"]
+        );
+        assert_eq!(report.files[0].path, std::path::PathBuf::from(".git"));
+        assert!(report.files[0].directory);
+    }
+
+    #[test]
+    fn test_create_failed_git_init() {
+        let dir = tempdir().unwrap();
+        let package_dir = dir.path().join("example");
+        let runner = MockRunner { fail: Some(Tool::Git), ..Default::default() };
+        let err =
+            create(&package_dir, false, Some("refcell".to_string()), &runner, None).unwrap_err();
+        assert!(err.to_string().contains("git init -b main` exited with status 101"));
+        // The remote is never added once `git init` fails.
+        assert_eq!(runner.calls.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_try_git_username() {
+        let runner = MockRunner { stdout: "refcell\n".to_string(), ..Default::default() };
+        assert_eq!(try_git_username(&runner), Some("refcell".to_string()));
+        let args = vec!["config".to_string(), "--get".to_string(), "user.name".to_string()];
+        assert_eq!(*runner.calls.borrow(), vec![(Tool::Git, args)]);
+
+        assert_eq!(try_git_username(&MockRunner::default()), None);
+        let runner = MockRunner { fail: Some(Tool::Git), ..Default::default() };
+        assert_eq!(try_git_username(&runner), None);
+    }
 }
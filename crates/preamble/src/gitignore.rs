@@ -1,14 +1,20 @@
-use anyhow::Result;
-use ptree::TreeBuilder;
+use crate::{
+    error::Result,
+    report::{FileAction, Report},
+};
 use std::{io::Write, path::Path};
 use tracing::instrument;
 
 /// Creates a new gitignore file in the given directory.
-#[instrument(name = "gitignore", skip(dir, dry, tree))]
-pub fn create(dir: &Path, dry: bool, tree: Option<&mut TreeBuilder>) -> Result<()> {
+#[instrument(name = "gitignore", skip(dir, dry, report))]
+pub fn create(dir: &Path, dry: bool, report: Option<&mut Report>) -> Result<()> {
     tracing::info!("Creating a .gitignore file");
     crate::utils::create_dir_gracefully!(dir, dry);
 
+    let action = match dir.join(".gitignore").exists() {
+        true => FileAction::Appended,
+        false => FileAction::Created,
+    };
     if !dry {
         tracing::debug!("Writing gitignore to {:?}", dir.join(".gitignore"));
         let mut file =
@@ -17,7 +23,7 @@ pub fn create(dir: &Path, dry: bool, tree: Option<&mut TreeBuilder>) -> Result<(
         file.write_all(rust_gitignore.as_bytes())?;
     }
 
-    tree.map(|t| t.add_empty_child(".gitignore".to_string()));
+    report.map(|r| r.file(".gitignore", action));
     Ok(())
 }
 
@@ -45,4 +51,15 @@ mod tests {
         let second_content_length = package_dir.join(".gitignore").metadata().unwrap().len();
         assert_eq!(second_content_length, 2 * first_content_length);
     }
+
+    #[test]
+    fn test_create_gitignore_report() {
+        let dir = tempdir().unwrap();
+        let package_dir = dir.path().join("example");
+        let mut report = Report::new("example", false);
+        create(&package_dir, false, Some(&mut report)).unwrap();
+        create(&package_dir, false, Some(&mut report)).unwrap();
+        assert_eq!(report.files[0].action, FileAction::Created);
+        assert_eq!(report.files[1].action, FileAction::Appended);
+    }
 }
@@ -17,6 +17,26 @@ fn main() {
     telemetry::init_tracing_subscriber(0).unwrap();
 }
 ```
+
+Workspaces and standalone crates are generated with the [builder::WorkspaceBuilder]
+and [builder::CrateBuilder]. Building returns a [report::Report] of the files,
+commands and fetches that were planned (in dry run mode) or applied.
+
+```ignore,rust
+use preamble::builder::{Author, Dependency, License, WorkspaceBuilder};
+
+fn main() -> preamble::Result<()> {
+    let report = WorkspaceBuilder::new("my-project", "example")
+        .author(Author::new("refcell").email("refcell@example.com"))
+        .license(License::new("Apache-2.0"))
+        .dependency(Dependency::new("serde").version("1.0").features(["derive"]))
+        .gitignore(true)
+        .dry_run(true)
+        .build()?;
+    println!("{}", report.to_json().unwrap());
+    Ok(())
+}
+```
 */
 
 #![doc(
@@ -28,15 +48,34 @@ fn main() {
 #![deny(unused_must_use, rust_2018_idioms)]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+/// Global Allocator Features
+pub mod allocator;
+
 /// Workspace Binary Crate Builders
 pub mod bins;
 
+/// Workspace and Crate Builders
+pub mod builder;
+
 /// Cargo Manifest Module
 pub mod cargo;
 
+/// Github Actions Workflow Checks
+pub mod check;
+
 /// Github Actions Workflow Module
 pub mod ci;
 
+/// External Command Runner
+pub mod command;
+
+/// Cross-Compiled Release Generation
+pub mod cross;
+
+/// Preamble Error Types
+pub mod error;
+pub use error::{Error, Result};
+
 /// Et Cetera Directory Handlers
 pub mod etc;
 
@@ -52,15 +91,39 @@ pub mod libs;
 /// License File Helpers
 pub mod license;
 
+/// Workspace Lint Profiles
+pub mod lints;
+
+/// CI Pipeline Generation
+pub mod pipeline;
+
+/// Cargo Profile Presets
+pub mod profiles;
+
+/// Workspace Release Planning
+pub mod release;
+
+/// Run Report Module
+pub mod report;
+
 /// Root-level Crate Helpers
 pub mod root;
 
+/// Supply Chain Policy Files
+pub mod supply_chain;
+
 /// Telemetry
 ///
-/// The telemetry module is used by workspace projects generated by amble to
-/// automatically set up tracing without needing to duplicate tracing subscriber
-/// setup code in every project.
+/// The telemetry module re-exports the [amble_telemetry] runtime crate, which
+/// projects generated by amble can depend on to set up tracing without needing
+/// to duplicate tracing subscriber setup code in every project.
 pub mod telemetry;
 
+/// Rust Tooling Config Files
+pub mod tooling;
+
+/// Dependency Update Bot Configuration
+pub mod updates;
+
 /// Utility Functions
 pub mod utils;
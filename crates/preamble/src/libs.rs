@@ -1,22 +1,17 @@
 use std::{io::Write, path::Path};
 
-use anyhow::Result;
-use ptree::TreeBuilder;
+use crate::{error::Result, report::Report};
 use tracing::instrument;
 
-/// Returns the lib contents.
-pub fn lib_contents() -> &'static str {
-    r#"#![doc = include_str!("../README.md")]
-#![warn(
-    missing_debug_implementations,
-    missing_docs,
-    unreachable_pub,
-    rustdoc::all
-)]
-#![deny(unused_must_use, rust_2018_idioms)]
+/// The crate-level attributes written at the top of every generated `lib.rs`.
+///
+/// Lints are configured in the manifest by a [crate::lints::LintProfile].
+pub const LIB_ATTRIBUTES: &str = r#"#![doc = include_str!("../README.md")]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+"#;
 
-/// Adds two [usize] numbers together.
+/// The example library body written below the [LIB_ATTRIBUTES].
+pub const LIB_BODY: &str = r#"/// Adds two [usize] numbers together.
 pub fn add(left: usize, right: usize) -> usize {
     left + right
 }
@@ -30,16 +25,38 @@ mod tests {
         let result = add(2, 2);
         assert_eq!(result, 4);
     }
-}"#
+}
+"#;
+
+/// The `amble-telemetry` version workspace library crates depend on.
+///
+/// Generated workspaces only resolve if `amble-telemetry` is published at
+/// the preamble version, so both inherit the workspace version and are
+/// published by the same `cargo release` of the amble workspace.
+pub const TELEMETRY_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Returns the lib contents.
+pub fn lib_contents() -> String {
+    format!("{}\n{}", LIB_ATTRIBUTES, LIB_BODY)
+}
+
+/// Returns the lib contents for a workspace library crate.
+/// Workspace libraries re-export `amble-telemetry` as the `telemetry`
+/// module that the workspace binaries use to set up tracing.
+pub fn workspace_lib_contents() -> String {
+    format!(
+        "{}\n/// Telemetry\npub use amble_telemetry as telemetry;\n\n{}",
+        LIB_ATTRIBUTES, LIB_BODY
+    )
 }
 
 /// Creates a new lib crate.
-#[instrument(name = "lib", skip(dir, name, dry, tree))]
+#[instrument(name = "lib", skip(dir, name, dry, report))]
 pub fn create(
     dir: &Path,
     name: impl AsRef<str>,
     dry: bool,
-    mut tree: Option<&mut TreeBuilder>,
+    mut report: Option<&mut Report>,
 ) -> Result<()> {
     tracing::info!("Creating lib crate");
 
@@ -51,33 +68,33 @@ pub fn create(
 
     crate::utils::create_dir_gracefully!(src_path_buf, dry);
 
-    tree.as_deref_mut().map(|t| t.begin_child("crates".to_string()));
-    tree.as_deref_mut().map(|t| t.begin_child(name.as_ref().to_string()));
+    report.as_deref_mut().map(|r| r.begin_dir("crates"));
+    report.as_deref_mut().map(|r| r.begin_dir(name.as_ref()));
 
     if !dry {
         tracing::debug!("Writing {:?}", cargo_toml_path_buf);
         fill_cargo(&cargo_toml_path_buf, name.as_ref())?;
     }
-    tree.as_deref_mut().map(|t| t.add_empty_child("Cargo.toml".to_string()));
+    report.as_deref_mut().map(|r| r.created("Cargo.toml"));
 
     if !dry {
         tracing::debug!("Writing {:?}", readme_path_buf);
         std::fs::write(&readme_path_buf, format!("# {}", name.as_ref()))?;
     }
-    tree.as_deref_mut().map(|t| t.add_empty_child("README.md".to_string()));
-    tree.as_deref_mut().map(|t| t.begin_child("src".to_string()));
+    report.as_deref_mut().map(|r| r.created("README.md"));
+    report.as_deref_mut().map(|r| r.begin_dir("src"));
 
     if !dry {
         tracing::debug!("Writing {:?}", lib_rs_path_buf);
-        let lib_contents = lib_contents();
+        let lib_contents = workspace_lib_contents();
         let mut lib_rs = std::fs::File::create(&lib_rs_path_buf)?;
         lib_rs.write_all(lib_contents.as_bytes())?;
     }
-    tree.as_deref_mut().map(|t| t.add_empty_child("lib.rs".to_string()));
+    report.as_deref_mut().map(|r| r.created("lib.rs"));
 
-    tree.as_deref_mut().map(|t| t.end_child()); // <- src/
-    tree.as_deref_mut().map(|t| t.end_child()); // <- <name>/
-    tree.map(|t| t.end_child()); // <- crates
+    report.as_deref_mut().map(|r| r.end_dir()); // <- src/
+    report.as_deref_mut().map(|r| r.end_dir()); // <- <name>/
+    report.map(|r| r.end_dir()); // <- crates
 
     Ok(())
 }
@@ -104,14 +121,8 @@ pub fn fill_cargo(file: &Path, name: &str) -> Result<()> {
     manifest["package"]["homepage"]["workspace"] = toml_edit::value(true);
 
     manifest["dependencies"] = toml_edit::Item::Table(toml_edit::Table::new());
-    manifest["dependencies"]["serde"] = inline.clone();
-    manifest["dependencies"]["serde"]["workspace"] = toml_edit::value(true);
-    manifest["dependencies"]["serde_json"] = inline.clone();
-    manifest["dependencies"]["serde_json"]["workspace"] = toml_edit::value(true);
-    manifest["dependencies"]["anyhow"] = inline.clone();
-    manifest["dependencies"]["anyhow"]["workspace"] = toml_edit::value(true);
-    manifest["dependencies"]["tracing"] = inline.clone();
-    manifest["dependencies"]["tracing"]["workspace"] = toml_edit::value(true);
+    manifest["dependencies"]["amble-telemetry"] = inline.clone();
+    manifest["dependencies"]["amble-telemetry"]["workspace"] = toml_edit::value(true);
 
     let mut file = std::fs::File::create(file)?;
     file.write_all(manifest.to_string().as_bytes())?;
@@ -125,6 +136,30 @@ mod tests {
     use std::{fs::File, io::Read};
     use tempfile::tempdir;
 
+    #[test]
+    fn test_telemetry_released_with_preamble() {
+        let crates = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let workspace = crates.parent().unwrap();
+        let root = crate::root::parse_manifest(&workspace.join("Cargo.toml")).unwrap();
+        assert_eq!(root["workspace"]["package"]["version"].as_str(), Some(TELEMETRY_VERSION));
+        let members = root["workspace"]["members"].as_array().unwrap();
+        assert!(members.iter().any(|m| m.as_str() == Some("crates/*")));
+
+        let telemetry =
+            crate::root::parse_manifest(&crates.join("telemetry").join("Cargo.toml")).unwrap();
+        assert_eq!(telemetry["package"]["name"].as_str(), Some("amble-telemetry"));
+        assert_eq!(telemetry["package"]["version"]["workspace"].as_bool(), Some(true));
+        assert!(telemetry["package"].get("publish").is_none());
+
+        // The release publishes every workspace member rather than a selection.
+        let release = workspace.join(".github").join("workflows").join("release.yml");
+        let release = std::fs::read_to_string(release).unwrap();
+        let command = release.lines().find(|l| l.contains("cargo release")).unwrap();
+        assert!(command.contains("--execute"));
+        assert!(!command.contains(" -p ") && !command.contains("--package"));
+        assert!(!command.contains("--exclude"));
+    }
+
     #[test]
     fn test_fill_cargo() {
         let dir = tempdir().unwrap();
@@ -148,10 +183,7 @@ repository = { workspace = true }
 homepage = { workspace = true }
 
 [dependencies]
-serde = { workspace = true }
-serde_json = { workspace = true }
-anyhow = { workspace = true }
-tracing = { workspace = true }
+amble-telemetry = { workspace = true }
 "#;
         assert_eq!(cargo_toml_contents, expected_contents);
     }
@@ -174,7 +206,8 @@ tracing = { workspace = true }
         let mut lib_rs = File::open(project_path.join("src").join("lib.rs")).unwrap();
         let mut lib_rs_contents = String::new();
         lib_rs.read_to_string(&mut lib_rs_contents).unwrap();
-        assert!(lib_rs_contents.len() > 0);
+        assert!(!lib_rs_contents.is_empty());
+        assert!(lib_rs_contents.contains("pub use amble_telemetry as telemetry;"));
     }
 
     #[test]
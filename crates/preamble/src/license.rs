@@ -1,88 +1,732 @@
-use aho_corasick::AhoCorasick;
-use anyhow::Result;
+use crate::{
+    command::SystemRunner,
+    error::{Error, Result},
+    report::Report,
+};
+use aho_corasick::{AhoCorasick, MatchKind};
 use chrono::Datelike;
-use ptree::TreeBuilder;
-use std::{io::Write, path::Path};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 use tracing::instrument;
 
 /// The MIT License.
 pub const MIT_LICENSE: &str = "MIT License\n\nCopyright (c) [year] [fullname]\n\nPermission is hereby granted, free of charge, to any person obtaining a copy\nof this software and associated documentation files (the \"Software\"), to deal\nin the Software without restriction, including without limitation the rights\nto use, copy, modify, merge, publish, distribute, sublicense, and/or sell\ncopies of the Software, and to permit persons to whom the Software is\nfurnished to do so, subject to the following conditions:\n\nThe above copyright notice and this permission notice shall be included in all\ncopies or substantial portions of the Software.\n\nTHE SOFTWARE IS PROVIDED \"AS IS\", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR\nIMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,\nFITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE\nAUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER\nLIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,\nOUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE\nSOFTWARE.\n";
 
+/// A SPDX license text embedded in preamble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddedLicense {
+    /// The SPDX license identifiers sharing this license text.
+    pub ids: &'static [&'static str],
+    /// The full license name.
+    pub name: &'static str,
+    /// The license text with `<year>` and `<copyright holders>` style placeholders.
+    pub text: &'static str,
+    /// The placeholders in the license text, see [Copyright::impute].
+    pub placeholders: &'static [(&'static str, &'static str)],
+}
+
+/// The `<year> <owner>` placeholders of the BSD and ISC licenses.
+const OWNER_PLACEHOLDERS: &[(&str, &str)] = &[("<year>", "{year}"), ("<owner>", "{holder}")];
+
+/// The common placeholders imputed in licenses that aren't embedded.
+pub const GENERIC_PLACEHOLDERS: &[(&str, &str)] = &[
+    ("<year>", "{year}"),
+    ("[year]", "{year}"),
+    ("[yyyy]", "{year}"),
+    ("<fullname>", "{holder}"),
+    ("[fullname]", "{holder}"),
+    ("<copyright holders>", "{holder}"),
+    ("<copyright holder>", "{holder}"),
+    ("<owner>", "{holder}"),
+    ("[name of copyright owner]", "{holder}"),
+    ("<organization>", "{organization}"),
+];
+
+/// The common SPDX license texts from the SPDX license list, available offline.
+pub static EMBEDDED_LICENSES: [EmbeddedLicense; 13] = [
+    EmbeddedLicense {
+        ids: &["MIT"],
+        name: "MIT License",
+        text: include_str!("../etc/licenses/MIT.txt"),
+        placeholders: &[("<year>", "{year}"), ("<copyright holders>", "{holder}")],
+    },
+    EmbeddedLicense {
+        ids: &["Apache-2.0"],
+        name: "Apache License 2.0",
+        text: include_str!("../etc/licenses/Apache-2.0.txt"),
+        placeholders: &[("[yyyy]", "{year}"), ("[name of copyright owner]", "{holder}")],
+    },
+    EmbeddedLicense {
+        ids: &["BSD-2-Clause"],
+        name: "BSD 2-Clause \"Simplified\" License",
+        text: include_str!("../etc/licenses/BSD-2-Clause.txt"),
+        placeholders: OWNER_PLACEHOLDERS,
+    },
+    EmbeddedLicense {
+        ids: &["BSD-3-Clause"],
+        name: "BSD 3-Clause \"New\" or \"Revised\" License",
+        text: include_str!("../etc/licenses/BSD-3-Clause.txt"),
+        placeholders: OWNER_PLACEHOLDERS,
+    },
+    EmbeddedLicense {
+        ids: &["MPL-2.0"],
+        name: "Mozilla Public License 2.0",
+        text: include_str!("../etc/licenses/MPL-2.0.txt"),
+        placeholders: &[],
+    },
+    EmbeddedLicense {
+        ids: &["GPL-2.0-only", "GPL-2.0-or-later", "GPL-2.0"],
+        name: "GNU General Public License v2.0",
+        text: include_str!("../etc/licenses/GPL-2.0.txt"),
+        placeholders: &[],
+    },
+    EmbeddedLicense {
+        ids: &["GPL-3.0-only", "GPL-3.0-or-later", "GPL-3.0"],
+        name: "GNU General Public License v3.0",
+        text: include_str!("../etc/licenses/GPL-3.0.txt"),
+        placeholders: &[],
+    },
+    EmbeddedLicense {
+        ids: &["LGPL-2.1-only", "LGPL-2.1-or-later", "LGPL-2.1"],
+        name: "GNU Lesser General Public License v2.1",
+        text: include_str!("../etc/licenses/LGPL-2.1.txt"),
+        placeholders: &[],
+    },
+    EmbeddedLicense {
+        ids: &["LGPL-3.0-only", "LGPL-3.0-or-later", "LGPL-3.0"],
+        name: "GNU Lesser General Public License v3.0",
+        text: include_str!("../etc/licenses/LGPL-3.0.txt"),
+        placeholders: &[],
+    },
+    EmbeddedLicense {
+        ids: &["AGPL-3.0-only", "AGPL-3.0-or-later", "AGPL-3.0"],
+        name: "GNU Affero General Public License v3.0",
+        text: include_str!("../etc/licenses/AGPL-3.0.txt"),
+        placeholders: &[],
+    },
+    EmbeddedLicense {
+        ids: &["ISC"],
+        name: "ISC License",
+        text: include_str!("../etc/licenses/ISC.txt"),
+        placeholders: OWNER_PLACEHOLDERS,
+    },
+    EmbeddedLicense {
+        ids: &["Unlicense"],
+        name: "The Unlicense",
+        text: include_str!("../etc/licenses/Unlicense.txt"),
+        placeholders: &[],
+    },
+    EmbeddedLicense {
+        ids: &["0BSD"],
+        name: "BSD Zero Clause License",
+        text: include_str!("../etc/licenses/0BSD.txt"),
+        placeholders: &[("YEAR by AUTHOR EMAIL", "{year} by {holder}")],
+    },
+];
+
+/// Finds an embedded license by SPDX identifier, ignoring case.
+pub fn find_embedded_license(id: &str) -> Option<&'static EmbeddedLicense> {
+    EMBEDDED_LICENSES.iter().find(|l| l.ids.iter().any(|i| i.eq_ignore_ascii_case(id.trim())))
+}
+
+/// Returns the placeholders of the license text for the SPDX identifier.
+pub fn license_placeholders(id: &str) -> &'static [(&'static str, &'static str)] {
+    find_embedded_license(id).map(|l| l.placeholders).unwrap_or(GENERIC_PLACEHOLDERS)
+}
+
+/// The copyright year, holders and organization imputed in license texts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Copyright {
+    /// The copyright year.
+    pub year: i32,
+    /// The copyright holders.
+    pub holders: Vec<String>,
+    /// The organization, for licenses naming one.
+    pub organization: Option<String>,
+}
+
+impl Default for Copyright {
+    fn default() -> Self {
+        Self::new(&None)
+    }
+}
+
+impl Copyright {
+    /// Creates a new [Copyright] for the current year, held by the given
+    /// authors or the current git user.
+    pub fn new(authors: &Option<Vec<String>>) -> Self {
+        let holders = match authors {
+            // Authors may be formatted as `name <email>`.
+            Some(v) if !v.is_empty() => {
+                v.iter().map(|a| a.split(" <").next().unwrap_or_default().to_string()).collect()
+            }
+            _ => vec![crate::root::get_current_username(&None, &SystemRunner::default())],
+        };
+        Self { year: chrono::Utc::now().year(), holders, organization: None }
+    }
+
+    /// Sets the organization.
+    pub fn organization(mut self, organization: Option<String>) -> Self {
+        self.organization = organization;
+        self
+    }
+
+    /// Returns the copyright holders, comma separated.
+    pub fn holder(&self) -> String {
+        self.holders.join(", ")
+    }
+
+    /// Replaces the placeholders in the license text with their templates,
+    /// rendered with the `year`, `holder` and `organization` values.
+    /// Placeholders are matched ignoring case.
+    ///
+    /// Returns the imputed text and the placeholders that couldn't be filled,
+    /// e.g. `<organization>` without an organization, which are left as is.
+    pub fn impute(&self, text: &str, placeholders: &[(&str, &str)]) -> (String, Vec<String>) {
+        let ac = AhoCorasick::builder()
+            .ascii_case_insensitive(true)
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(placeholders.iter().map(|(p, _)| p))
+            .unwrap();
+        let mut values = std::collections::HashMap::new();
+        values.insert("year", self.year.to_string());
+        values.insert("holder", self.holder());
+        if let Some(organization) = &self.organization {
+            values.insert("organization", organization.clone());
+        }
+        let mut unfilled = Vec::new();
+        let mut result = String::new();
+        ac.replace_all_with(text, &mut result, |mat, found, dst| {
+            let template = placeholders[mat.pattern().as_usize()].1;
+            match leon::Template::parse(template).ok().and_then(|t| t.render(&values).ok()) {
+                Some(value) => dst.push_str(&value),
+                None => {
+                    if !unfilled.iter().any(|u| u == found) {
+                        unfilled.push(found.to_string());
+                    }
+                    dst.push_str(found);
+                }
+            }
+            true
+        });
+        (result, unfilled)
+    }
+}
+
+/// Lists the embedded licenses.
+pub fn list_licenses() -> Result<()> {
+    let mut table = prettytable::Table::new();
+    table.add_row(prettytable::Row::new(vec![
+        prettytable::Cell::new("SPDX Identifier"),
+        prettytable::Cell::new("Name"),
+    ]));
+    for license in EMBEDDED_LICENSES.iter() {
+        table.add_row(prettytable::Row::new(vec![
+            prettytable::Cell::new(&license.ids.join(", ")),
+            prettytable::Cell::new(license.name),
+        ]));
+    }
+    table.printstd();
+    println!("Other SPDX license identifiers are fetched from the SPDX license list.");
+    Ok(())
+}
+
+/// A SPDX license expression, e.g. `MIT` or `MIT OR Apache-2.0`.
+///
+/// Identifiers of [EMBEDDED_LICENSES] and operators are normalized to their
+/// canonical case, so `mit or apache-2.0` becomes `MIT OR Apache-2.0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct License(String);
+
+impl License {
+    /// Creates a new [License] from a SPDX license expression without validating it.
+    pub fn new(expression: impl AsRef<str>) -> Self {
+        Self(join_tokens(&tokenize(expression.as_ref())))
+    }
+
+    /// Returns the normalized SPDX license expression.
+    pub fn expression(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the license identifiers in the expression, excluding `WITH` exceptions.
+    pub fn ids(&self) -> Vec<String> {
+        let tokens = tokenize(&self.0);
+        let mut ids = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            let exception = i > 0 && tokens[i - 1] == "WITH";
+            if !exception && !is_operator(token) && !ids.contains(token) {
+                ids.push(token.clone());
+            }
+        }
+        ids
+    }
+
+    /// Returns the license identifiers the project is unconditionally under,
+    /// i.e. the identifiers required by every branch of an `OR` expression.
+    ///
+    /// `GPL-3.0-only AND MIT` requires both licenses, while
+    /// `GPL-3.0-only OR MIT` requires neither.
+    pub fn required_ids(&self) -> Vec<String> {
+        required_ids(&tokenize(&self.0), &mut 0)
+    }
+
+    /// Returns the `(identifier, file name)` pairs of the license files.
+    ///
+    /// A single license is written to `LICENSE`, while each license of a
+    /// compound expression is written to `LICENSE-<ID>`, e.g. `LICENSE-MIT`
+    /// and `LICENSE-APACHE` for `MIT OR Apache-2.0`.
+    pub fn files(&self) -> Vec<(String, String)> {
+        let ids = self.ids();
+        if ids.len() == 1 {
+            return ids.into_iter().map(|id| (id, "LICENSE".to_string())).collect();
+        }
+        let suffixes = ids.iter().map(|id| file_suffix(id)).collect::<Vec<_>>();
+        ids.iter()
+            .zip(suffixes.iter())
+            .map(|(id, suffix)| {
+                // Fall back to the full identifier if the short suffixes collide.
+                match suffixes.iter().filter(|s| *s == suffix).count() {
+                    1 => (id.clone(), format!("LICENSE-{}", suffix)),
+                    _ => (id.clone(), format!("LICENSE-{}", id.to_uppercase())),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the expression with the license identifier replaced.
+    fn replace_id(&self, id: &str, replacement: &str) -> Self {
+        let tokens = tokenize(&self.0)
+            .into_iter()
+            .map(|t| if t == id { replacement.to_string() } else { t })
+            .collect::<Vec<_>>();
+        Self(join_tokens(&tokens))
+    }
+
+    /// Returns the shields.io badge label for the expression.
+    pub fn badge(&self) -> String {
+        self.0.replace('-', "--").replace('_', "__").replace(' ', "%20")
+    }
+
+    /// Returns the link target for the license, the license file for a
+    /// single license or the readme License section otherwise.
+    pub fn link(&self) -> String {
+        match self.files().as_slice() {
+            [(_, file)] => file.clone(),
+            _ => "#license".to_string(),
+        }
+    }
+
+    /// Returns the readme License section body, linking each license file.
+    pub fn readme_section(&self) -> String {
+        let links = self
+            .files()
+            .into_iter()
+            .map(|(id, file)| {
+                let name = find_embedded_license(&id).map(|l| l.name).unwrap_or(&id);
+                format!("[{}]({})", name, file)
+            })
+            .collect::<Vec<_>>();
+        let tokens = tokenize(&self.0);
+        let operators = tokens.iter().filter(|t| is_operator(t)).collect::<Vec<_>>();
+        let list = links.iter().map(|l| format!("- {}", l)).collect::<Vec<_>>().join("\n");
+        match links.as_slice() {
+            [link] if operators.is_empty() => {
+                format!("This project is licensed under the {}.", link)
+            }
+            _ if operators.iter().all(|o| *o == "OR") => {
+                format!("This project is licensed under either of\n\n{}\n\nat your option.", list)
+            }
+            _ if operators.iter().all(|o| *o == "AND") => {
+                format!("This project is licensed under all of\n\n{}", list)
+            }
+            _ => format!("This project is licensed under `{}`.\n\n{}", self.0, list),
+        }
+    }
+}
+
+impl Default for License {
+    fn default() -> Self {
+        Self::new("MIT")
+    }
+}
+
+impl std::str::FromStr for License {
+    type Err = Error;
+
+    /// Parses and normalizes a SPDX license expression.
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s);
+        let invalid = |reason: &str| {
+            Err(Error::License(format!("Invalid SPDX license expression \"{}\": {}", s, reason)))
+        };
+        if tokens.is_empty() {
+            return invalid("expected a license identifier");
+        }
+        let mut depth = 0usize;
+        let mut expect_id = true;
+        for token in tokens.iter() {
+            match token.as_str() {
+                "(" if expect_id => depth += 1,
+                ")" if !expect_id && depth > 0 => depth -= 1,
+                "AND" | "OR" | "WITH" if !expect_id => expect_id = true,
+                t if expect_id && !is_operator(t) => expect_id = false,
+                t => return invalid(&format!("unexpected `{}`", t)),
+            }
+        }
+        if expect_id || depth > 0 {
+            return invalid("unexpected end of expression");
+        }
+        Ok(Self(join_tokens(&tokens)))
+    }
+}
+
+impl std::fmt::Display for License {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Whether the expression token is an operator or parenthesis.
+fn is_operator(token: &str) -> bool {
+    matches!(token, "AND" | "OR" | "WITH" | "(" | ")")
+}
+
+/// Returns the identifiers required by the `OR` expression starting at `pos`,
+/// advancing `pos` past it. `WITH` binds tighter than `AND`, which binds
+/// tighter than `OR`.
+fn required_ids(tokens: &[String], pos: &mut usize) -> Vec<String> {
+    let mut required = required_and_ids(tokens, pos);
+    while tokens.get(*pos).is_some_and(|t| t == "OR") {
+        *pos += 1;
+        let branch = required_and_ids(tokens, pos);
+        required.retain(|id| branch.contains(id));
+    }
+    required
+}
+
+/// Returns the identifiers required by the `AND` expression starting at `pos`.
+fn required_and_ids(tokens: &[String], pos: &mut usize) -> Vec<String> {
+    let mut required = Vec::new();
+    loop {
+        let operand = match tokens.get(*pos).map(String::as_str) {
+            Some("(") => {
+                *pos += 1;
+                let nested = required_ids(tokens, pos);
+                *pos += 1; // <- )
+                nested
+            }
+            Some(id) => {
+                *pos += 1;
+                if tokens.get(*pos).is_some_and(|t| t == "WITH") {
+                    *pos += 2;
+                }
+                vec![id.to_string()]
+            }
+            None => vec![],
+        };
+        for id in operand {
+            if !required.contains(&id) {
+                required.push(id);
+            }
+        }
+        if !tokens.get(*pos).is_some_and(|t| t == "AND") {
+            return required;
+        }
+        *pos += 1;
+    }
+}
+
+/// Splits a SPDX license expression into normalized tokens.
+fn tokenize(expression: &str) -> Vec<String> {
+    expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(|token| match token.to_uppercase().as_str() {
+            op @ ("AND" | "OR" | "WITH") => op.to_string(),
+            _ => find_embedded_license(token)
+                .and_then(|l| l.ids.iter().find(|i| i.eq_ignore_ascii_case(token)))
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| token.to_string()),
+        })
+        .collect()
+}
+
+/// Joins expression tokens, without spaces inside parentheses.
+fn join_tokens(tokens: &[String]) -> String {
+    tokens.join(" ").replace("( ", "(").replace(" )", ")")
+}
+
+/// Returns the license file suffix for the identifier, e.g. `APACHE` for `Apache-2.0`.
+fn file_suffix(id: &str) -> String {
+    static VERSION: OnceLock<regex::Regex> = OnceLock::new();
+    let re = VERSION.get_or_init(|| regex::Regex::new(r"-\d+(\.\d+)*(-only|-or-later)?$").unwrap());
+    re.replace(id, "").to_uppercase()
+}
+
 /// Helper function to build an MIT License with imputed values.
-pub fn build_mit_license() -> String {
-    impute_license(MIT_LICENSE)
+pub fn build_mit_license(copyright: &Copyright) -> String {
+    impute_license(MIT_LICENSE, copyright)
+}
+
+/// Impute templated license strs with the [GENERIC_PLACEHOLDERS].
+pub fn impute_license(haystack: &str, copyright: &Copyright) -> String {
+    copyright.impute(haystack, GENERIC_PLACEHOLDERS).0
+}
+
+/// The marker identifying a SPDX license header.
+pub const SPDX_HEADER_MARKER: &str = "SPDX-License-Identifier:";
+
+/// Returns the SPDX license header for rust source files.
+pub fn license_header(license: &License, copyright: &Copyright) -> String {
+    format!(
+        "// {} {}\n// Copyright (c) {} {}\n",
+        SPDX_HEADER_MARKER,
+        license,
+        copyright.year,
+        copyright.holder()
+    )
+}
+
+/// Prepends the header to the source file contents, keeping a leading shebang
+/// line first. Returns `None` if the contents already have a SPDX header.
+pub fn prepend_license_header(contents: &str, header: &str) -> Option<String> {
+    if contents.lines().take(5).any(|l| l.contains(SPDX_HEADER_MARKER)) {
+        return None;
+    }
+    // A `#![...]` inner attribute isn't a shebang.
+    let (shebang, rest) = match contents.strip_prefix("#!") {
+        Some(rest) if !rest.trim_start().starts_with('[') => {
+            let end = contents.find('\n').map(|i| i + 1).unwrap_or(contents.len());
+            contents.split_at(end)
+        }
+        _ => ("", contents),
+    };
+    Some(format!("{}{}\n{}", shebang, header, rest))
 }
 
-/// Impute templated license strs with dynamic values.
-pub fn impute_license(haystack: &str) -> String {
-    let patterns = &["<year>", "[year]", "<fullname>", "[fullname]"];
-    let ac = AhoCorasick::builder().ascii_case_insensitive(true).build(patterns).unwrap();
-    let mut result = String::new();
-    ac.replace_all_with(haystack, &mut result, |mat, _, dst| {
-        match mat.pattern().as_usize() {
-            0 | 1 => dst.push_str(&chrono::Utc::now().year().to_string()),
-            2 | 3 => dst.push_str(&crate::root::get_current_username(&None)),
-            _ => unreachable!(),
+/// Recursively finds the `.rs` files in the directory, skipping `target/`
+/// and hidden directories. Paths are relative to the directory.
+pub fn find_rust_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(relative) = dirs.pop() {
+        let mut entries = std::fs::read_dir(dir.join(&relative))?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let path = relative.join(&name);
+            if entry.file_type()?.is_dir() {
+                if name != "target" && !name.starts_with('.') {
+                    dirs.push(path);
+                }
+            } else if name.ends_with(".rs") {
+                files.push(path);
+            }
         }
-        true
-    });
-    result
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Adds the SPDX license header for the [License] to the given `.rs` files,
+/// relative to the directory. Files that already have a SPDX header are left
+/// untouched, so this is idempotent. Returns the files that were changed.
+#[instrument(name = "license_headers", skip(dir, files, license, copyright, dry))]
+pub fn add_license_headers(
+    dir: &Path,
+    files: &[PathBuf],
+    license: &License,
+    copyright: &Copyright,
+    dry: bool,
+) -> Result<Vec<PathBuf>> {
+    tracing::info!("Adding license headers");
+    let header = license_header(license, copyright);
+    let mut changed = Vec::new();
+    for file in files {
+        let contents = std::fs::read_to_string(dir.join(file))?;
+        if let Some(contents) = prepend_license_header(&contents, &header) {
+            if !dry {
+                tracing::debug!("Adding license header to {:?}", file);
+                std::fs::write(dir.join(file), contents)?;
+            }
+            changed.push(file.clone());
+        }
+    }
+    Ok(changed)
+}
+
+/// Reads the `license` field of the package or workspace manifest in the directory.
+pub fn manifest_license(dir: &Path) -> Result<Option<License>> {
+    let license = manifest_field(dir, "license")?;
+    license.as_ref().and_then(|l| l.as_str()).map(|l| l.parse()).transpose()
+}
+
+/// Reads the `authors` field of the package or workspace manifest in the directory.
+pub fn manifest_authors(dir: &Path) -> Result<Option<Vec<String>>> {
+    let authors = manifest_field(dir, "authors")?;
+    Ok(authors
+        .as_ref()
+        .and_then(|a| a.as_array())
+        .map(|a| a.iter().filter_map(|a| a.as_str()).map(String::from).collect()))
 }
 
-/// Creates a new license file in the given directory.
-#[instrument(name = "license", skip(dir, license, dry, tree))]
+/// Reads a package field of the manifest in the directory, falling back to
+/// the `workspace.package` table.
+fn manifest_field(dir: &Path, key: &str) -> Result<Option<toml_edit::Item>> {
+    let manifest = std::fs::read_to_string(dir.join("Cargo.toml"))?;
+    let manifest = manifest.parse::<toml_edit::Document>().map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid Cargo.toml: {}", e))
+    })?;
+    Ok(manifest
+        .get("package")
+        .and_then(|p| p.get(key))
+        .or_else(|| manifest.get("workspace").and_then(|w| w.get("package")?.get(key)))
+        .cloned())
+}
+
+/// Creates the license files for the given [License] in the given directory.
+///
+/// A single license is written to `LICENSE` and each license of a compound
+/// expression to its own `LICENSE-<ID>` file, see [License::files].
+///
+/// The copyright year and holders are imputed with the license's placeholder
+/// table, see [license_placeholders]. Placeholders that can't be filled are
+/// recorded as warnings in the [Report].
+///
+/// Returns the license that was written, with canonical SPDX identifiers, so
+/// the manifest and readme can be kept in sync with the license files. This
+/// is the MIT License if the user chose to fall back to it, and
+/// [Error::UserAborted] is returned if they declined.
+#[instrument(name = "license", skip(dir, license, copyright, dry, report))]
 pub fn create(
     dir: &Path,
-    license: impl AsRef<str>,
+    license: &License,
+    copyright: &Copyright,
     dry: bool,
-    tree: Option<&mut TreeBuilder>,
-) -> Result<()> {
-    tracing::info!("Creating license file");
+    mut report: Option<&mut Report>,
+) -> Result<License> {
+    tracing::info!("Creating license files for {}", license);
     crate::utils::create_dir_gracefully!(dir, dry);
 
-    // Fetch the license in a tokio runtime.
-    let license = match tokio::runtime::Runtime::new()?.block_on(fetch_license(license.as_ref())) {
-        Ok(license) => license
-            .replacen("<year>", &chrono::Utc::now().year().to_string(), 1)
-            .replacen("<copyright holders>", &crate::root::get_current_username(&None), 1),
-        Err(_) => {
-            if !inquire::Confirm::new(&format!(
-                "Failed to query for license \"{}\", do you want to proceed with the MIT License instead?",
-                license.as_ref(),
-            ))
-            .prompt()?
-            {
-                tracing::warn!("User chose not to proceed with the MIT License");
-                return Ok(());
-            }
-            build_mit_license()
+    let files = license.files();
+    let mut written = license.clone();
+    for (id, file_name) in files.iter() {
+        let text = match spdx_license(id, report.as_deref_mut()) {
+            Ok((canonical, text)) => {
+                written = written.replace_id(id, &canonical);
+                let (text, unfilled) = copyright.impute(&text, license_placeholders(&canonical));
+                for placeholder in unfilled {
+                    let warning =
+                        format!("{} has an unfilled placeholder {}", file_name, placeholder);
+                    tracing::warn!("{}", warning);
+                    report.as_deref_mut().map(|r| r.warn(warning));
+                }
+                text
+            }
+            // Only a single license can fall back to the MIT License.
+            Err(err) if files.len() > 1 => return Err(err),
+            Err(err) => {
+                let proceed = inquire::Confirm::new(&format!(
+                    "Failed to query for license \"{}\", do you want to proceed with the MIT License instead?",
+                    id,
+                ))
+                .prompt();
+                // Surface the fetch error when the user can't be prompted.
+                if let Err(inquire::InquireError::NotTTY) = proceed {
+                    return Err(err);
+                }
+                if !proceed? {
+                    tracing::warn!("User chose not to proceed with the MIT License");
+                    return Err(Error::UserAborted(format!(
+                        "User declined the MIT License in place of \"{}\"",
+                        id
+                    )));
+                }
+                written = License::default();
+                build_mit_license(copyright)
+            }
+        };
+
+        if !dry {
+            tracing::debug!("Writing {} license to {:?}", id, dir.join(file_name));
+            let mut file = std::fs::File::create(dir.join(file_name))?;
+            file.write_all(text.as_bytes())?;
         }
-    };
+        report.as_deref_mut().map(|r| r.created(file_name));
+    }
+    Ok(written)
+}
 
-    if !dry {
-        tracing::debug!("Writing MIT license to {:?}", dir.join("LICENSE"));
-        let mut file = std::fs::File::create(dir.join("LICENSE"))?;
-        file.write_all(license.as_bytes())?;
+/// Returns the canonical SPDX identifier and license text, using the embedded
+/// license text if available and only fetching rare licenses in a tokio runtime.
+pub fn spdx_license(id: &str, report: Option<&mut Report>) -> Result<(String, String)> {
+    if let Some(embedded) = find_embedded_license(id) {
+        let canonical = embedded.ids.iter().find(|i| i.eq_ignore_ascii_case(id)).unwrap_or(&id);
+        return Ok((canonical.to_string(), embedded.text.to_string()));
     }
+    let fetched = tokio::runtime::Runtime::new()?.block_on(fetch_spdx_license(id));
+    report.map(|r| r.fetch(spdx_license_url(id), fetched.is_ok()));
+    fetched
+}
 
-    tree.map(|t| t.add_empty_child("LICENSE".to_string()));
-    Ok(())
+/// Returns the SPDX license details url for the given license identifier.
+pub fn spdx_license_url(id: &str) -> String {
+    format!("{}/{}.json", lice::SPDX::BASE_URL, id)
 }
 
-/// Fetch a license using [lice].
+/// Fetch a license text using [lice].
+///
+/// Returns [Error::License] if the identifier doesn't match a SPDX license
+/// and [Error::Network] if the SPDX database can't be queried.
 pub async fn fetch_license(name: impl AsRef<str>) -> Result<String> {
+    fetch_spdx_license(name).await.map(|(_, text)| text)
+}
+
+/// Fetch a license using [lice], returning its SPDX identifier and license text.
+///
+/// [lice] fuzzy matches identifiers, so the matched identifier is checked with
+/// [validate_spdx_id] before it can end up in a manifest.
+pub async fn fetch_spdx_license(name: impl AsRef<str>) -> Result<(String, String)> {
     tracing::debug!("Fetching license from lice");
     let license = lice::get(name.as_ref()).await.map_err(|e| {
         tracing::warn!("Failed to find license \"{}\" in SPDX database", name.as_ref());
-        anyhow::anyhow!(e)
+        // lice doesn't expose typed errors, an unmatched identifier is reported as "no license!".
+        match e.to_string().as_str() {
+            "no license!" => {
+                Error::License(format!("Unknown SPDX license identifier \"{}\"", name.as_ref()))
+            }
+            _ => Error::Network { url: spdx_license_url(name.as_ref()), source: e.into() },
+        }
     })?;
     tracing::debug!("Fetched license from lice");
-    license.license_text.ok_or(anyhow::anyhow!("no license text!"))
+    let id = validate_spdx_id(name.as_ref(), &license.license_id)?;
+    let text = license.license_text.ok_or_else(|| {
+        Error::License(format!("SPDX license \"{}\" has no license text", name.as_ref()))
+    })?;
+    Ok((id, text))
+}
+
+/// Validates that the matched SPDX identifier is the requested one, ignoring
+/// case, and returns the canonical matched identifier.
+pub fn validate_spdx_id(requested: &str, matched: &str) -> Result<String> {
+    match matched.eq_ignore_ascii_case(requested.trim()) {
+        true => Ok(matched.to_string()),
+        false => Err(Error::License(format!(
+            "\"{}\" is not a SPDX license identifier, did you mean \"{}\"?",
+            requested, matched
+        ))),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::root::get_current_username;
     use std::{fs::File, io::Read};
     use tempfile::tempdir;
 
@@ -92,10 +736,14 @@ mod tests {
             .await
             .unwrap()
             .replacen("<year>", &chrono::Utc::now().year().to_string(), 1)
-            .replacen("<copyright holders>", &crate::root::get_current_username(&None), 1);
+            .replacen(
+                "<copyright holders>",
+                &get_current_username(&None, &SystemRunner::default()),
+                1,
+            );
         assert_eq!(
             license.replace("\n\n", " ").replace("\n", " "),
-            build_mit_license().replace("\n\n", " ").replace("\n", " ")
+            build_mit_license(&Copyright::default()).replace("\n\n", " ").replace("\n", " ")
         );
     }
 
@@ -104,8 +752,43 @@ mod tests {
         let haystack = r#"MIT License <year> <fullname>"#;
         let license = haystack
             .replacen("<year>", &chrono::Utc::now().year().to_string(), 1)
-            .replacen("<fullname>", &crate::root::get_current_username(&None), 1);
-        assert_eq!(license, impute_license(haystack));
+            .replacen("<fullname>", &get_current_username(&None, &SystemRunner::default()), 1);
+        assert_eq!(license, impute_license(haystack, &Copyright::default()));
+    }
+
+    #[test]
+    fn test_find_embedded_license() {
+        assert_eq!(find_embedded_license("mit").unwrap().ids, &["MIT"]);
+        assert_eq!(find_embedded_license("APACHE-2.0").unwrap().name, "Apache License 2.0");
+        assert_eq!(
+            find_embedded_license("gpl-3.0-or-later").unwrap(),
+            find_embedded_license("GPL-3.0-only").unwrap()
+        );
+        assert!(find_embedded_license("WTFPL").is_none());
+        for license in EMBEDDED_LICENSES.iter() {
+            assert!(!license.text.is_empty());
+            assert!(license.text.ends_with('\n'));
+        }
+    }
+
+    #[test]
+    fn test_create_embedded_license() {
+        let dir = tempdir().unwrap();
+        let package_dir = dir.path().join("example");
+        let mut report = Report::new("example", false);
+        create(
+            &package_dir,
+            &License::new("apache-2.0"),
+            &Copyright::default(),
+            false,
+            Some(&mut report),
+        )
+        .unwrap();
+
+        let contents = std::fs::read_to_string(package_dir.join("LICENSE")).unwrap();
+        assert!(contents.starts_with("Apache License\nVersion 2.0, January 2004"));
+        // Embedded licenses never hit the network.
+        assert!(report.fetches.is_empty());
     }
 
     #[test]
@@ -113,7 +796,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
         let package_dir = dir_path_buf.join("example");
-        create(&package_dir, "mit", false, None).unwrap();
+        create(&package_dir, &License::new("mit"), &Copyright::default(), false, None).unwrap();
 
         assert!(package_dir.exists());
         assert!(package_dir.join("LICENSE").exists());
@@ -123,7 +806,196 @@ mod tests {
         file.read_to_string(&mut contents).unwrap();
         assert_eq!(
             contents.replace("\n\n", " ").replace("\n", " "),
-            build_mit_license().replace("\n\n", " ").replace("\n", " ")
+            build_mit_license(&Copyright::default()).replace("\n\n", " ").replace("\n", " ")
         );
     }
+
+    #[test]
+    fn test_parse_license_expression() {
+        let license = "mit or apache-2.0".parse::<License>().unwrap();
+        assert_eq!(license.expression(), "MIT OR Apache-2.0");
+        assert_eq!(license.ids(), vec!["MIT", "Apache-2.0"]);
+        assert!(license.required_ids().is_empty());
+
+        let license = License::new("(GPL-3.0-only OR MIT) AND (GPL-3.0-only OR ISC)");
+        assert!(license.required_ids().is_empty());
+        let license = License::new("GPL-3.0-only AND (MIT OR Apache-2.0)");
+        assert_eq!(license.required_ids(), vec!["GPL-3.0-only"]);
+        let license = License::new("(GPL-3.0-only AND MIT) OR (GPL-3.0-only AND ISC)");
+        assert_eq!(license.required_ids(), vec!["GPL-3.0-only"]);
+
+        let license = "( gpl-3.0-or-later WITH Classpath-exception-2.0 ) and isc";
+        let license = license.parse::<License>().unwrap();
+        assert_eq!(license.expression(), "(GPL-3.0-or-later WITH Classpath-exception-2.0) AND ISC");
+        assert_eq!(license.ids(), vec!["GPL-3.0-or-later", "ISC"]);
+        assert_eq!(license.required_ids(), vec!["GPL-3.0-or-later", "ISC"]);
+
+        assert!("".parse::<License>().is_err());
+        assert!("MIT OR".parse::<License>().is_err());
+        assert!("MIT Apache-2.0".parse::<License>().is_err());
+        assert!("(MIT OR Apache-2.0".parse::<License>().is_err());
+    }
+
+    #[test]
+    fn test_validate_spdx_id() {
+        assert_eq!(validate_spdx_id("wtfpl", "WTFPL").unwrap(), "WTFPL");
+        let err = validate_spdx_id("apache", "Apache-1.0").unwrap_err();
+        assert!(matches!(err, Error::License(_)));
+        assert_eq!(
+            err.to_string(),
+            "License error: \"apache\" is not a SPDX license identifier, did you mean \"Apache-1.0\"?"
+        );
+    }
+
+    #[test]
+    fn test_create_returns_canonical_license() {
+        let dir = tempdir().unwrap();
+        let license = License::new("gpl-3.0-or-later OR mit").replace_id("MIT", "mit");
+        assert_eq!(license.expression(), "GPL-3.0-or-later OR mit");
+        let written = create(dir.path(), &license, &Copyright::default(), true, None).unwrap();
+        assert_eq!(written.expression(), "GPL-3.0-or-later OR MIT");
+    }
+
+    #[test]
+    fn test_license_files() {
+        assert_eq!(License::new("mit").files(), vec![("MIT".into(), "LICENSE".into())]);
+        assert_eq!(
+            License::new("MIT OR Apache-2.0").files(),
+            vec![
+                ("MIT".into(), "LICENSE-MIT".into()),
+                ("Apache-2.0".into(), "LICENSE-APACHE".into())
+            ]
+        );
+        // Colliding suffixes fall back to the full identifier.
+        assert_eq!(
+            License::new("GPL-2.0-only OR GPL-3.0-only").files(),
+            vec![
+                ("GPL-2.0-only".into(), "LICENSE-GPL-2.0-ONLY".into()),
+                ("GPL-3.0-only".into(), "LICENSE-GPL-3.0-ONLY".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_license_readme() {
+        let license = License::new("MIT OR Apache-2.0");
+        assert_eq!(license.badge(), "MIT%20OR%20Apache--2.0");
+        assert_eq!(license.link(), "#license");
+        assert_eq!(
+            license.readme_section(),
+            "This project is licensed under either of\n\n- [MIT License](LICENSE-MIT)\n- [Apache License 2.0](LICENSE-APACHE)\n\nat your option."
+        );
+        let license = License::default();
+        assert_eq!(license.link(), "LICENSE");
+        assert_eq!(
+            license.readme_section(),
+            "This project is licensed under the [MIT License](LICENSE)."
+        );
+    }
+
+    #[test]
+    fn test_create_dual_license() {
+        let dir = tempdir().unwrap();
+        let package_dir = dir.path().join("example");
+        let mut report = Report::new("example", false);
+        create(
+            &package_dir,
+            &License::new("MIT OR Apache-2.0"),
+            &Copyright::default(),
+            false,
+            Some(&mut report),
+        )
+        .unwrap();
+
+        assert!(!package_dir.join("LICENSE").exists());
+        let mit = std::fs::read_to_string(package_dir.join("LICENSE-MIT")).unwrap();
+        assert!(mit.starts_with("MIT License"));
+        let apache = std::fs::read_to_string(package_dir.join("LICENSE-APACHE")).unwrap();
+        assert!(apache.starts_with("Apache License"));
+        let files = report.files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert_eq!(files, vec![PathBuf::from("LICENSE-MIT"), PathBuf::from("LICENSE-APACHE")]);
+    }
+
+    #[test]
+    fn test_prepend_license_header() {
+        let header = "// SPDX-License-Identifier: MIT\n// Copyright (c) 2023 refcell\n";
+        let contents = prepend_license_header("fn main() {}\n", header).unwrap();
+        assert_eq!(contents, format!("{}\nfn main() {{}}\n", header));
+        // Adding the header again is a no-op.
+        assert!(prepend_license_header(&contents, header).is_none());
+
+        let contents = prepend_license_header("#!/usr/bin/env rust-script\nfn main() {}", header);
+        assert!(contents.unwrap().starts_with("#!/usr/bin/env rust-script\n// SPDX"));
+        let contents = prepend_license_header("#![no_std]\n", header).unwrap();
+        assert!(contents.starts_with("// SPDX") && contents.ends_with("\n#![no_std]\n"));
+    }
+
+    #[test]
+    fn test_add_license_headers() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("src").join("lib.rs"), "pub mod a;\n").unwrap();
+        std::fs::write(dir.path().join("src").join("a.rs"), "").unwrap();
+        std::fs::write(dir.path().join("target").join("build.rs"), "").unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace.package]\nlicense = \"mit or apache-2.0\"\n",
+        )
+        .unwrap();
+
+        let license = manifest_license(dir.path()).unwrap().unwrap();
+        assert_eq!(license.expression(), "MIT OR Apache-2.0");
+        let files = find_rust_files(dir.path()).unwrap();
+        assert_eq!(files, vec![PathBuf::from("src/a.rs"), PathBuf::from("src/lib.rs")]);
+
+        let changed =
+            add_license_headers(dir.path(), &files, &license, &Copyright::default(), false)
+                .unwrap();
+        assert_eq!(changed, files);
+        let lib_rs = std::fs::read_to_string(dir.path().join("src").join("lib.rs")).unwrap();
+        assert!(
+            lib_rs.starts_with("// SPDX-License-Identifier: MIT OR Apache-2.0\n// Copyright (c) ")
+        );
+        assert!(lib_rs.ends_with("\n\npub mod a;\n"));
+        assert!(add_license_headers(dir.path(), &files, &license, &Copyright::default(), false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_copyright_impute() {
+        let authors = Some(vec!["refcell <r@example.com>".to_string(), "alice".to_string()]);
+        let copyright = Copyright::new(&authors);
+        assert_eq!(copyright.holder(), "refcell, alice");
+
+        let apache = find_embedded_license("Apache-2.0").unwrap();
+        let (text, unfilled) = copyright.impute(apache.text, apache.placeholders);
+        let year = chrono::Utc::now().year();
+        assert!(text.contains(&format!("Copyright {} refcell, alice", year)));
+        assert!(unfilled.is_empty());
+
+        let zero_bsd = find_embedded_license("0BSD").unwrap();
+        let (text, _) = copyright.impute(zero_bsd.text, zero_bsd.placeholders);
+        assert!(text.starts_with(&format!("Copyright (C) {} by refcell, alice\n", year)));
+        assert!(text.contains("THE AUTHOR DISCLAIMS"));
+
+        let text = "Neither the name of <organization> nor <copyright holder>.";
+        let (imputed, unfilled) = copyright.impute(text, GENERIC_PLACEHOLDERS);
+        assert_eq!(imputed, "Neither the name of <organization> nor refcell, alice.");
+        assert_eq!(unfilled, vec!["<organization>"]);
+        let copyright = copyright.organization(Some("Example Inc.".to_string()));
+        let (imputed, unfilled) = copyright.impute(text, GENERIC_PLACEHOLDERS);
+        assert_eq!(imputed, "Neither the name of Example Inc. nor refcell, alice.");
+        assert!(unfilled.is_empty());
+    }
+
+    #[test]
+    fn test_embedded_license_placeholders() {
+        for license in EMBEDDED_LICENSES.iter() {
+            for (placeholder, _) in license.placeholders {
+                assert!(license.text.contains(placeholder), "{} in {}", placeholder, license.name);
+            }
+        }
+    }
 }
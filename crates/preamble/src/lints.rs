@@ -0,0 +1,216 @@
+use std::path::Path;
+
+use crate::{
+    error::Result,
+    root::{member_manifests, parse_manifest},
+};
+
+/// The lint groups, configured with a lower priority than the lints so that
+/// a lint in the group can still be set on its own.
+pub const LINT_GROUPS: [&str; 3] = ["rust_2018_idioms", "all", "pedantic"];
+
+/// The minimum cargo version reading the `[lints]` manifest table.
+pub const LINTS_RUST_VERSION: (u64, u64) = (1, 74);
+
+/// The lint policy written to the manifest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LintProfile {
+    /// Denies ignored results and warns on outdated idioms.
+    Relaxed,
+    /// Warns on missing docs, debug implementations and unreachable `pub`
+    /// items, and denies ignored results and outdated idioms.
+    #[default]
+    Standard,
+    /// The [LintProfile::Standard] lints with missing docs and `unsafe` code
+    /// denied, and the default clippy lints.
+    Strict,
+    /// The [LintProfile::Strict] lints with the pedantic clippy lints.
+    PedanticClippy,
+}
+
+impl std::fmt::Display for LintProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Relaxed => write!(f, "relaxed"),
+            Self::Standard => write!(f, "standard"),
+            Self::Strict => write!(f, "strict"),
+            Self::PedanticClippy => write!(f, "pedantic-clippy"),
+        }
+    }
+}
+
+impl std::str::FromStr for LintProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "relaxed" => Ok(Self::Relaxed),
+            "standard" => Ok(Self::Standard),
+            "strict" => Ok(Self::Strict),
+            "pedantic-clippy" | "pedantic" => Ok(Self::PedanticClippy),
+            _ => Err(format!(
+                "unknown lint profile \"{}\", expected relaxed, standard, strict or pedantic-clippy",
+                s
+            )),
+        }
+    }
+}
+
+impl LintProfile {
+    /// Returns the `rust` lints and their levels.
+    pub fn rust(&self) -> Vec<(&'static str, &'static str)> {
+        let mut lints = vec![("unused_must_use", "deny")];
+        match self {
+            Self::Relaxed => lints.push(("rust_2018_idioms", "warn")),
+            Self::Standard | Self::Strict | Self::PedanticClippy => {
+                let missing_docs = match self {
+                    Self::Standard => "warn",
+                    _ => "deny",
+                };
+                lints.push(("rust_2018_idioms", "deny"));
+                lints.push(("missing_debug_implementations", "warn"));
+                lints.push(("missing_docs", missing_docs));
+                lints.push(("unreachable_pub", "warn"));
+            }
+        }
+        if matches!(self, Self::Strict | Self::PedanticClippy) {
+            lints.push(("unsafe_code", "deny"));
+        }
+        lints
+    }
+
+    /// Returns the `rustdoc` lints and their levels.
+    pub fn rustdoc(&self) -> Vec<(&'static str, &'static str)> {
+        match self {
+            Self::Relaxed => Vec::new(),
+            _ => vec![("all", "warn")],
+        }
+    }
+
+    /// Returns the `clippy` lints and their levels.
+    pub fn clippy(&self) -> Vec<(&'static str, &'static str)> {
+        let mut lints = Vec::new();
+        if matches!(self, Self::Strict | Self::PedanticClippy) {
+            lints.push(("all", "warn"));
+        }
+        if matches!(self, Self::PedanticClippy) {
+            lints.push(("pedantic", "warn"));
+            lints.push(("module_name_repetitions", "allow"));
+            lints.push(("must_use_candidate", "allow"));
+        }
+        if matches!(self, Self::Strict | Self::PedanticClippy) {
+            lints.push(("dbg_macro", "deny"));
+            lints.push(("todo", "deny"));
+            lints.push(("unimplemented", "deny"));
+        }
+        lints
+    }
+
+    /// Returns the `[lints]` table, with a table for each tool that has lints.
+    pub fn table(&self) -> toml_edit::Table {
+        let mut lints = toml_edit::Table::new();
+        lints.set_implicit(true);
+        let tools = [("rust", self.rust()), ("rustdoc", self.rustdoc()), ("clippy", self.clippy())];
+        for (tool, tool_lints) in tools.into_iter().filter(|(_, l)| !l.is_empty()) {
+            let mut table = toml_edit::Table::new();
+            for (lint, level) in tool_lints {
+                table[lint] = match LINT_GROUPS.contains(&lint) {
+                    true => {
+                        let mut group = toml_edit::InlineTable::new();
+                        group.insert("level", level.into());
+                        group.insert("priority", (-1).into());
+                        toml_edit::value(group)
+                    }
+                    false => toml_edit::value(level),
+                };
+            }
+            lints.insert(tool, toml_edit::Item::Table(table));
+        }
+        lints
+    }
+}
+
+/// Returns if cargo reads the `[lints]` table at the rust version, e.g. `1.70`.
+pub fn supported(rust_version: &str) -> bool {
+    let mut parts = rust_version.trim().split('.').map(|p| p.parse::<u64>().unwrap_or(0));
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    (major, minor) >= LINTS_RUST_VERSION
+}
+
+/// Sets the [LintProfile] lints in the manifest of the directory.
+///
+/// A workspace sets the `[workspace.lints]`, which each member crate in
+/// `bin/` and `crates/` inherits with `lints.workspace = true`. A package
+/// sets its own `[lints]`.
+pub fn set_lints(dir: &Path, profile: LintProfile) -> Result<()> {
+    let manifest_path = dir.join("Cargo.toml");
+    let mut manifest = parse_manifest(&manifest_path)?;
+    if manifest.get("workspace").is_none() {
+        manifest["lints"] = toml_edit::Item::Table(profile.table());
+        std::fs::write(&manifest_path, manifest.to_string())?;
+        return Ok(());
+    }
+    manifest["workspace"]["lints"] = toml_edit::Item::Table(profile.table());
+    std::fs::write(&manifest_path, manifest.to_string())?;
+    for member_manifest_path in member_manifests(dir)? {
+        let mut member_manifest = parse_manifest(&member_manifest_path)?;
+        member_manifest["lints"] = toml_edit::Item::Table(toml_edit::Table::new());
+        member_manifest["lints"]["workspace"] = toml_edit::value(true);
+        std::fs::write(&member_manifest_path, member_manifest.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lint_profile() {
+        assert_eq!("pedantic".parse::<LintProfile>(), Ok(LintProfile::PedanticClippy));
+        assert_eq!(LintProfile::PedanticClippy.to_string(), "pedantic-clippy");
+        assert!("loose".parse::<LintProfile>().is_err());
+        assert!(LintProfile::Relaxed.clippy().is_empty());
+        assert!(LintProfile::Strict.rust().contains(&("missing_docs", "deny")));
+        assert!(supported("1.74") && supported("1.80.1") && !supported("1.70"));
+
+        let mut manifest = toml_edit::Document::new();
+        manifest["lints"] = toml_edit::Item::Table(LintProfile::PedanticClippy.table());
+        let table = manifest.to_string();
+        assert!(table.starts_with("[lints.rust]\n"));
+        assert!(table.contains("[lints.clippy]\nall = { level = \"warn\", priority = -1 }\n"));
+        assert!(table.contains("pedantic = { level = \"warn\", priority = -1 }\n"));
+        assert!(table.contains("module_name_repetitions = \"allow\"\n"));
+    }
+
+    #[test]
+    fn test_set_lints() {
+        let dir = tempdir().unwrap();
+        let manifest = "[workspace]\nmembers = [\"bin/*\", \"crates/*\"]\n";
+        std::fs::write(dir.path().join("Cargo.toml"), manifest).unwrap();
+        let member = dir.path().join("crates").join("common");
+        std::fs::create_dir_all(&member).unwrap();
+        let member_manifest = "[package]\nname = \"common\"\n";
+        std::fs::write(member.join("Cargo.toml"), member_manifest).unwrap();
+
+        set_lints(dir.path(), LintProfile::Standard).unwrap();
+        let manifest = std::fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        assert!(manifest.contains("[workspace.lints.rust]\nunused_must_use = \"deny\"\n"));
+        assert!(manifest.contains("rust_2018_idioms = { level = \"deny\", priority = -1 }\n"));
+        assert!(manifest.contains("[workspace.lints.rustdoc]\nall = "));
+        assert!(!manifest.contains("[workspace.lints]\n"));
+        assert!(!manifest.contains("clippy"));
+        let member_manifest = std::fs::read_to_string(member.join("Cargo.toml")).unwrap();
+        assert!(member_manifest.ends_with("[lints]\nworkspace = true\n"));
+
+        let package = tempdir().unwrap();
+        let manifest = "[package]\nname = \"example\"\n";
+        std::fs::write(package.path().join("Cargo.toml"), manifest).unwrap();
+        set_lints(package.path(), LintProfile::Strict).unwrap();
+        let manifest = std::fs::read_to_string(package.path().join("Cargo.toml")).unwrap();
+        let manifest = manifest.parse::<toml_edit::Document>().unwrap();
+        assert_eq!(manifest["lints"]["clippy"]["todo"].as_str(), Some("deny"));
+    }
+}
@@ -0,0 +1,810 @@
+use crate::{ci::ActionLock, error::Result, release::ReleasePlan};
+use std::{fmt::Write, path::Path};
+
+/// The rust toolchains tested in CI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toolchain {
+    /// The workspace toolchain channel, `stable` unless set by `rust-toolchain.toml`.
+    Stable,
+    /// The minimum supported rust version, from the manifest `rust-version`.
+    Msrv,
+    /// The nightly toolchain.
+    Nightly,
+}
+
+impl std::str::FromStr for Toolchain {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "stable" => Ok(Self::Stable),
+            "msrv" => Ok(Self::Msrv),
+            "nightly" => Ok(Self::Nightly),
+            _ => Err(format!("unknown toolchain \"{}\", expected stable, msrv or nightly", s)),
+        }
+    }
+}
+
+/// The optional CI jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiJob {
+    /// Builds the docs with warnings denied and runs the doc tests.
+    Docs,
+    /// Uploads `cargo llvm-cov` coverage to codecov.
+    Coverage,
+    /// Runs the tests under miri.
+    Miri,
+    /// Checks for semver violations with `cargo semver-checks`.
+    SemverChecks,
+    /// Checks advisories, licenses, bans and sources against the `deny.toml`
+    /// with `cargo deny`.
+    Deny,
+}
+
+impl std::str::FromStr for CiJob {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "docs" => Ok(Self::Docs),
+            "coverage" => Ok(Self::Coverage),
+            "miri" => Ok(Self::Miri),
+            "semver-checks" => Ok(Self::SemverChecks),
+            "deny" => Ok(Self::Deny),
+            _ => Err(format!(
+                "unknown ci job \"{}\", expected docs, coverage, miri, semver-checks or deny",
+                s
+            )),
+        }
+    }
+}
+
+/// The CI providers the pipeline can be rendered for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CiProvider {
+    /// Github Actions workflows in `.github/workflows/`.
+    #[default]
+    Github,
+    /// A GitLab CI `.gitlab-ci.yml` pipeline.
+    Gitlab,
+    /// Forgejo or Gitea Actions workflows in `.forgejo/workflows/`.
+    Forgejo,
+}
+
+impl std::fmt::Display for CiProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Github => write!(f, "github"),
+            Self::Gitlab => write!(f, "gitlab"),
+            Self::Forgejo => write!(f, "forgejo"),
+        }
+    }
+}
+
+impl std::str::FromStr for CiProvider {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "github" => Ok(Self::Github),
+            "gitlab" => Ok(Self::Gitlab),
+            "forgejo" | "gitea" => Ok(Self::Forgejo),
+            _ => Err(format!("unknown ci provider \"{}\", expected github, gitlab or forgejo", s)),
+        }
+    }
+}
+
+/// The parameters the CI workflow is generated from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiConfig {
+    /// The toolchains the tests run on.
+    pub toolchains: Vec<Toolchain>,
+    /// The toolchain channel used for [Toolchain::Stable] and the other jobs.
+    pub channel: String,
+    /// The minimum supported rust version. [Toolchain::Msrv] is skipped without one.
+    pub msrv: Option<String>,
+    /// The operating systems the tests run on.
+    pub os: Vec<String>,
+    /// Whether to test every feature combination with `cargo hack`.
+    pub feature_powerset: bool,
+    /// The optional jobs.
+    pub jobs: Vec<CiJob>,
+    /// The provider the pipeline is generated for.
+    pub provider: CiProvider,
+    /// The action lock the workflow `uses:` references are pinned with,
+    /// `None` to keep the tags.
+    pub pins: Option<ActionLock>,
+    /// Whether the project pins its toolchain with a `rust-toolchain.toml`,
+    /// which the jobs on other toolchains override with `RUSTUP_TOOLCHAIN`.
+    pub toolchain_file: bool,
+    /// Whether the fmt job runs nightly rustfmt for unstable `rustfmt.toml` options.
+    pub nightly_rustfmt: bool,
+}
+
+impl Default for CiConfig {
+    fn default() -> Self {
+        Self {
+            toolchains: vec![Toolchain::Stable, Toolchain::Msrv],
+            channel: "stable".to_string(),
+            msrv: None,
+            os: vec!["ubuntu-latest".to_string()],
+            feature_powerset: false,
+            jobs: vec![CiJob::Docs],
+            provider: CiProvider::default(),
+            pins: None,
+            toolchain_file: false,
+            nightly_rustfmt: false,
+        }
+    }
+}
+
+impl CiConfig {
+    /// Reads the toolchain channel from the `rust-toolchain.toml` and the
+    /// minimum supported rust version from the `Cargo.toml` in the directory,
+    /// keeping the current values for missing files.
+    pub fn workspace(mut self, dir: &Path) -> Result<Self> {
+        let toolchain = dir.join("rust-toolchain.toml");
+        if toolchain.exists() {
+            self.toolchain_file = true;
+            let toolchain = parse_toml(&std::fs::read_to_string(toolchain)?)?;
+            if let Some(channel) = toolchain.get("toolchain").and_then(|t| t.get("channel")) {
+                self.channel = channel.as_str().unwrap_or(&self.channel).to_string();
+            }
+        }
+        let manifest = dir.join("Cargo.toml");
+        if manifest.exists() {
+            let manifest = parse_toml(&std::fs::read_to_string(manifest)?)?;
+            let rust_version = manifest
+                .get("workspace")
+                .and_then(|w| w.get("package")?.get("rust-version"))
+                .or_else(|| manifest.get("package").and_then(|p| p.get("rust-version")));
+            if let Some(rust_version) = rust_version.and_then(|v| v.as_str()) {
+                self.msrv = Some(rust_version.to_string());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Returns the toolchain names the tests run on.
+    fn toolchain_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for toolchain in &self.toolchains {
+            let name = match toolchain {
+                Toolchain::Stable => Some(self.channel.clone()),
+                Toolchain::Msrv => self.msrv.clone(),
+                Toolchain::Nightly => Some("nightly".to_string()),
+            };
+            if let Some(name) = name.filter(|n| !names.contains(n)) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Returns the toolchain for the fmt job, nightly for unstable rustfmt
+    /// options or if tested.
+    fn fmt_toolchain(&self) -> String {
+        match self.nightly_rustfmt {
+            true => "nightly".to_string(),
+            false => self.lint_toolchain(),
+        }
+    }
+
+    /// Returns the toolchain for the lint job, nightly if tested so that
+    /// unstable rustfmt options can be used.
+    fn lint_toolchain(&self) -> String {
+        match self.toolchains.contains(&Toolchain::Nightly) {
+            true => "nightly".to_string(),
+            false => self.channel.clone(),
+        }
+    }
+}
+
+fn parse_toml(contents: &str) -> Result<toml_edit::Document> {
+    contents.parse::<toml_edit::Document>().map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid toml: {}", e)).into()
+    })
+}
+
+/// A step of a [Job], either using an action or running a command.
+///
+/// A step using an action may also set the equivalent command, which is run
+/// by providers without actions. Action steps without one are skipped there.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Step {
+    /// The step name.
+    pub name: Option<String>,
+    /// The action used by the step.
+    pub uses: Option<String>,
+    /// The action inputs.
+    pub with: Vec<(String, String)>,
+    /// The step environment variables.
+    pub env: Vec<(String, String)>,
+    /// The command run by the step, or in place of the action.
+    pub run: Option<String>,
+}
+
+impl Step {
+    /// Creates a step using the given action.
+    pub fn uses(action: impl Into<String>) -> Self {
+        Self { uses: Some(action.into()), ..Default::default() }
+    }
+
+    /// Creates a step running the given command.
+    pub fn run(command: impl Into<String>) -> Self {
+        Self { run: Some(command.into()), ..Default::default() }
+    }
+
+    /// Creates a step installing the cargo subcommand, e.g. `cargo-hack`.
+    pub fn install(tool: &str) -> Self {
+        Self::uses("taiki-e/install-action@v2")
+            .with("tool", tool)
+            .or_run(format!("cargo install --locked {}", tool))
+    }
+
+    /// Sets the command run in place of the action.
+    pub fn or_run(mut self, command: impl Into<String>) -> Self {
+        self.run = Some(command.into());
+        self
+    }
+
+    /// Sets the step name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Adds an action input.
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.with.push((key.into(), value.into()));
+        self
+    }
+
+    /// Adds an environment variable.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// A CI job.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Job {
+    /// The job identifier.
+    pub id: String,
+    /// The display name.
+    pub name: Option<String>,
+    /// The runner the job runs on.
+    pub runs_on: String,
+    /// The matrix axes and their values, referenced as `${{ matrix.<axis> }}`.
+    pub matrix: Vec<(String, Vec<String>)>,
+    /// The ids of the jobs that must pass first.
+    pub needs: Vec<String>,
+    /// Whether the job only runs for `v*` tags.
+    pub on_tag: bool,
+    /// The toolchain installed by the setup steps.
+    pub toolchain: Option<String>,
+    /// The steps of the job.
+    pub steps: Vec<Step>,
+}
+
+impl Job {
+    /// Creates a job running on `ubuntu-latest`.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self { id: id.into(), runs_on: "ubuntu-latest".to_string(), ..Default::default() }
+    }
+
+    /// Adds the checkout, toolchain and cache steps.
+    fn setup(mut self, toolchain: impl Into<String>, components: &[&str]) -> Self {
+        self.steps.push(Step::uses("actions/checkout@v4"));
+        let toolchain = toolchain.into();
+        self.toolchain = Some(toolchain.clone());
+        let mut step = Step::uses("dtolnay/rust-toolchain@v1").with("toolchain", &toolchain);
+        let mut install = format!("rustup toolchain install {} --profile minimal", toolchain);
+        if !components.is_empty() {
+            step = step.with("components", components.join(", "));
+            install = format!("{} --component {}", install, components.join(","));
+        }
+        step = step.or_run(format!("{} && rustup default {}", install, toolchain));
+        self.steps.push(step);
+        self.steps.push(Step::uses("Swatinem/rust-cache@v2"));
+        self
+    }
+
+    /// Adds a step.
+    fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+}
+
+/// The CI pipeline, rendered to a provider's workflow format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    /// The pipeline name.
+    pub name: String,
+    /// The environment variables of every job.
+    pub env: Vec<(String, String)>,
+    /// The jobs.
+    pub jobs: Vec<Job>,
+}
+
+impl Pipeline {
+    /// Builds the CI pipeline from the [CiConfig].
+    pub fn ci(config: &CiConfig) -> Self {
+        let mut jobs = Vec::new();
+
+        let mut test = Job::new("test").setup("${{ matrix.toolchain }}", &[]);
+        test.name = Some("test (${{ matrix.os }}, ${{ matrix.toolchain }})".to_string());
+        test.runs_on = "${{ matrix.os }}".to_string();
+        test.matrix = vec![
+            ("os".to_string(), config.os.clone()),
+            ("toolchain".to_string(), config.toolchain_names()),
+        ];
+        test = match config.feature_powerset {
+            true => test
+                .step(Step::install("cargo-hack"))
+                .step(Step::run("cargo hack test --workspace --feature-powerset")),
+            false => test
+                .step(Step::run("cargo test --workspace"))
+                .step(Step::run("cargo test --workspace --all-features")),
+        };
+        jobs.push(test);
+
+        jobs.push(
+            Job::new("fmt")
+                .setup(config.fmt_toolchain(), &["rustfmt"])
+                .step(Step::run("cargo fmt --all -- --check")),
+        );
+        jobs.push(Job::new("lint").setup(config.lint_toolchain(), &["clippy"]).step(Step::run(
+            "cargo clippy --workspace --all-targets --all-features -- -D warnings",
+        )));
+        jobs.push(
+            Job::new("build")
+                .setup(&config.channel, &[])
+                .step(Step::run("cargo build --workspace --release")),
+        );
+
+        for job in &config.jobs {
+            jobs.push(match job {
+                CiJob::Docs => Job::new("docs")
+                    .setup(&config.channel, &[])
+                    .step(
+                        Step::run("cargo doc --workspace --no-deps --all-features")
+                            .env("RUSTDOCFLAGS", "-D warnings"),
+                    )
+                    .step(Step::run("cargo test --doc --workspace --all-features")),
+                CiJob::Coverage => Job::new("coverage")
+                    .setup(&config.channel, &["llvm-tools-preview"])
+                    .step(Step::install("cargo-llvm-cov"))
+                    .step(Step::run(
+                        "cargo llvm-cov --workspace --all-features --lcov --output-path lcov.info",
+                    ))
+                    .step(
+                        Step::uses("codecov/codecov-action@v4")
+                            .with("files", "lcov.info")
+                            .or_run(CODECOV_UPLOAD),
+                    ),
+                CiJob::Miri => Job::new("miri")
+                    .setup("nightly", &["miri"])
+                    .step(Step::run("cargo miri test --workspace")),
+                CiJob::SemverChecks => Job::new("semver-checks")
+                    .step(Step::uses("actions/checkout@v4"))
+                    .step(Step::install("cargo-semver-checks"))
+                    .step(Step::run("cargo semver-checks")),
+                CiJob::Deny => Job::new("deny")
+                    .step(Step::uses("actions/checkout@v4"))
+                    .step(Step::install("cargo-deny"))
+                    .step(Step::run("cargo deny check")),
+            });
+        }
+
+        if config.toolchain_file {
+            // The `rust-toolchain.toml` takes precedence over the default toolchain.
+            for job in &mut jobs {
+                let Some(toolchain) = job.toolchain.clone().filter(|t| *t != config.channel) else {
+                    continue;
+                };
+                for step in job.steps.iter_mut().filter(|s| s.uses.is_none()) {
+                    step.env.push(("RUSTUP_TOOLCHAIN".to_string(), toolchain.clone()));
+                }
+            }
+        }
+
+        Self {
+            name: "CI".to_string(),
+            env: vec![("RUSTFLAGS".to_string(), "-Dwarnings".to_string())],
+            jobs,
+        }
+    }
+
+    /// Adds a job publishing the packages of the [ReleasePlan] to crates.io
+    /// in order on `v*` tags once every other job passes.
+    pub fn release(mut self, config: &CiConfig, plan: &ReleasePlan) -> Self {
+        let mut release = Job::new("release").setup(&config.channel, &[]);
+        for command in plan.publish_commands() {
+            release = release.step(
+                Step::run(command)
+                    .env("CARGO_REGISTRY_TOKEN", "${{ secrets.CARGO_REGISTRY_TOKEN }}"),
+            );
+        }
+        release.needs = self.jobs.iter().map(|j| j.id.clone()).collect();
+        release.on_tag = true;
+        self.jobs.push(release);
+        self
+    }
+
+    /// Renders the pipeline for the given provider.
+    pub fn render(&self, provider: CiProvider) -> String {
+        match provider {
+            CiProvider::Github => self.github(),
+            CiProvider::Gitlab => self.gitlab(),
+            CiProvider::Forgejo => self.forgejo(),
+        }
+    }
+
+    /// Renders the pipeline as a github actions workflow.
+    pub fn github(&self) -> String {
+        self.actions(|action| action.to_string())
+    }
+
+    /// Renders the pipeline as a forgejo actions workflow.
+    ///
+    /// Forgejo resolves actions from its own mirror, which only carries the
+    /// `actions/*` actions, so the others are referenced by their github url.
+    pub fn forgejo(&self) -> String {
+        self.actions(|action| match action.starts_with("actions/") {
+            true => action.to_string(),
+            false => format!("https://github.com/{}", action),
+        })
+    }
+
+    /// Renders the pipeline in the github actions syntax shared by forgejo.
+    fn actions(&self, action: impl Fn(&str) -> String) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "name: {}", yaml_scalar(&self.name));
+        out.push_str("\non:\n  push:\n    branches:\n      - main\n");
+        if self.jobs.iter().any(|j| j.on_tag) {
+            out.push_str("    tags:\n      - 'v*'\n");
+        }
+        out.push_str("  pull_request:\n");
+        out.push_str("  workflow_dispatch:\n\npermissions:\n  contents: read\n");
+        if !self.env.is_empty() {
+            out.push_str("\nenv:\n");
+            for (key, value) in &self.env {
+                let _ = writeln!(out, "  {}: {}", key, yaml_scalar(value));
+            }
+        }
+        out.push_str("\njobs:");
+        for job in &self.jobs {
+            let _ = writeln!(out, "\n  {}:", job.id);
+            if let Some(name) = &job.name {
+                let _ = writeln!(out, "    name: {}", yaml_scalar(name));
+            }
+            let _ = writeln!(out, "    runs-on: {}", yaml_scalar(&job.runs_on));
+            if !job.needs.is_empty() {
+                let _ = writeln!(out, "    needs: [{}]", job.needs.join(", "));
+            }
+            if job.on_tag {
+                out.push_str("    if: startsWith(github.ref, 'refs/tags/v')\n");
+            }
+            out.push_str("    timeout-minutes: 20\n");
+            if !job.matrix.is_empty() {
+                out.push_str("    strategy:\n      fail-fast: false\n      matrix:\n");
+                for (axis, values) in &job.matrix {
+                    let _ = writeln!(out, "        {}:", axis);
+                    for value in values {
+                        let _ = writeln!(out, "          - {}", yaml_scalar(value));
+                    }
+                }
+            }
+            out.push_str("    steps:\n");
+            for step in &job.steps {
+                let mut lines = Vec::new();
+                if let Some(name) = &step.name {
+                    lines.push(format!("name: {}", yaml_scalar(name)));
+                }
+                if let Some(uses) = &step.uses {
+                    lines.push(format!("uses: {}", yaml_scalar(&action(uses))));
+                }
+                if !step.with.is_empty() {
+                    lines.push("with:".to_string());
+                    for (key, value) in &step.with {
+                        lines.push(format!("  {}: {}", key, yaml_scalar(value)));
+                    }
+                }
+                if !step.env.is_empty() {
+                    lines.push("env:".to_string());
+                    for (key, value) in &step.env {
+                        lines.push(format!("  {}: {}", key, yaml_scalar(value)));
+                    }
+                }
+                if let Some(run) = step.run.as_ref().filter(|_| step.uses.is_none()) {
+                    lines.push(format!("run: {}", yaml_scalar(run)));
+                }
+                for (i, line) in lines.iter().enumerate() {
+                    let prefix = if i == 0 { "      - " } else { "        " };
+                    let _ = writeln!(out, "{}{}", prefix, line);
+                }
+            }
+        }
+        out
+    }
+
+    /// Renders the pipeline as a gitlab ci pipeline.
+    ///
+    /// Jobs run in the `rust` docker image, so action steps without a command
+    /// are skipped and the matrix axes only referenced by the runner, like the
+    /// operating system, are dropped. Tag jobs run in a later `release` stage.
+    pub fn gitlab(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}", self.name);
+        out.push_str("stages:\n  - test\n");
+        if self.jobs.iter().any(|j| j.on_tag) {
+            out.push_str("  - release\n");
+        }
+        out.push_str("\nvariables:\n");
+        for (key, value) in &self.env {
+            let _ = writeln!(out, "  {}: {}", key, yaml_scalar(&gitlab_variables(value)));
+        }
+        out.push_str("  CARGO_HOME: $CI_PROJECT_DIR/.cargo\n");
+        out.push_str("\ndefault:\n  image: rust:latest\n  cache:\n    key: $CI_JOB_NAME_SLUG\n");
+        out.push_str("    paths:\n      - .cargo/registry\n      - target/\n");
+        out.push_str("\nworkflow:\n  rules:\n");
+        out.push_str("    - if: $CI_PIPELINE_SOURCE == \"merge_request_event\"\n");
+        out.push_str("    - if: $CI_COMMIT_BRANCH == $CI_DEFAULT_BRANCH\n");
+        out.push_str("    - if: $CI_COMMIT_TAG =~ /^v/\n");
+        for job in &self.jobs {
+            let steps = job.steps.iter().filter_map(|s| s.run.as_ref().map(|r| (s, r)));
+            let steps = steps.collect::<Vec<_>>();
+            let _ = writeln!(out, "\n{}:", job.id);
+            let stage = if job.on_tag { "release" } else { "test" };
+            let _ = writeln!(out, "  stage: {}", stage);
+            if job.on_tag {
+                out.push_str("  rules:\n    - if: $CI_COMMIT_TAG =~ /^v/\n");
+            }
+            let matrix = job
+                .matrix
+                .iter()
+                .filter(|(axis, _)| {
+                    let expr = matrix_expr(axis);
+                    steps.iter().any(|(s, run)| {
+                        run.contains(&expr) || s.env.iter().any(|(_, v)| v.contains(&expr))
+                    })
+                })
+                .collect::<Vec<_>>();
+            if !matrix.is_empty() {
+                out.push_str("  parallel:\n    matrix:\n");
+                for (i, (axis, values)) in matrix.iter().enumerate() {
+                    let prefix = if i == 0 { "      - " } else { "        " };
+                    let _ = writeln!(out, "{}{}:", prefix, axis.to_uppercase().replace('-', "_"));
+                    for value in values {
+                        let _ = writeln!(out, "          - {}", yaml_scalar(value));
+                    }
+                }
+            }
+            let env = steps
+                .iter()
+                .flat_map(|(s, _)| s.env.iter())
+                .map(|(key, value)| (key, gitlab_variables(value)))
+                .filter(|(key, value)| *value != format!("${}", key))
+                .collect::<Vec<_>>();
+            if !env.is_empty() {
+                out.push_str("  variables:\n");
+                for (key, value) in env {
+                    let _ = writeln!(out, "    {}: {}", key, yaml_scalar(&value));
+                }
+            }
+            out.push_str("  script:\n");
+            for (_, run) in steps {
+                let _ = writeln!(out, "    - {}", yaml_scalar(&gitlab_variables(run)));
+            }
+        }
+        out
+    }
+}
+
+/// Returns the expression referencing the matrix axis.
+fn matrix_expr(axis: &str) -> String {
+    format!("${{{{ matrix.{} }}}}", axis)
+}
+
+/// Replaces the `${{ matrix.<axis> }}` and `${{ secrets.<name> }}` expressions
+/// with the equivalent gitlab `$AXIS` and `$NAME` variables.
+fn gitlab_variables(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${{") {
+        let Some(end) = rest[start..].find("}}") else { break };
+        let expr = rest[start + 3..start + end].trim();
+        let name = expr.rsplit('.').next().unwrap_or(expr);
+        let _ = write!(out, "{}${}", &rest[..start], name.to_uppercase().replace('-', "_"));
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Uploads the `lcov.info` report with the codecov cli, reading `CODECOV_TOKEN`.
+const CODECOV_UPLOAD: &str = "curl -Os https://cli.codecov.io/latest/linux/codecov && \
+    chmod +x codecov && ./codecov upload-process -f lcov.info";
+
+/// Quotes the yaml scalar if it would otherwise parse as a non-string or
+/// a yaml indicator, e.g. the `1.70` toolchain.
+pub fn yaml_scalar(value: &str) -> String {
+    let reserved = ["true", "false", "yes", "no", "on", "off", "null", "~"];
+    let needs_quotes = value.is_empty() ||
+        value.parse::<f64>().is_ok() ||
+        reserved.contains(&value.to_lowercase().as_str()) ||
+        value.starts_with(|c: char| ",[]{}#&*!|>'\"%@`".contains(c) || c.is_whitespace()) ||
+        ["-", "?", ":"].iter().any(|i| value == *i || value.starts_with(&format!("{} ", i))) ||
+        value.ends_with(|c: char| c == ':' || c.is_whitespace()) ||
+        value.contains(": ") ||
+        value.contains(" #");
+    match needs_quotes {
+        true => format!("'{}'", value.replace('\'', "''")),
+        false => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn parse(yaml: &str) -> serde_yaml::Value {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_default_ci() {
+        let workflow = parse(&Pipeline::ci(&CiConfig::default()).github());
+        assert_eq!(workflow["name"], "CI");
+        assert!(workflow["on"]["pull_request"].is_null());
+        let jobs = workflow["jobs"].as_mapping().unwrap();
+        let ids = jobs.keys().map(|k| k.as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["test", "fmt", "lint", "build", "docs"]);
+
+        let matrix = &workflow["jobs"]["test"]["strategy"]["matrix"];
+        assert_eq!(matrix["os"], parse("[ubuntu-latest]"));
+        // The msrv is skipped without a `rust-version`.
+        assert_eq!(matrix["toolchain"], parse("[stable]"));
+        assert_eq!(workflow["jobs"]["test"]["runs-on"], "${{ matrix.os }}");
+        let steps = workflow["jobs"]["test"]["steps"].as_sequence().unwrap();
+        assert_eq!(steps[1]["uses"], "dtolnay/rust-toolchain@v1");
+        assert_eq!(steps[1]["with"]["toolchain"], "${{ matrix.toolchain }}");
+        assert_eq!(steps[3]["run"], "cargo test --workspace");
+        assert_eq!(workflow["jobs"]["docs"]["steps"][3]["env"]["RUSTDOCFLAGS"], "-D warnings");
+    }
+
+    #[test]
+    fn test_parameterized_ci() {
+        let config = CiConfig {
+            toolchains: vec![Toolchain::Stable, Toolchain::Msrv, Toolchain::Nightly],
+            msrv: Some("1.70".to_string()),
+            os: vec!["ubuntu-latest".to_string(), "macos-latest".to_string()],
+            feature_powerset: true,
+            jobs: vec![CiJob::Coverage, CiJob::Miri, CiJob::SemverChecks],
+            ..Default::default()
+        };
+        let yaml = Pipeline::ci(&config).github();
+        let workflow = parse(&yaml);
+        let jobs = workflow["jobs"].as_mapping().unwrap();
+        let ids = jobs.keys().map(|k| k.as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["test", "fmt", "lint", "build", "coverage", "miri", "semver-checks"]);
+
+        let matrix = &workflow["jobs"]["test"]["strategy"]["matrix"];
+        assert_eq!(matrix["os"], parse("[ubuntu-latest, macos-latest]"));
+        // The msrv must stay a string rather than the float 1.7.
+        assert_eq!(matrix["toolchain"], parse("[stable, '1.70', nightly]"));
+        assert_eq!(
+            workflow["jobs"]["test"]["steps"][4]["run"],
+            "cargo hack test --workspace --feature-powerset"
+        );
+        assert_eq!(workflow["jobs"]["lint"]["steps"][1]["with"]["toolchain"], "nightly");
+        assert_eq!(workflow["jobs"]["miri"]["steps"][1]["with"]["components"], "miri");
+        assert!(!yaml.contains("actions-rs"));
+    }
+
+    #[test]
+    fn test_forgejo_ci() {
+        let workflow = parse(
+            &Pipeline::ci(&CiConfig::default())
+                .release(&CiConfig::default(), &ReleasePlan::default())
+                .forgejo(),
+        );
+        assert_eq!(workflow["on"]["push"]["tags"], parse("['v*']"));
+        let steps = &workflow["jobs"]["test"]["steps"];
+        assert_eq!(steps[0]["uses"], "actions/checkout@v4");
+        assert_eq!(steps[1]["uses"], "https://github.com/dtolnay/rust-toolchain@v1");
+        assert!(steps[1]["run"].is_null());
+
+        let release = &workflow["jobs"]["release"];
+        assert_eq!(release["needs"], parse("[test, fmt, lint, build, docs]"));
+        assert_eq!(release["if"], "startsWith(github.ref, 'refs/tags/v')");
+        assert_eq!(
+            release["steps"][3]["env"]["CARGO_REGISTRY_TOKEN"],
+            "${{ secrets.CARGO_REGISTRY_TOKEN }}"
+        );
+    }
+
+    #[test]
+    fn test_gitlab_ci() {
+        let config = CiConfig {
+            msrv: Some("1.70".to_string()),
+            os: vec!["ubuntu-latest".to_string(), "macos-latest".to_string()],
+            jobs: vec![CiJob::Docs, CiJob::SemverChecks, CiJob::Deny],
+            ..Default::default()
+        };
+        let pipeline =
+            parse(&Pipeline::ci(&config).release(&config, &ReleasePlan::default()).gitlab());
+        assert_eq!(pipeline["stages"], parse("[test, release]"));
+        assert_eq!(pipeline["variables"]["RUSTFLAGS"], "-Dwarnings");
+
+        // The os axis only selects the runner and is dropped.
+        let test = &pipeline["test"];
+        assert_eq!(test["parallel"]["matrix"], parse("[{TOOLCHAIN: [stable, '1.70']}]"));
+        assert_eq!(
+            test["script"][0],
+            "rustup toolchain install $TOOLCHAIN --profile minimal && rustup default $TOOLCHAIN"
+        );
+        assert_eq!(test["script"][1], "cargo test --workspace");
+        assert_eq!(
+            pipeline["fmt"]["script"][0],
+            "rustup toolchain install stable --profile minimal --component rustfmt && rustup \
+             default stable"
+        );
+        assert_eq!(pipeline["docs"]["variables"]["RUSTDOCFLAGS"], "-D warnings");
+        assert_eq!(
+            pipeline["semver-checks"]["script"],
+            parse("['cargo install --locked cargo-semver-checks', 'cargo semver-checks']")
+        );
+        assert_eq!(
+            pipeline["deny"]["script"],
+            parse("['cargo install --locked cargo-deny', 'cargo deny check']")
+        );
+
+        // The registry token is read from the ci/cd variable of the same name.
+        let release = &pipeline["release"];
+        assert_eq!(release["stage"], "release");
+        assert_eq!(release["rules"][0]["if"], "$CI_COMMIT_TAG =~ /^v/");
+        assert!(release["variables"].is_null());
+        assert_eq!(release["script"][1], "cargo publish");
+    }
+
+    #[test]
+    fn test_ci_provider() {
+        assert_eq!("gitlab".parse::<CiProvider>().unwrap(), CiProvider::Gitlab);
+        assert_eq!("Gitea".parse::<CiProvider>().unwrap(), CiProvider::Forgejo);
+        assert!("jenkins".parse::<CiProvider>().is_err());
+        let pipeline = Pipeline::ci(&CiConfig::default());
+        assert_eq!(pipeline.render(CiProvider::Github), pipeline.github());
+        assert!(!pipeline.github().contains("tags:"));
+    }
+
+    #[test]
+    fn test_ci_config_workspace() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace.package]\nrust-version = \"1.72\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("rust-toolchain.toml"), "[toolchain]\nchannel = \"1.75\"\n")
+            .unwrap();
+        let config = CiConfig::default().workspace(dir.path()).unwrap();
+        assert_eq!(config.msrv.as_deref(), Some("1.72"));
+        assert_eq!(config.channel, "1.75");
+        assert_eq!(config.toolchain_names(), vec!["1.75", "1.72"]);
+    }
+
+    #[test]
+    fn test_yaml_scalar() {
+        assert_eq!(yaml_scalar("stable"), "stable");
+        assert_eq!(yaml_scalar("1.70"), "'1.70'");
+        assert_eq!(yaml_scalar("-D warnings"), "-D warnings");
+        assert_eq!(yaml_scalar("- item"), "'- item'");
+        assert_eq!(yaml_scalar("${{ matrix.os }}"), "${{ matrix.os }}");
+        assert_eq!(yaml_scalar("it's: here"), "'it''s: here'");
+    }
+}
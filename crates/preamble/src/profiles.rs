@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use crate::{error::Result, root::parse_manifest};
+
+/// A cargo profile preset written to the root manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilePreset {
+    /// A `maxperf` release profile with fat LTO and a single codegen unit.
+    MaxPerf,
+    /// A `debug-fast` release profile with debug info.
+    DebugFast,
+    /// A `min-size` release profile optimizing for size, stripping symbols
+    /// and aborting on panic.
+    MinSize,
+    /// A `ci` dev profile without incremental compilation or debug info,
+    /// which only slow down and bloat the cache of one-off ci builds.
+    Ci,
+}
+
+impl std::fmt::Display for ProfilePreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl std::str::FromStr for ProfilePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "maxperf" => Ok(Self::MaxPerf),
+            "debug-fast" => Ok(Self::DebugFast),
+            "min-size" => Ok(Self::MinSize),
+            "ci" => Ok(Self::Ci),
+            _ => Err(format!(
+                "unknown profile preset \"{}\", expected maxperf, debug-fast, min-size or ci",
+                s
+            )),
+        }
+    }
+}
+
+impl ProfilePreset {
+    /// Returns the profile name, e.g. `cargo build --profile maxperf`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MaxPerf => "maxperf",
+            Self::DebugFast => "debug-fast",
+            Self::MinSize => "min-size",
+            Self::Ci => "ci",
+        }
+    }
+
+    /// Returns the `[profile.<name>]` table.
+    pub fn table(&self) -> toml_edit::Table {
+        let mut table = toml_edit::Table::new();
+        match self {
+            Self::MaxPerf => {
+                table["inherits"] = toml_edit::value("release");
+                table["lto"] = toml_edit::value("fat");
+                table["codegen-units"] = toml_edit::value(1);
+                table["incremental"] = toml_edit::value(false);
+            }
+            Self::DebugFast => {
+                table["inherits"] = toml_edit::value("release");
+                table["debug"] = toml_edit::value(true);
+            }
+            Self::MinSize => {
+                table["inherits"] = toml_edit::value("release");
+                table["opt-level"] = toml_edit::value("z");
+                table["lto"] = toml_edit::value(true);
+                table["codegen-units"] = toml_edit::value(1);
+                table["strip"] = toml_edit::value(true);
+                table["panic"] = toml_edit::value("abort");
+            }
+            Self::Ci => {
+                table["inherits"] = toml_edit::value("dev");
+                table["incremental"] = toml_edit::value(false);
+                table["debug"] = toml_edit::value(false);
+            }
+        }
+        table
+    }
+}
+
+/// Adds the [ProfilePreset]s to the manifest of the directory, replacing
+/// profiles of the same name.
+///
+/// Cargo only reads profiles from the root manifest, so this is the
+/// workspace manifest of a workspace.
+pub fn set_profiles(dir: &Path, presets: &[ProfilePreset]) -> Result<()> {
+    let manifest_path = dir.join("Cargo.toml");
+    let mut manifest = parse_manifest(&manifest_path)?;
+    if manifest.get("profile").is_none() {
+        let mut profile = toml_edit::Table::new();
+        profile.set_implicit(true);
+        manifest["profile"] = toml_edit::Item::Table(profile);
+    }
+    for preset in presets {
+        manifest["profile"][preset.name()] = toml_edit::Item::Table(preset.table());
+    }
+    std::fs::write(&manifest_path, manifest.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_profile_preset() {
+        assert_eq!("min-size".parse::<ProfilePreset>(), Ok(ProfilePreset::MinSize));
+        assert_eq!(ProfilePreset::DebugFast.to_string(), "debug-fast");
+        assert!("fast".parse::<ProfilePreset>().is_err());
+    }
+
+    #[test]
+    fn test_set_profiles() {
+        let dir = tempdir().unwrap();
+        let manifest = "[package]\nname = \"example\"\n\n[profile.dev]\nopt-level = 1\n";
+        std::fs::write(dir.path().join("Cargo.toml"), manifest).unwrap();
+        set_profiles(dir.path(), &[ProfilePreset::MaxPerf, ProfilePreset::Ci]).unwrap();
+
+        let manifest = std::fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        assert!(manifest.starts_with("[package]\nname = \"example\"\n\n[profile.dev]\n"));
+        assert!(manifest.contains("[profile.maxperf]\ninherits = \"release\"\nlto = \"fat\"\n"));
+        assert!(manifest
+            .ends_with("[profile.ci]\ninherits = \"dev\"\nincremental = false\ndebug = false\n"));
+
+        let package = tempdir().unwrap();
+        let manifest = "[package]\nname = \"example\"\n";
+        std::fs::write(package.path().join("Cargo.toml"), manifest).unwrap();
+        set_profiles(package.path(), &[ProfilePreset::MinSize]).unwrap();
+        let manifest = std::fs::read_to_string(package.path().join("Cargo.toml")).unwrap();
+        assert!(!manifest.contains("[profile]\n"));
+        let manifest = manifest.parse::<toml_edit::Document>().unwrap();
+        assert_eq!(manifest["profile"]["min-size"]["opt-level"].as_str(), Some("z"));
+        assert_eq!(manifest["profile"]["min-size"]["panic"].as_str(), Some("abort"));
+    }
+}
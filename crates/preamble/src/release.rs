@@ -0,0 +1,566 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::Result, root::parse_manifest};
+
+/// A workspace package considered for release.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Package {
+    /// The package name.
+    pub name: String,
+    /// The package directory, relative to the workspace root.
+    pub path: PathBuf,
+    /// The package version, resolved from `workspace.package.version` if inherited.
+    pub version: Option<String>,
+    /// Whether the version is inherited from `workspace.package.version`.
+    pub inherits_version: bool,
+    /// Whether the package is published to crates.io.
+    pub publish: bool,
+    /// Whether the package is a binary without a library target.
+    pub bin: bool,
+    /// The workspace packages this package depends on, excluding dev-dependencies.
+    pub dependencies: Vec<String>,
+    /// The workspace packages this package depends on by path without a version.
+    pub unversioned: Vec<String>,
+}
+
+/// The workspace packages in the order they are published.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleasePlan {
+    /// The packages, libraries in dependency order before binaries.
+    pub packages: Vec<Package>,
+    /// The shared `workspace.package.version`.
+    pub workspace_version: Option<String>,
+}
+
+impl ReleasePlan {
+    /// Reads the packages of the workspace or single package in the directory.
+    ///
+    /// Members are expanded from `workspace.members`, where a `dir/*` pattern
+    /// matches every directory in `dir` with a `Cargo.toml`. The plan is empty
+    /// if the directory has no manifest.
+    pub fn workspace(dir: &Path) -> Result<Self> {
+        let manifest_path = dir.join("Cargo.toml");
+        if !manifest_path.exists() {
+            return Ok(Self::default());
+        }
+        let manifest = parse_manifest(&manifest_path)?;
+        let workspace = manifest.get("workspace");
+        let workspace_version =
+            workspace.and_then(|w| w.get("package")?.get("version")?.as_str()).map(String::from);
+        let workspace_deps = workspace.and_then(|w| w.get("dependencies"));
+
+        let mut paths = Vec::new();
+        if manifest.get("package").is_some() {
+            paths.push(PathBuf::new());
+        }
+        let members = workspace.and_then(|w| w.get("members")?.as_array());
+        for member in members.into_iter().flatten().filter_map(|m| m.as_str()) {
+            match member.strip_suffix("/*") {
+                Some(parent) if dir.join(parent).is_dir() => {
+                    let mut entries = std::fs::read_dir(dir.join(parent))?
+                        .map(|e| e.map(|e| Path::new(parent).join(e.file_name())))
+                        .collect::<std::result::Result<Vec<_>, _>>()?;
+                    entries.sort();
+                    paths.extend(
+                        entries.into_iter().filter(|p| dir.join(p).join("Cargo.toml").exists()),
+                    );
+                }
+                Some(_) => {}
+                None => paths.push(PathBuf::from(member)),
+            }
+        }
+
+        let mut packages = Vec::new();
+        for path in paths {
+            let manifest = parse_manifest(&dir.join(&path).join("Cargo.toml"))?;
+            let Some(package) = manifest.get("package") else { continue };
+            let version = package.get("version");
+            let inherits_version = version.and_then(|v| v.get("workspace")).is_some();
+            let publish = match package.get("publish") {
+                Some(publish) if publish.as_bool() == Some(false) => false,
+                Some(publish) => publish.as_array().map_or(true, |registries| {
+                    registries.iter().any(|r| r.as_str() == Some("crates-io"))
+                }),
+                None => true,
+            };
+            let src = dir.join(&path).join("src");
+            let bin = manifest.get("lib").is_none() &&
+                !src.join("lib.rs").exists() &&
+                (manifest.get("bin").is_some() || src.join("main.rs").exists());
+
+            // Member dependencies are resolved by path once all packages are known.
+            let mut dependencies = Vec::new();
+            for kind in ["dependencies", "build-dependencies"] {
+                let Some(table) = manifest.get(kind).and_then(|t| t.as_table_like()) else {
+                    continue
+                };
+                for (key, dep) in table.iter() {
+                    let (dep, base) = match dep.get("workspace").and_then(|w| w.as_bool()) {
+                        Some(true) => match workspace_deps.and_then(|d| d.get(key)) {
+                            Some(workspace_dep) => (workspace_dep, PathBuf::new()),
+                            None => continue,
+                        },
+                        _ => (dep, path.clone()),
+                    };
+                    let Some(dep_path) = dep.get("path").and_then(|p| p.as_str()) else { continue };
+                    dependencies
+                        .push((normalize(&base.join(dep_path)), dep.get("version").is_some()));
+                }
+            }
+            packages.push((
+                Package {
+                    name: package
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    path,
+                    version: match inherits_version {
+                        true => workspace_version.clone(),
+                        false => version.and_then(|v| v.as_str()).map(String::from),
+                    },
+                    inherits_version,
+                    publish,
+                    bin,
+                    ..Default::default()
+                },
+                dependencies,
+            ));
+        }
+        let names =
+            packages.iter().map(|(p, _)| (p.path.clone(), p.name.clone())).collect::<Vec<_>>();
+        let packages = packages
+            .into_iter()
+            .map(|(mut package, dependencies)| {
+                for (dep_path, versioned) in dependencies {
+                    let Some((_, name)) = names.iter().find(|(p, _)| *p == dep_path) else {
+                        continue
+                    };
+                    package.dependencies.push(name.clone());
+                    if !versioned {
+                        package.unversioned.push(name.clone());
+                    }
+                }
+                package
+            })
+            .collect();
+        Ok(Self { packages: publish_order(packages), workspace_version })
+    }
+
+    /// Returns the packages published to crates.io, in publish order.
+    pub fn publishable(&self) -> impl Iterator<Item = &Package> {
+        self.packages.iter().filter(|p| p.publish)
+    }
+
+    /// Whether every published package shares the `workspace.package.version`,
+    /// in which case the workspace is released under a single `v<version>` tag.
+    pub fn shared_version(&self) -> bool {
+        self.workspace_version.is_some() && self.publishable().all(|p| p.inherits_version)
+    }
+
+    /// Returns the package the workspace release is named after, the first
+    /// published binary or otherwise the last published library.
+    pub fn primary(&self) -> Option<&Package> {
+        self.publishable().find(|p| p.bin).or_else(|| self.publishable().last())
+    }
+
+    /// Returns the problems that would stop the packages from being published.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for package in self.publishable() {
+            for dep in &package.dependencies {
+                if self.packages.iter().any(|p| p.name == *dep && !p.publish) {
+                    warnings.push(format!(
+                        "The {} crate depends on {}, which has `publish = false`",
+                        package.name, dep
+                    ));
+                } else if package.unversioned.contains(dep) {
+                    warnings.push(format!(
+                        "The {} crate depends on {} by path without a version, which crates.io rejects",
+                        package.name, dep
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Returns the commands publishing each package in order, skipping the
+    /// versions already on crates.io so that the release can be rerun.
+    pub fn publish_commands(&self) -> Vec<String> {
+        if self.packages.is_empty() {
+            return vec!["cargo publish".to_string()];
+        }
+        self.publishable()
+            .map(|p| {
+                format!(
+                    "if curl -sfA amble https://crates.io/api/v1/crates/{name}/$({version}) > /dev/null; \
+                     then echo \"{name} is already published\"; else cargo publish -p {name}; fi",
+                    name = p.name,
+                    version = version_command(Some(p)),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the tag prefix of the package, `v` for a shared version.
+    fn tag_prefix(&self, package: Option<&Package>) -> String {
+        match package {
+            Some(package) if !self.shared_version() => format!("{}-v", package.name),
+            _ => "v".to_string(),
+        }
+    }
+
+    /// Renders the github workflow publishing the packages after CI passes on main.
+    pub fn publish_workflow(&self) -> String {
+        let mut out = String::from("name: Release\n");
+        out.push_str(WORKFLOW_RUN);
+        out.push_str("\njobs:\n  publish:\n    name: Publish Crates to Crates.io\n");
+        out.push_str("    runs-on: ubuntu-latest\n");
+        out.push_str("    if: github.event_name == 'workflow_dispatch' || github.event.workflow_run.conclusion == 'success'\n");
+        out.push_str("    steps:\n      - uses: actions/checkout@v4\n");
+        out.push_str(TOOLCHAIN_STEP);
+        for package in self.packages.iter().filter(|p| !p.publish) {
+            let _ = writeln!(out, "      # {} is skipped with `publish = false`", package.name);
+        }
+        let names = self.publishable().map(|p| p.name.clone()).collect::<Vec<_>>();
+        for (i, command) in self.publish_commands().into_iter().enumerate() {
+            match names.get(i) {
+                Some(name) => {
+                    let _ = writeln!(out, "      - name: Publish {}", name);
+                }
+                None => out.push_str("      - name: Publish\n"),
+            }
+            let _ = writeln!(out, "        run: {}", command);
+            out.push_str(
+                "        env:\n          CARGO_REGISTRY_TOKEN: ${{ secrets.CARGO_REGISTRY_TOKEN }}\n",
+            );
+        }
+        out
+    }
+
+    /// Renders the github workflow tagging the released versions after CI passes on main.
+    ///
+    /// A shared version is tagged `v<version>`, otherwise each published
+    /// package is tagged `<name>-v<version>`.
+    pub fn tag_workflow(&self) -> String {
+        let mut out = String::from("name: Tag\n");
+        out.push_str(WORKFLOW_RUN);
+        out.push_str("\njobs:\n  tag:\n    runs-on: ubuntu-latest\n");
+        out.push_str("    if: github.event_name == 'workflow_dispatch' || github.event.workflow_run.conclusion == 'success'\n");
+        out.push_str("    permissions:\n      contents: write\n    timeout-minutes: 20\n");
+        out.push_str("    steps:\n      - uses: actions/checkout@v4\n");
+        let packages = match self.shared_version() || self.packages.is_empty() {
+            true => vec![self.primary()],
+            false => self.publishable().map(Some).collect(),
+        };
+        for package in packages {
+            let prefix = self.tag_prefix(package);
+            let _ = writeln!(out, "      - name: Tag {}<version>", prefix);
+            out.push_str("        run: |\n");
+            let _ = writeln!(out, "          TAG=\"{}$({})\"", prefix, version_command(package));
+            out.push_str("          if git ls-remote --exit-code --tags origin \"refs/tags/$TAG\" > /dev/null; then\n");
+            out.push_str("            echo \"$TAG already exists\"\n          else\n");
+            out.push_str(
+                "            git tag \"$TAG\" && git push origin \"$TAG\"\n          fi\n",
+            );
+        }
+        out
+    }
+
+    /// Renders the github workflow creating a release with the binaries of
+    /// the primary package's tag after CI passes on main.
+    pub fn github_release_workflow(&self) -> String {
+        let mut out = String::from("name: Github Release\n");
+        out.push_str(WORKFLOW_RUN);
+        out.push_str(
+            "\njobs:\n  release:\n    name: Release on Github\n    runs-on: ubuntu-latest\n",
+        );
+        out.push_str("    if: github.event_name == 'workflow_dispatch' || github.event.workflow_run.conclusion == 'success'\n");
+        out.push_str("    permissions:\n      contents: write\n");
+        out.push_str("    steps:\n      - uses: actions/checkout@v4\n");
+        out.push_str(TOOLCHAIN_STEP);
+        let primary = self.primary();
+        out.push_str("      - name: Export the release tag\n        id: tag\n");
+        let _ = writeln!(
+            out,
+            "        run: echo \"TAG={}$({})\" >> $GITHUB_OUTPUT",
+            self.tag_prefix(primary),
+            version_command(primary)
+        );
+        out.push_str("      - run: cargo build --release --workspace\n");
+        let bins =
+            self.packages.iter().filter(|p| p.bin).map(|p| p.name.as_str()).collect::<Vec<_>>();
+        if !bins.is_empty() {
+            out.push_str("      - name: Generate the release tarball\n");
+            let _ = writeln!(
+                out,
+                "        run: tar -czf release.tar.gz -C target/release {}",
+                bins.join(" ")
+            );
+        }
+        out.push_str("      - uses: ncipollo/release-action@v1\n        with:\n");
+        if !bins.is_empty() {
+            out.push_str("          artifacts: release.tar.gz\n");
+        }
+        out.push_str(
+            "          tag: ${{ steps.tag.outputs.TAG }}\n          skipIfReleaseExists: true\n",
+        );
+        out
+    }
+
+    /// Renders the github workflow checking pull requests bump the versions past their latest tag.
+    ///
+    /// A shared version must be bumped by every pull request, while a package
+    /// with its own version only needs a bump when its directory changes.
+    pub fn validate_version_workflow(&self) -> String {
+        let mut out = String::from("name: Validate Release Version\n\n");
+        out.push_str("on:\n  pull_request:\n    branches:\n      - main\n  workflow_dispatch:\n");
+        out.push_str("\njobs:\n  validate-version:\n    runs-on: ubuntu-latest\n    steps:\n");
+        out.push_str(
+            "      - uses: actions/checkout@v4\n        with:\n          fetch-depth: 0\n",
+        );
+        let packages = match self.shared_version() || self.packages.is_empty() {
+            true => vec![self.primary()],
+            false => self.publishable().map(Some).collect(),
+        };
+        for package in packages {
+            let prefix = self.tag_prefix(package);
+            let label = match package {
+                Some(package) if !self.shared_version() => package.name.as_str(),
+                _ => "workspace",
+            };
+            let _ = writeln!(out, "      - name: Validate the {} version", label);
+            out.push_str("        run: |\n");
+            if let Some(package) = package.filter(|_| !self.shared_version()) {
+                let path = match package.path.as_os_str().is_empty() {
+                    true => ".".to_string(),
+                    false => package.path.display().to_string(),
+                };
+                let _ = writeln!(
+                    out,
+                    "          if git diff --quiet \"origin/${{{{ github.base_ref || 'main' }}}}\" -- {}; then exit 0; fi",
+                    path
+                );
+            }
+            let _ = writeln!(out, "          VERSION=$({})", version_command(package));
+            let _ = writeln!(
+                out,
+                "          LATEST=$(git tag --list '{0}*' --sort=-v:refname | head -n 1)\n          LATEST=${{LATEST#{0}}}",
+                prefix
+            );
+            out.push_str("          if [ -n \"$LATEST\" ] && [ \"$(printf '%s\\n' \"$LATEST\" \"$VERSION\" | sort -V | tail -n 1)\" = \"$LATEST\" ]; then\n");
+            let _ = writeln!(
+                out,
+                "            echo \"::error::The {} version $VERSION must be greater than the latest tag $LATEST\"",
+                label
+            );
+            out.push_str("            exit 1\n          fi\n");
+        }
+        out
+    }
+}
+
+/// The trigger of the workflows that run after CI passes on main.
+const WORKFLOW_RUN: &str = "
+on:
+  workflow_dispatch:
+  workflow_run:
+    workflows: [\"CI\"]
+    branches: [main]
+    types:
+      - completed
+";
+
+/// The step installing the stable toolchain.
+const TOOLCHAIN_STEP: &str =
+    "      - uses: dtolnay/rust-toolchain@v1\n        with:\n          toolchain: stable\n";
+
+/// Returns the command printing the package version, or the root package's if `None`.
+///
+/// The version is read from `cargo metadata` without `jq`, which the gitlab
+/// `rust` image lacks, and without a `Cargo.lock`, which `cargo pkgid` needs.
+fn version_command(package: Option<&Package>) -> String {
+    let metadata = "cargo metadata --format-version 1 --no-deps";
+    match package {
+        Some(package) => format!(
+            "{} | grep -o '\"name\":\"{}\",\"version\":\"[^\"]*' | cut -d'\"' -f8",
+            metadata, package.name
+        ),
+        None => {
+            format!("{} | grep -o '\"version\":\"[^\"]*' | head -n 1 | cut -d'\"' -f4", metadata)
+        }
+    }
+}
+
+/// Removes the `.` and `..` components of the relative path.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Orders the packages so that each comes after its workspace dependencies,
+/// preferring libraries over binaries and then the package name.
+fn publish_order(mut remaining: Vec<Package>) -> Vec<Package> {
+    let mut ordered: Vec<Package> = Vec::new();
+    while !remaining.is_empty() {
+        let ready = remaining
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| {
+                p.dependencies.iter().all(|d| {
+                    ordered.iter().any(|o| o.name == *d) || !remaining.iter().any(|r| r.name == *d)
+                })
+            })
+            .min_by(|(_, a), (_, b)| (a.bin, &a.name).cmp(&(b.bin, &b.name)))
+            .map(|(i, _)| i);
+        // A dependency cycle can't be ordered, so the rest keeps its order.
+        let Some(i) = ready else {
+            ordered.append(&mut remaining);
+            break;
+        };
+        ordered.push(remaining.remove(i));
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_package(dir: &Path, path: &str, manifest: &str, target: &str) {
+        std::fs::create_dir_all(dir.join(path).join("src")).unwrap();
+        std::fs::write(dir.join(path).join("Cargo.toml"), manifest).unwrap();
+        std::fs::write(dir.join(path).join("src").join(target), "").unwrap();
+    }
+
+    fn workspace() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            r#"[workspace]
+members = ["bin/*", "crates/*"]
+
+[workspace.package]
+version = "0.2.0"
+
+[workspace.dependencies]
+util = { path = "crates/util" }
+"#,
+        )
+        .unwrap();
+        let inherited = "version = { workspace = true }";
+        write_package(
+            dir.path(),
+            "bin/app",
+            &format!(
+                "[package]\nname = \"app\"\n{}\n\n[dependencies]\nutil = {{ workspace = true }}\n",
+                inherited
+            ),
+            "main.rs",
+        );
+        write_package(
+            dir.path(),
+            "crates/util",
+            &format!(
+                "[package]\nname = \"util\"\n{}\n\n[dependencies]\ncore = {{ path = \"../core\", version = \"0.2.0\" }}\n",
+                inherited
+            ),
+            "lib.rs",
+        );
+        write_package(
+            dir.path(),
+            "crates/core",
+            &format!("[package]\nname = \"core\"\n{}\n", inherited),
+            "lib.rs",
+        );
+        write_package(
+            dir.path(),
+            "crates/internal",
+            "[package]\nname = \"internal\"\nversion = \"0.1.0\"\npublish = false\n",
+            "lib.rs",
+        );
+        dir
+    }
+
+    #[test]
+    fn test_release_plan_order() {
+        let dir = workspace();
+        let plan = ReleasePlan::workspace(dir.path()).unwrap();
+        let names = plan.packages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        // Libraries in dependency order come before the binaries.
+        assert_eq!(names, vec!["core", "internal", "util", "app"]);
+        let publishable = plan.publishable().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(publishable, vec!["core", "util", "app"]);
+        assert_eq!(plan.workspace_version.as_deref(), Some("0.2.0"));
+        assert!(plan.shared_version());
+        assert_eq!(plan.primary().unwrap().name, "app");
+        assert_eq!(plan.packages[0].path, Path::new("crates/core"));
+        assert_eq!(plan.packages[3].dependencies, vec!["util"]);
+
+        // The workspace dependency on util has no version.
+        assert_eq!(
+            plan.warnings(),
+            vec![
+                "The app crate depends on util by path without a version, which crates.io rejects"
+            ]
+        );
+        assert!(ReleasePlan::workspace(tempdir().unwrap().path()).unwrap().packages.is_empty());
+    }
+
+    #[test]
+    fn test_release_workflows() {
+        let dir = workspace();
+        let plan = ReleasePlan::workspace(dir.path()).unwrap();
+        let publish: serde_yaml::Value = serde_yaml::from_str(&plan.publish_workflow()).unwrap();
+        let steps = publish["jobs"]["publish"]["steps"].as_sequence().unwrap();
+        let names = steps.iter().filter_map(|s| s["name"].as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Publish core", "Publish util", "Publish app"]);
+        assert!(steps[2]["run"].as_str().unwrap().ends_with("else cargo publish -p core; fi"));
+        assert!(plan.publish_workflow().contains("# internal is skipped with `publish = false`"));
+
+        // A shared version is tagged and validated once.
+        let tag: serde_yaml::Value = serde_yaml::from_str(&plan.tag_workflow()).unwrap();
+        let steps = tag["jobs"]["tag"]["steps"].as_sequence().unwrap();
+        assert_eq!(steps.len(), 2);
+        let run = steps[1]["run"].as_str().unwrap();
+        assert!(run.starts_with("TAG=\"v$(cargo metadata --format-version 1 --no-deps | grep -o"));
+        assert!(run.contains("'\"name\":\"app\",\"version\":\"[^\"]*'"));
+        let release = plan.github_release_workflow();
+        assert!(release.contains("tar -czf release.tar.gz -C target/release app\n"));
+        serde_yaml::from_str::<serde_yaml::Value>(&release).unwrap();
+
+        // Otherwise each crate is tagged and validated on its own.
+        std::fs::write(
+            dir.path().join("crates").join("core").join("Cargo.toml"),
+            "[package]\nname = \"core\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        let plan = ReleasePlan::workspace(dir.path()).unwrap();
+        assert!(!plan.shared_version());
+        let tag: serde_yaml::Value = serde_yaml::from_str(&plan.tag_workflow()).unwrap();
+        let steps = tag["jobs"]["tag"]["steps"].as_sequence().unwrap();
+        let names = steps.iter().filter_map(|s| s["name"].as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Tag core-v<version>", "Tag util-v<version>", "Tag app-v<version>"]);
+        let validate: serde_yaml::Value =
+            serde_yaml::from_str(&plan.validate_version_workflow()).unwrap();
+        let steps = validate["jobs"]["validate-version"]["steps"].as_sequence().unwrap();
+        assert_eq!(steps[1]["name"], "Validate the core version");
+        let run = steps[1]["run"].as_str().unwrap();
+        assert!(run.contains("-- crates/core; then exit 0; fi\n"));
+        assert!(run.contains("LATEST=${LATEST#core-v}\n"));
+    }
+}
@@ -0,0 +1,220 @@
+use ptree::{item::StringItem, TreeBuilder};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// The action taken on a file or directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    /// The file or directory was created (or overwritten).
+    Created,
+    /// The file already existed and was appended to.
+    Appended,
+    /// The file was deliberately not written.
+    Skipped,
+}
+
+/// A file or directory touched during a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileRecord {
+    /// The path relative to the project directory.
+    pub path: PathBuf,
+    /// Whether the path is a directory.
+    pub directory: bool,
+    /// The action taken on the path.
+    pub action: FileAction,
+}
+
+/// A command executed during a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandRecord {
+    /// The executed program.
+    pub program: String,
+    /// The program arguments.
+    pub args: Vec<String>,
+    /// The working directory the command was executed in.
+    pub dir: PathBuf,
+    /// The exit code, if the command exited normally.
+    pub status: Option<i32>,
+    /// Whether the command exited successfully.
+    pub success: bool,
+}
+
+/// A network fetch performed during a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FetchRecord {
+    /// The fetched url.
+    pub url: String,
+    /// Whether the fetch succeeded.
+    pub success: bool,
+}
+
+/// A machine-readable report of a run.
+///
+/// The report is threaded through the `create` functions in place of a
+/// [TreeBuilder]. It records every file, command and network fetch, and
+/// renders the dry run directory tree from the recorded files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    /// The project directory.
+    pub root: String,
+    /// Whether the run was a dry run, in which case nothing was written.
+    pub dry_run: bool,
+    /// Files and directories in the order they were recorded.
+    pub files: Vec<FileRecord>,
+    /// Executed commands in the order they were run.
+    pub commands: Vec<CommandRecord>,
+    /// Network fetches in the order they were performed.
+    pub fetches: Vec<FetchRecord>,
+    /// Warnings about the generated files, e.g. unfilled license placeholders.
+    pub warnings: Vec<String>,
+    /// The error the run failed with, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip)]
+    cwd: PathBuf,
+}
+
+impl Report {
+    /// Creates a new, empty report for the given project directory.
+    pub fn new(root: impl Into<String>, dry_run: bool) -> Self {
+        Self { root: root.into(), dry_run, ..Default::default() }
+    }
+
+    /// Enters the named directory, recording it if it wasn't already.
+    pub fn begin_dir(&mut self, name: impl AsRef<Path>) -> &mut Self {
+        let path = self.cwd.join(name);
+        if !self.files.iter().any(|f| f.directory && f.path == path) {
+            self.files.push(FileRecord {
+                path: path.clone(),
+                directory: true,
+                action: FileAction::Created,
+            });
+        }
+        self.cwd = path;
+        self
+    }
+
+    /// Leaves the current directory.
+    pub fn end_dir(&mut self) -> &mut Self {
+        self.cwd.pop();
+        self
+    }
+
+    /// Records the named file in the current directory.
+    pub fn file(&mut self, name: impl AsRef<Path>, action: FileAction) -> &mut Self {
+        let path = self.cwd.join(name);
+        self.files.push(FileRecord { path, directory: false, action });
+        self
+    }
+
+    /// Records a created file in the current directory.
+    pub fn created(&mut self, name: impl AsRef<Path>) -> &mut Self {
+        self.file(name, FileAction::Created)
+    }
+
+    /// Records an executed command and its exit status.
+    pub fn command(&mut self, record: CommandRecord) -> &mut Self {
+        self.commands.push(record);
+        self
+    }
+
+    /// Records a network fetch.
+    pub fn fetch(&mut self, url: impl Into<String>, success: bool) -> &mut Self {
+        self.fetches.push(FetchRecord { url: url.into(), success });
+        self
+    }
+
+    /// Records a warning.
+    pub fn warn(&mut self, warning: impl Into<String>) -> &mut Self {
+        self.warnings.push(warning.into());
+        self
+    }
+
+    /// Records the error the run failed with.
+    pub fn fail(&mut self, error: impl std::fmt::Display) -> &mut Self {
+        self.error = Some(error.to_string());
+        self
+    }
+
+    /// Serializes the report as pretty-printed json.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Builds the directory tree of recorded files, omitting skipped files.
+    pub fn tree(&self) -> StringItem {
+        let mut builder = TreeBuilder::new(self.root.clone());
+        self.add_children(Path::new(""), &mut builder);
+        builder.build()
+    }
+
+    fn add_children(&self, parent: &Path, builder: &mut TreeBuilder) {
+        let children = self
+            .files
+            .iter()
+            .filter(|f| f.action != FileAction::Skipped && f.path.parent() == Some(parent));
+        for child in children {
+            let name = child.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if child.directory {
+                builder.begin_child(name);
+                self.add_children(&child.path, builder);
+                builder.end_child();
+            } else {
+                builder.add_empty_child(name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree() {
+        let mut report = Report::new("project", true);
+        report.created("Cargo.toml");
+        report.begin_dir("bin").begin_dir("example").created("Cargo.toml").end_dir().end_dir();
+        report.begin_dir("bin").begin_dir("other").end_dir().end_dir();
+        report.file("README.md", FileAction::Skipped);
+
+        let paths = report.files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("Cargo.toml"),
+                PathBuf::from("bin"),
+                PathBuf::from("bin/example"),
+                PathBuf::from("bin/example/Cargo.toml"),
+                PathBuf::from("bin/other"),
+                PathBuf::from("README.md"),
+            ]
+        );
+
+        let mut output = Vec::new();
+        ptree::write_tree(&report.tree(), &mut output).unwrap();
+        let expected =
+            "project\n├─ Cargo.toml\n└─ bin\n   ├─ example\n   │  └─ Cargo.toml\n   └─ other\n";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut report = Report::new("project", false);
+        report.begin_dir(".github").created("ci.yml").end_dir();
+        report.fetch("https://example.com", false);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["root"], "project");
+        assert_eq!(json["dry_run"], false);
+        assert_eq!(json["files"][1]["path"], ".github/ci.yml");
+        assert_eq!(json["files"][1]["action"], "created");
+        assert_eq!(json["fetches"][0]["success"], false);
+        assert!(json.get("cwd").is_none());
+        assert!(json.get("error").is_none());
+
+        report.fail("Conflict: Cargo.toml");
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["error"], "Conflict: Cargo.toml");
+    }
+}
@@ -1,9 +1,17 @@
 use leon::Template;
-use std::{io::Write, path::Path};
-
-use crate::git;
-use anyhow::Result;
-use ptree::TreeBuilder;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    builder::Dependency,
+    command::{CommandRunner, Tool},
+    error::Result,
+    git,
+    license::License,
+    report::{FileAction, Report},
+};
 use tracing::instrument;
 
 /// A template readme as a string literal.
@@ -11,7 +19,7 @@ pub const TEMPLATE_README: &str = include_str!("../etc/README.md");
 
 /// Creates new top-level workspace artifacts at the given directory &[Path].
 #[allow(clippy::too_many_arguments)]
-#[instrument(name = "workspace", skip(dir, name, description, dry, author, tree))]
+#[instrument(name = "workspace", skip(dir, name, description, dry, author, runner, report))]
 pub fn create(
     dir: &Path,
     name: impl AsRef<str> + std::fmt::Display,
@@ -19,8 +27,10 @@ pub fn create(
     dry: bool,
     no_readme_override: bool,
     author: Option<Vec<String>>,
-    overrides: Option<Vec<String>>,
-    mut tree: Option<&mut TreeBuilder>,
+    license: &License,
+    overrides: Option<Vec<Dependency>>,
+    runner: &dyn CommandRunner,
+    mut report: Option<&mut Report>,
 ) -> Result<()> {
     tracing::info!("Creating top level workspace artifacts for {}", name);
 
@@ -29,41 +39,56 @@ pub fn create(
 
     if !dry && !no_readme_override {
         tracing::debug!("Writing {:?}", dir.join("README.md"));
-        let templated_readme =
-            format_template_readme(name.as_ref(), &description, &get_current_username(&author))?;
+        let templated_readme = format_template_readme(
+            name.as_ref(),
+            &description,
+            &get_current_username(&author, runner),
+            license,
+        )?;
         let mut file = std::fs::File::create(dir.join("README.md"))?;
         file.write_all(templated_readme.as_bytes())?;
     }
-    if !no_readme_override {
-        tree.as_deref_mut().map(|t| t.add_empty_child("README.md".to_string()));
-    }
+    match no_readme_override {
+        true => report.as_deref_mut().map(|r| r.file("README.md", FileAction::Skipped)),
+        false => report.as_deref_mut().map(|r| r.created("README.md")),
+    };
 
     if !dry {
         tracing::debug!("Writing {:?}", dir.join("Cargo.toml"));
-        fill_cargo(&dir.join("Cargo.toml"), author, name.as_ref(), &description, overrides)?;
+        fill_cargo(
+            &dir.join("Cargo.toml"),
+            author,
+            name.as_ref(),
+            &description,
+            license,
+            overrides,
+            runner,
+            report.as_deref_mut(),
+        )?;
     }
-    tree.map(|t| t.add_empty_child("Cargo.toml".to_string()));
+    report.map(|r| r.created("Cargo.toml"));
 
     Ok(())
 }
 
-/// Returns the current username.
-pub fn get_current_username(authors: &Option<Vec<String>>) -> String {
+/// Returns the current username, the first author or else the git username.
+pub fn get_current_username(authors: &Option<Vec<String>>, runner: &dyn CommandRunner) -> String {
     match authors {
-        Some(v) => v[0].clone(),
-        None => match git::try_git_username() {
+        // Authors may be formatted as `name <email>`.
+        Some(v) => v[0].split(" <").next().unwrap_or_default().to_string(),
+        None => match git::try_git_username(runner) {
             Some(name) => name,
             None => whoami::username().to_string(),
         },
     }
 }
 
-/// Returns the current authors.
-pub fn get_authors(authors: Option<Vec<String>>) -> toml_edit::Item {
+/// Returns the current authors, defaulting to the git username.
+pub fn get_authors(authors: Option<Vec<String>>, runner: &dyn CommandRunner) -> toml_edit::Item {
     let mut array = toml_edit::Array::default();
     match authors {
         Some(v) => v.into_iter().for_each(|a| array.push(a)),
-        None => match git::try_git_username() {
+        None => match git::try_git_username(runner) {
             Some(name) => array.push(name),
             None => array.push(whoami::username().to_string()),
         },
@@ -71,28 +96,50 @@ pub fn get_authors(authors: Option<Vec<String>>) -> toml_edit::Item {
     toml_edit::value(array)
 }
 
-/// Fetch a packages version using bash commands and `cargo search`.
-pub fn fetch_version(c: &str) -> Option<String> {
-    let cargo_search_output =
-        std::process::Command::new("cargo").arg("search").arg(c).output().ok()?;
-    if !cargo_search_output.status.success() {
-        tracing::warn!("Failed to run `cargo search {}` command", c);
-        return None;
-    }
-    let output_str = String::from_utf8(cargo_search_output.stdout).ok()?;
+/// Fetch a packages version with `cargo search`.
+pub fn fetch_version(c: &str, runner: &dyn CommandRunner) -> Option<String> {
+    let output_str = match runner.run(Tool::Cargo, &["search", c], Path::new("."), None) {
+        Ok(output) => output.stdout,
+        Err(e) => {
+            tracing::warn!("Failed to run `cargo search {}` command: {}", c, e);
+            return None;
+        }
+    };
     let anyhow_line = output_str.lines().find(|l| l.starts_with(&format!("{} = ", c)))?;
     let version =
         anyhow_line.strip_prefix(&format!("{} = \"", c)).and_then(|s| s.split('"').next());
     version.map(|s| s.to_string())
 }
 
+/// Returns the crates.io api url queried by `cargo search` for the given package.
+pub fn crates_io_search_url(c: &str) -> String {
+    format!("https://crates.io/api/v1/crates?q={}", c)
+}
+
+/// Resolves a package version with [fetch_version], falling back to the given default.
+/// The lookup is recorded as a network fetch in the [Report].
+pub fn resolve_version(
+    c: &str,
+    default: &str,
+    runner: &dyn CommandRunner,
+    report: Option<&mut Report>,
+) -> String {
+    let version = fetch_version(c, runner);
+    report.map(|r| r.fetch(crates_io_search_url(c), version.is_some()));
+    version.unwrap_or_else(|| default.to_string())
+}
+
 /// Writes binary contents to the `Cargo.toml` file located at [file].
+#[allow(clippy::too_many_arguments)]
 pub fn fill_cargo(
     file: &Path,
     author: Option<Vec<String>>,
     name: &str,
     description: &str,
-    overrides: Option<Vec<String>>,
+    license: &License,
+    overrides: Option<Vec<Dependency>>,
+    runner: &dyn CommandRunner,
+    report: Option<&mut Report>,
 ) -> Result<()> {
     let mut manifest = toml_edit::Document::new();
 
@@ -108,10 +155,10 @@ pub fn fill_cargo(
     manifest["workspace.package"]["description"] = toml_edit::value(description);
     manifest["workspace.package"]["version"] = toml_edit::value("0.1.0");
     manifest["workspace.package"]["edition"] = toml_edit::value("2021");
-    manifest["workspace.package"]["license"] = toml_edit::value("MIT");
-    let user = get_current_username(&author);
+    manifest["workspace.package"]["license"] = toml_edit::value(license.expression());
+    let user = get_current_username(&author, runner);
     let repo = git::build_repository_url(&user, name);
-    manifest["workspace.package"]["authors"] = get_authors(author);
+    manifest["workspace.package"]["authors"] = get_authors(author, runner);
     manifest["workspace.package"]["repository"] = toml_edit::value(&repo);
     manifest["workspace.package"]["homepage"] = toml_edit::value(&repo);
     let mut array = toml_edit::Array::default();
@@ -120,7 +167,7 @@ pub fn fill_cargo(
     array.push("tests".to_string());
     manifest["workspace.package"]["exclude"] = toml_edit::value(array);
 
-    add_workspace_deps(&mut manifest, overrides);
+    add_workspace_deps(&mut manifest, overrides, runner, report);
 
     manifest["profile.dev"] = toml_edit::Item::Table(toml_edit::Table::new());
     manifest["profile.dev"]["opt-level"] = toml_edit::value(1);
@@ -173,12 +220,21 @@ pub fn list_dependencies() -> Result<()> {
         prettytable::Cell::new("clap"),
         prettytable::Cell::new("4.4.3"),
     ]));
+    table.add_row(prettytable::Row::new(vec![
+        prettytable::Cell::new("amble-telemetry"),
+        prettytable::Cell::new(crate::libs::TELEMETRY_VERSION),
+    ]));
     table.printstd();
     Ok(())
 }
 
 /// Add dependencies to the manifest.
-pub fn add_workspace_deps(manifest: &mut toml_edit::Document, overrides: Option<Vec<String>>) {
+pub fn add_workspace_deps(
+    manifest: &mut toml_edit::Document,
+    overrides: Option<Vec<Dependency>>,
+    runner: &dyn CommandRunner,
+    mut report: Option<&mut Report>,
+) {
     let default_inline_dependencies = vec![
         ("anyhow".to_string(), "1.0".to_string()),
         ("inquire".to_string(), "0.6.2".to_string()),
@@ -188,31 +244,32 @@ pub fn add_workspace_deps(manifest: &mut toml_edit::Document, overrides: Option<
         ("tracing-subscriber".to_string(), "0.3.17".to_string()),
         ("clap".to_string(), "4.4.3".to_string()),
     ];
-    let combined = match overrides {
-        Some(v) => {
-            let mut combined = default_inline_dependencies;
-            let override_deps = v.into_iter().map(|s| (s, "0.0.0".to_string()));
-            combined.extend(override_deps);
-            combined
-        }
-        None => default_inline_dependencies,
-    };
     manifest["workspace.dependencies"] = toml_edit::Item::Table(toml_edit::Table::new());
-    add_inline_deps(manifest, combined);
+    add_inline_deps(manifest, default_inline_dependencies, runner, report.as_deref_mut());
+    for dep in overrides.unwrap_or_default() {
+        manifest["workspace.dependencies"][&dep.name] = dep.resolve(runner, report.as_deref_mut());
+    }
     manifest["workspace.dependencies"]["clap"] =
         toml_edit::Item::Value(toml_edit::Value::InlineTable(toml_edit::InlineTable::new()));
-    let version = fetch_version("clap").unwrap_or_else(|| "4.4.3".to_string());
+    let version = resolve_version("clap", "4.4.3", runner, report);
     manifest["workspace.dependencies"]["clap"]["version"] = toml_edit::value(version);
     let mut array = toml_edit::Array::default();
     array.push("derive".to_string());
     manifest["workspace.dependencies"]["clap"]["features"] = toml_edit::value(array);
+    manifest["workspace.dependencies"]["amble-telemetry"] =
+        toml_edit::value(crate::libs::TELEMETRY_VERSION);
 }
 
 /// Adds inline dependencies to the manifest.
-pub fn add_inline_deps(manifest: &mut toml_edit::Document, deps: Vec<(String, String)>) {
+pub fn add_inline_deps(
+    manifest: &mut toml_edit::Document,
+    deps: Vec<(String, String)>,
+    runner: &dyn CommandRunner,
+    mut report: Option<&mut Report>,
+) {
     let deps_table = manifest["workspace.dependencies"].as_table_mut().unwrap();
     for (dep, default_version) in deps {
-        let version = fetch_version(&dep).unwrap_or_else(|| default_version.to_string());
+        let version = resolve_version(&dep, &default_version, runner, report.as_deref_mut());
         deps_table[&dep] = toml_edit::value(version);
     }
 }
@@ -225,17 +282,72 @@ pub fn remove_table_quotes(s: String) -> String {
     result.to_string()
 }
 
+/// Sets the minimum supported rust version in the manifest of the directory.
+///
+/// A workspace sets `workspace.package.rust-version`, which each member crate
+/// in `bin/` and `crates/` inherits. A package sets `package.rust-version`.
+pub fn set_rust_version(dir: &Path, rust_version: &str) -> Result<()> {
+    let manifest_path = dir.join("Cargo.toml");
+    let mut manifest = parse_manifest(&manifest_path)?;
+    if manifest.get("workspace").is_none() {
+        manifest["package"]["rust-version"] = toml_edit::value(rust_version);
+        std::fs::write(&manifest_path, manifest.to_string())?;
+        return Ok(());
+    }
+    manifest["workspace"]["package"]["rust-version"] = toml_edit::value(rust_version);
+    std::fs::write(&manifest_path, manifest.to_string())?;
+    for member_manifest_path in member_manifests(dir)? {
+        let mut member_manifest = parse_manifest(&member_manifest_path)?;
+        let mut inherited = toml_edit::InlineTable::new();
+        inherited.insert("workspace", true.into());
+        member_manifest["package"]["rust-version"] = toml_edit::value(inherited);
+        std::fs::write(&member_manifest_path, member_manifest.to_string())?;
+    }
+    Ok(())
+}
+
+/// Returns the manifest paths of the member crates in `bin/` and `crates/`.
+pub(crate) fn member_manifests(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut manifests = Vec::new();
+    for members in [dir.join("bin"), dir.join("crates")] {
+        if !members.exists() {
+            continue;
+        }
+        for member in std::fs::read_dir(members)? {
+            let member_manifest_path = member?.path().join("Cargo.toml");
+            if member_manifest_path.exists() {
+                manifests.push(member_manifest_path);
+            }
+        }
+    }
+    Ok(manifests)
+}
+
+/// Parses the `Cargo.toml` manifest at the given path.
+pub(crate) fn parse_manifest(path: &Path) -> Result<toml_edit::Document> {
+    let manifest = std::fs::read_to_string(path)?;
+    manifest.parse::<toml_edit::Document>().map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid {:?}: {}", path, e))
+            .into()
+    })
+}
+
 /// Reads the template readme file and returns the formatted string contents.
 pub fn format_template_readme(
     project_name: &str,
     project_description: &str,
     project_owner: &str,
+    license: &License,
 ) -> Result<String> {
     let template = Template::parse(TEMPLATE_README)?;
     let mut context = std::collections::HashMap::new();
     context.insert("projectname", project_name);
     context.insert("projectdescription", project_description);
     context.insert("projectowner", project_owner);
+    let (badge, link, section) = (license.badge(), license.link(), license.readme_section());
+    context.insert("licensebadge", &badge);
+    context.insert("licenselink", &link);
+    context.insert("licensesection", &section);
     let formatted = template.render(&context)?;
     Ok(formatted)
 }
@@ -243,6 +355,7 @@ pub fn format_template_readme(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::command::tests::MockRunner;
     use std::{fs::File, io::Read};
     use tempfile::tempdir;
 
@@ -253,21 +366,38 @@ mod tests {
         assert!(TEMPLATE_README.contains("{projectname}"));
         assert!(TEMPLATE_README.contains("{projectdescription}"));
         assert!(TEMPLATE_README.contains("{projectowner}"));
-        let template_readme = format_template_readme("example", "example workspace", "refcell")
-            .unwrap_or_else(|_| panic!("Failed to format template readme"));
+        let license = License::new("MIT OR Apache-2.0");
+        let template_readme =
+            format_template_readme("example", "example workspace", "refcell", &license)
+                .unwrap_or_else(|_| panic!("Failed to format template readme"));
         assert!(template_readme.contains("example"));
         assert!(template_readme.contains("example workspace"));
         assert!(template_readme.contains("refcell"));
+        assert!(template_readme.contains("badge/license-MIT%20OR%20Apache--2.0-7795AF.svg"));
+        assert!(template_readme.contains("- [Apache License 2.0](LICENSE-APACHE)"));
+        // The link label doesn't collide with the case-insensitive `[License]` badge.
+        assert!(template_readme.contains("[![License]][license-link]\n"));
+        assert!(template_readme.contains("\n[license-link]: #license\n"));
+        assert!(!template_readme.contains("\n[license]: "));
+
+        let template_readme =
+            format_template_readme("example", "example workspace", "refcell", &License::default())
+                .unwrap();
+        assert!(template_readme.contains("\n[license-link]: LICENSE\n"));
     }
 
     #[test]
     fn test_fetch_version() {
-        let version = fetch_version("anyhow").unwrap();
-        let expected = semver::Version::parse("1.0.75").unwrap();
-        let semversion = semver::Version::parse(&version).unwrap();
-        // expect as greater than or equal to the expected version
-        // since the version may be updated in the future.
-        assert!(semversion.gt(&expected) || semversion.eq(&expected));
+        let runner = MockRunner {
+            stdout: "anyhow = \"1.0.75\"    # Flexible concrete Error type\n".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(fetch_version("anyhow", &runner), Some("1.0.75".to_string()));
+        let args = vec!["search".to_string(), "anyhow".to_string()];
+        assert_eq!(*runner.calls.borrow(), vec![(Tool::Cargo, args)]);
+
+        let runner = MockRunner { fail: Some(Tool::Cargo), ..Default::default() };
+        assert_eq!(fetch_version("anyhow", &runner), None);
     }
 
     #[test]
@@ -304,6 +434,9 @@ mod tests {
             Some(vec!["refcell".to_string()]),
             proj_name,
             "example workspace",
+            &License::default(),
+            None,
+            &MockRunner::default(),
             None,
         )
         .unwrap();
@@ -313,15 +446,6 @@ mod tests {
         let mut cargo_toml = File::open(cargo_toml_path_buf).unwrap();
         let mut cargo_toml_contents = String::new();
         cargo_toml.read_to_string(&mut cargo_toml_contents).unwrap();
-        let anyhow_version = fetch_version("anyhow").unwrap_or_else(|| "1.0".to_string());
-        let inquire_version = fetch_version("inquire").unwrap_or_else(|| "0.6.2".to_string());
-        let tracing_version = fetch_version("tracing").unwrap_or_else(|| "0.1.39".to_string());
-        let serde_version = fetch_version("serde").unwrap_or_else(|| "1.0.189".to_string());
-        let serde_json_version =
-            fetch_version("serde_json").unwrap_or_else(|| "1.0.107".to_string());
-        let tracing_subscriber_version =
-            fetch_version("tracing-subscriber").unwrap_or_else(|| "0.3.17".to_string());
-        let clap_version = fetch_version("clap").unwrap_or_else(|| "4.4.3".to_string());
         let expected_contents = format!(
             r#"[workspace]
 members = ["bin/*", "crates/*"]
@@ -339,13 +463,14 @@ homepage = "https://github.com/refcell/example"
 exclude = ["**/target", "benches/", "tests"]
 
 [workspace.dependencies]
-anyhow = "{}"
-inquire = "{}"
-tracing = "{}"
-serde = "{}"
-serde_json = "{}"
-tracing-subscriber = "{}"
-clap = {{ version = "{}", features = ["derive"] }}
+anyhow = "1.0"
+inquire = "0.6.2"
+tracing = "0.1.39"
+serde = "1.0.189"
+serde_json = "1.0.107"
+tracing-subscriber = "0.3.17"
+clap = {{ version = "4.4.3", features = ["derive"] }}
+amble-telemetry = "{}"
 
 [profile.dev]
 opt-level = 1
@@ -354,13 +479,7 @@ overflow-checks = false
 [profile.bench]
 debug = true
 "#,
-            anyhow_version,
-            inquire_version,
-            tracing_version,
-            serde_version,
-            serde_json_version,
-            tracing_subscriber_version,
-            clap_version
+            crate::libs::TELEMETRY_VERSION
         );
         assert_eq!(cargo_toml_contents, expected_contents);
     }
@@ -369,8 +488,19 @@ debug = true
     fn test_create() {
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
-        create(&dir_path_buf, "example", Some("example workspace"), false, false, None, None, None)
-            .unwrap();
+        create(
+            &dir_path_buf,
+            "example",
+            Some("example workspace"),
+            false,
+            false,
+            None,
+            &License::default(),
+            None,
+            &MockRunner::default(),
+            None,
+        )
+        .unwrap();
         assert!(dir_path_buf.exists());
         assert!(dir_path_buf.join("Cargo.toml").exists());
         assert!(dir_path_buf.join("README.md").exists());
@@ -380,9 +510,69 @@ debug = true
     fn test_create_dry_run() {
         let dir = tempdir().unwrap();
         let dir_path_buf = dir.path().to_path_buf();
-        create(&dir_path_buf, "example", Some("example workspace"), true, false, None, None, None)
-            .unwrap();
+        create(
+            &dir_path_buf,
+            "example",
+            Some("example workspace"),
+            true,
+            false,
+            None,
+            &License::default(),
+            None,
+            &MockRunner::default(),
+            None,
+        )
+        .unwrap();
         assert!(!dir_path_buf.join("Cargo.toml").exists());
         assert!(!dir_path_buf.join("README.md").exists());
     }
+
+    #[test]
+    fn test_create_report() {
+        let dir = tempdir().unwrap();
+        let dir_path_buf = dir.path().to_path_buf();
+        let mut report = Report::new("example", true);
+        create(
+            &dir_path_buf,
+            "example",
+            Some("example workspace"),
+            true,
+            true,
+            None,
+            &License::default(),
+            None,
+            &MockRunner::default(),
+            Some(&mut report),
+        )
+        .unwrap();
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(report.files[0].path, std::path::PathBuf::from("README.md"));
+        assert_eq!(report.files[0].action, FileAction::Skipped);
+        assert_eq!(report.files[1].path, std::path::PathBuf::from("Cargo.toml"));
+        assert_eq!(report.files[1].action, FileAction::Created);
+    }
+
+    #[test]
+    fn test_set_rust_version() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("bin").join("example")).unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"bin/*\"]\n\n[workspace.package]\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("bin").join("example").join("Cargo.toml"),
+            "[package]\nname = \"example\"\nversion = { workspace = true }\n",
+        )
+        .unwrap();
+        set_rust_version(dir.path(), "1.70").unwrap();
+
+        let manifest = std::fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        assert!(manifest.ends_with("version = \"0.1.0\"\nrust-version = \"1.70\"\n"));
+        let member =
+            std::fs::read_to_string(dir.path().join("bin").join("example").join("Cargo.toml"))
+                .unwrap();
+        assert!(member.ends_with("rust-version = { workspace = true }\n"));
+    }
 }
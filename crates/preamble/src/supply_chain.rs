@@ -0,0 +1,160 @@
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use crate::{
+    ci::render_template, error::Result, license::License, release::ReleasePlan, report::Report,
+};
+use tracing::instrument;
+
+/// The `deny.toml` Template.
+pub const DENY_TOML: &str = include_str!("../etc/supply-chain/deny.toml");
+
+/// The `cargo-vet` `supply-chain/config.toml` Template.
+pub const VET_CONFIG_TOML: &str = include_str!("../etc/supply-chain/config.toml");
+
+/// The `cargo-vet` `supply-chain/audits.toml` File.
+pub const VET_AUDITS_TOML: &str = include_str!("../etc/supply-chain/audits.toml");
+
+/// The `cargo-vet` `supply-chain/imports.lock` File.
+pub const VET_IMPORTS_LOCK: &str = include_str!("../etc/supply-chain/imports.lock");
+
+/// The permissive licenses dependencies may use under any project license.
+pub const PERMISSIVE_LICENSES: [&str; 9] = [
+    "MIT",
+    "Apache-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "ISC",
+    "Zlib",
+    "Unicode-3.0",
+    "Unicode-DFS-2016",
+    "CC0-1.0",
+];
+
+/// Returns the licenses dependencies may use, the licenses the project is
+/// unconditionally under followed by the [PERMISSIVE_LICENSES].
+///
+/// A copyleft project, e.g. `GPL-3.0-only`, allows dependencies under the
+/// same license, while a project offered under `GPL-3.0-only OR MIT` only
+/// allows permissive dependencies so the `MIT` option stays usable.
+pub fn allowed_licenses(license: &License) -> Vec<String> {
+    let mut allowed = license.required_ids();
+    for permissive in PERMISSIVE_LICENSES {
+        if !allowed.iter().any(|l| l == permissive) {
+            allowed.push(permissive.to_string());
+        }
+    }
+    allowed
+}
+
+/// Renders the `deny.toml` allowing the [allowed_licenses] of the project license.
+pub fn deny_toml(license: &License) -> String {
+    let allow = allowed_licenses(license)
+        .iter()
+        .map(|l| format!("    \"{}\",", l))
+        .collect::<Vec<_>>()
+        .join("\n");
+    render_template(DENY_TOML, &HashMap::from([("allow", allow)]))
+}
+
+/// Renders the `cargo-vet` `supply-chain/config.toml`, auditing the workspace
+/// packages as first-party crates even if a crate of the same name is published.
+pub fn vet_config(plan: &ReleasePlan) -> String {
+    let mut config = VET_CONFIG_TOML.to_string();
+    for package in &plan.packages {
+        let _ = write!(config, "\n[policy.{}]\naudit-as-crates-io = false\n", package.name);
+    }
+    config
+}
+
+/// Creates the `deny.toml` checked by the `cargo deny` ci job.
+#[instrument(name = "deny", skip(dir, license, dry, report))]
+pub fn create_deny(
+    dir: &Path,
+    license: &License,
+    dry: bool,
+    report: Option<&mut Report>,
+) -> Result<()> {
+    tracing::info!("Creating deny.toml");
+    if !dry {
+        tracing::debug!("Writing {:?}", dir.join("deny.toml"));
+        std::fs::write(dir.join("deny.toml"), deny_toml(license))?;
+    }
+    report.map(|r| r.created("deny.toml"));
+    Ok(())
+}
+
+/// Creates the `cargo-vet` `supply-chain/` directory for the workspace.
+///
+/// The dependencies still need to be audited or exempted, e.g. with
+/// `cargo vet regenerate exemptions`, before `cargo vet` passes.
+#[instrument(name = "vet", skip(dir, dry, report))]
+pub fn create_vet(dir: &Path, dry: bool, mut report: Option<&mut Report>) -> Result<()> {
+    tracing::info!("Creating cargo-vet supply chain");
+    let plan = match !dry && dir.join("Cargo.toml").exists() {
+        true => ReleasePlan::workspace(dir)?,
+        false => ReleasePlan::default(),
+    };
+    let supply_chain_dir = dir.join("supply-chain");
+    crate::utils::create_dir_gracefully!(&supply_chain_dir, dry);
+    report.as_deref_mut().map(|r| r.begin_dir("supply-chain"));
+    let files = [
+        ("config.toml", vet_config(&plan)),
+        ("audits.toml", VET_AUDITS_TOML.to_string()),
+        ("imports.lock", VET_IMPORTS_LOCK.to_string()),
+    ];
+    for (name, contents) in files {
+        if !dry {
+            tracing::debug!("Writing {:?}", supply_chain_dir.join(name));
+            std::fs::write(supply_chain_dir.join(name), contents)?;
+        }
+        report.as_deref_mut().map(|r| r.created(name));
+    }
+    report.as_deref_mut().map(|r| r.end_dir()); // <- supply-chain/
+    let warning = "Run `cargo vet regenerate exemptions` to exempt the current dependencies";
+    tracing::warn!("{}", warning);
+    report.map(|r| r.warn(warning));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_deny_toml() {
+        let deny = deny_toml(&License::new("GPL-3.0-only OR MIT"));
+        let deny = deny.parse::<toml_edit::Document>().unwrap();
+        let allow = deny["licenses"]["allow"].as_array().unwrap();
+        let allow = allow.iter().map(|l| l.as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(allow, PERMISSIVE_LICENSES);
+        assert_eq!(deny["advisories"]["yanked"].as_str(), Some("deny"));
+        assert_eq!(deny["bans"]["deny"].as_array().unwrap().len(), 2);
+
+        let deny = deny_toml(&License::new("GPL-3.0-only AND MIT"));
+        let deny = deny.parse::<toml_edit::Document>().unwrap();
+        let allow = deny["licenses"]["allow"].as_array().unwrap();
+        let allow = allow.iter().map(|l| l.as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(allow[..3], ["GPL-3.0-only", "MIT", "Apache-2.0"]);
+        assert_eq!(allow.len(), PERMISSIVE_LICENSES.len() + 1);
+    }
+
+    #[test]
+    fn test_create_vet() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"common\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        let mut report = Report::new("example", false);
+        create_vet(dir.path(), false, Some(&mut report)).unwrap();
+        let config = std::fs::read_to_string(dir.path().join("supply-chain/config.toml")).unwrap();
+        let config = config.parse::<toml_edit::Document>().unwrap();
+        assert_eq!(config["cargo-vet"]["version"].as_str(), Some("0.10"));
+        assert_eq!(config["policy"]["common"]["audit-as-crates-io"].as_bool(), Some(false));
+        assert!(dir.path().join("supply-chain/audits.toml").exists());
+        assert!(dir.path().join("supply-chain/imports.lock").exists());
+        assert_eq!(report.warnings.len(), 1);
+    }
+}
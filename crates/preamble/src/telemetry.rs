@@ -1,8 +1,12 @@
-use anyhow::Result;
-use tracing::Level;
+use crate::error::{Error, Result};
+
+pub use amble_telemetry::{verbosity_level, FileOutput, LogFormat, Rotation, Telemetry};
 
 /// Initializes the tracing subscriber.
 ///
+/// This is a thin compatibility shim over [Telemetry], which additionally
+/// supports `RUST_LOG` directives, output formats, file output and span timing.
+///
 /// The verbosity level determines the maximum level of tracing.
 /// - 0: ERROR
 /// - 1: WARN
@@ -14,16 +18,10 @@ use tracing::Level;
 /// * `verbosity_level` - The verbosity level (0-4)
 ///
 /// # Returns
-/// * `Result<()>` - Ok if successful, Err otherwise.
+/// * `Result<()>` - Ok if successful, [Error::Io] otherwise.
 pub fn init_tracing_subscriber(verbosity_level: u8) -> Result<()> {
-    let subscriber = tracing_subscriber::fmt()
-        .with_max_level(match verbosity_level {
-            0 => Level::ERROR,
-            1 => Level::WARN,
-            2 => Level::INFO,
-            3 => Level::DEBUG,
-            _ => Level::TRACE,
-        })
-        .finish();
-    tracing::subscriber::set_global_default(subscriber).map_err(|e| anyhow::anyhow!(e))
+    Telemetry::new()
+        .verbosity(verbosity_level)
+        .init()
+        .map_err(|e| Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))
 }
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use crate::{error::Result, pipeline::CiConfig, report::Report};
+use tracing::instrument;
+
+/// The `rustfmt.toml` File, amble's own formatting style.
+pub const RUSTFMT_TOML: &str = include_str!("../etc/tooling/rustfmt.toml");
+
+/// The `.editorconfig` File.
+pub const EDITORCONFIG: &str = include_str!("../etc/tooling/editorconfig");
+
+/// Renders the `rust-toolchain.toml` pinning the [CiConfig] channel with the
+/// components the fmt and lint jobs use.
+pub fn rust_toolchain_toml(config: &CiConfig) -> String {
+    format!(
+        "[toolchain]\nchannel = \"{}\"\ncomponents = [\"rustfmt\", \"clippy\"]\nprofile = \"minimal\"\n",
+        config.channel
+    )
+}
+
+/// Renders the `clippy.toml`, linting against the [CiConfig] minimum supported
+/// rust version if there is one.
+pub fn clippy_toml(config: &CiConfig) -> String {
+    let mut clippy = String::new();
+    if let Some(msrv) = &config.msrv {
+        clippy.push_str(&format!("msrv = \"{}\"\n", msrv));
+    }
+    clippy.push_str("allow-unwrap-in-tests = true\nallow-expect-in-tests = true\n");
+    clippy
+}
+
+/// Creates the `rustfmt.toml`, `clippy.toml`, `rust-toolchain.toml` and
+/// `.editorconfig` matching the toolchain and MSRV of the [CiConfig].
+///
+/// The ci workflow should be generated from the same [CiConfig] with
+/// [CiConfig::toolchain_file] and [CiConfig::nightly_rustfmt] set, so the
+/// fmt job runs the nightly rustfmt the `rustfmt.toml` needs.
+#[instrument(name = "tooling", skip(dir, config, dry, report))]
+pub fn create(
+    dir: &Path,
+    config: &CiConfig,
+    dry: bool,
+    mut report: Option<&mut Report>,
+) -> Result<()> {
+    tracing::info!("Creating tooling config");
+    let files = [
+        ("rustfmt.toml", RUSTFMT_TOML.to_string()),
+        ("clippy.toml", clippy_toml(config)),
+        ("rust-toolchain.toml", rust_toolchain_toml(config)),
+        (".editorconfig", EDITORCONFIG.to_string()),
+    ];
+    for (name, contents) in files {
+        if !dry {
+            tracing::debug!("Writing {:?}", dir.join(name));
+            std::fs::write(dir.join(name), contents)?;
+        }
+        report.as_deref_mut().map(|r| r.created(name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_create_tooling() {
+        let dir = tempdir().unwrap();
+        let config = CiConfig {
+            channel: "1.75".to_string(),
+            msrv: Some("1.70".to_string()),
+            ..Default::default()
+        };
+        create(dir.path(), &config, false, None).unwrap();
+        let clippy = std::fs::read_to_string(dir.path().join("clippy.toml")).unwrap();
+        assert!(clippy.starts_with("msrv = \"1.70\"\n"));
+        assert!(dir.path().join("rustfmt.toml").exists());
+        assert!(dir.path().join(".editorconfig").exists());
+
+        // The ci workflow reads the toolchain back from the project.
+        let manifest = "[package]\nname = \"example\"\nrust-version = \"1.70\"\n";
+        std::fs::write(dir.path().join("Cargo.toml"), manifest).unwrap();
+        let workspace = CiConfig::default().workspace(dir.path()).unwrap();
+        assert_eq!(workspace.channel, "1.75");
+        assert_eq!(workspace.msrv.as_deref(), Some("1.70"));
+        assert!(workspace.toolchain_file);
+    }
+
+    #[test]
+    fn test_tooling_ci_toolchains() {
+        let config = CiConfig {
+            msrv: Some("1.70".to_string()),
+            toolchain_file: true,
+            nightly_rustfmt: true,
+            ..Default::default()
+        };
+        let workflow: serde_yaml::Value =
+            serde_yaml::from_str(&Pipeline::ci(&config).github()).unwrap();
+        let fmt = workflow["jobs"]["fmt"]["steps"].as_sequence().unwrap();
+        assert_eq!(fmt[1]["with"]["toolchain"], "nightly");
+        assert_eq!(fmt[3]["env"]["RUSTUP_TOOLCHAIN"], "nightly");
+        // The matrix toolchain overrides the `rust-toolchain.toml`.
+        let test = workflow["jobs"]["test"]["steps"].as_sequence().unwrap();
+        assert_eq!(test[3]["env"]["RUSTUP_TOOLCHAIN"], "${{ matrix.toolchain }}");
+        // Jobs on the pinned channel use the toolchain file.
+        let build = workflow["jobs"]["build"]["steps"].as_sequence().unwrap();
+        assert!(build[3].get("env").is_none());
+    }
+}
@@ -0,0 +1,239 @@
+use std::{fmt::Write, path::Path};
+
+use crate::{error::Result, report::Report, root::parse_manifest};
+use tracing::instrument;
+
+/// The bot keeping the dependencies and github actions current.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UpdateBot {
+    /// A `.github/dependabot.yml` config.
+    #[default]
+    Dependabot,
+    /// A `renovate.json` config.
+    Renovate,
+}
+
+impl std::fmt::Display for UpdateBot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Dependabot => write!(f, "dependabot"),
+            Self::Renovate => write!(f, "renovate"),
+        }
+    }
+}
+
+impl std::str::FromStr for UpdateBot {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "dependabot" => Ok(Self::Dependabot),
+            "renovate" => Ok(Self::Renovate),
+            _ => Err(format!("unknown update bot \"{}\", expected dependabot or renovate", s)),
+        }
+    }
+}
+
+/// Reads the registry dependencies of the manifest in the directory, the
+/// `[workspace.dependencies]` of a workspace or the `[dependencies]` of a
+/// crate. Path dependencies are skipped.
+pub fn manifest_dependencies(dir: &Path) -> Result<Vec<String>> {
+    let manifest = parse_manifest(&dir.join("Cargo.toml"))?;
+    let deps = manifest
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .or_else(|| manifest.get("dependencies"))
+        .and_then(|d| d.as_table_like());
+    let mut names = deps
+        .map(|deps| {
+            deps.iter()
+                .filter(|(_, dep)| dep.get("path").is_none())
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    names.sort();
+    Ok(names)
+}
+
+/// Groups the dependencies of a family, sharing the name before the first
+/// `-` or `_`, e.g. `serde` and `serde_json`, so they're updated together.
+pub fn dependency_groups(deps: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for dep in deps {
+        let family = dep.split(['-', '_']).next().unwrap_or(dep);
+        match groups.iter_mut().find(|(name, _)| name == family) {
+            Some((_, members)) => members.push(dep.clone()),
+            None => groups.push((family.to_string(), vec![dep.clone()])),
+        }
+    }
+    groups.retain(|(_, members)| members.len() > 1);
+    groups.sort();
+    groups
+}
+
+/// Renders the `.github/dependabot.yml` updating the cargo dependencies and
+/// github actions weekly.
+///
+/// Each dependency family is updated in one pull request, and the minor and
+/// patch updates of the other dependencies and of the actions are grouped.
+pub fn dependabot(deps: &[String]) -> String {
+    let mut yml = String::from("version: 2\nupdates:\n");
+    yml.push_str("  - package-ecosystem: cargo\n    directory: /\n");
+    yml.push_str("    schedule:\n      interval: weekly\n");
+    yml.push_str("    open-pull-requests-limit: 10\n    groups:\n");
+    for (family, members) in dependency_groups(deps) {
+        let _ = writeln!(yml, "      {}:\n        patterns:", family);
+        for member in members {
+            let _ = writeln!(yml, "          - {}", member);
+        }
+    }
+    yml.push_str("      dependencies:\n        patterns:\n          - \"*\"\n");
+    yml.push_str("        update-types:\n          - minor\n          - patch\n");
+    yml.push_str("  - package-ecosystem: github-actions\n    directory: /\n");
+    yml.push_str("    schedule:\n      interval: weekly\n    groups:\n");
+    yml.push_str("      actions:\n        patterns:\n          - \"*\"\n");
+    yml
+}
+
+/// Renders the `renovate.json` updating the cargo dependencies and github
+/// actions weekly, grouped like the [dependabot] config.
+pub fn renovate(deps: &[String]) -> String {
+    // Later package rules take precedence, so the families follow the catch-all group.
+    let mut rules = vec![serde_json::json!({
+        "matchManagers": ["cargo"],
+        "matchUpdateTypes": ["minor", "patch"],
+        "groupName": "dependencies",
+    })];
+    for (family, members) in dependency_groups(deps) {
+        rules.push(serde_json::json!({
+            "matchManagers": ["cargo"],
+            "matchPackageNames": members,
+            "groupName": family,
+        }));
+    }
+    rules.push(serde_json::json!({
+        "matchManagers": ["github-actions"],
+        "groupName": "actions",
+    }));
+    let config = serde_json::json!({
+        "$schema": "https://docs.renovatebot.com/renovate-schema.json",
+        "extends": ["config:recommended"],
+        "schedule": ["before 6am on monday"],
+        "packageRules": rules,
+    });
+    let mut json = serde_json::to_string_pretty(&config).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// Creates the [UpdateBot] config for the dependencies of the project manifest.
+#[instrument(name = "updates", skip(dir, bot, dry, report))]
+pub fn create(
+    dir: &Path,
+    bot: UpdateBot,
+    dry: bool,
+    mut report: Option<&mut Report>,
+) -> Result<()> {
+    tracing::info!("Creating {} config", bot);
+    let deps = match dir.join("Cargo.toml").exists() {
+        true => manifest_dependencies(dir)?,
+        false => Vec::new(),
+    };
+    match bot {
+        UpdateBot::Dependabot => {
+            let github_dir = dir.join(".github");
+            crate::utils::create_dir_gracefully!(&github_dir, dry);
+            report.as_deref_mut().map(|r| r.begin_dir(".github"));
+            if !dry {
+                tracing::debug!("Writing {:?}", github_dir.join("dependabot.yml"));
+                std::fs::write(github_dir.join("dependabot.yml"), dependabot(&deps))?;
+            }
+            report.as_deref_mut().map(|r| r.created("dependabot.yml"));
+            report.map(|r| r.end_dir()); // <- .github/
+        }
+        UpdateBot::Renovate => {
+            if !dry {
+                tracing::debug!("Writing {:?}", dir.join("renovate.json"));
+                std::fs::write(dir.join("renovate.json"), renovate(&deps))?;
+            }
+            report.map(|r| r.created("renovate.json"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const MANIFEST: &str = r#"[workspace]
+members = ["bin/*", "crates/*"]
+
+[workspace.dependencies]
+anyhow = "1.0"
+serde = "1.0"
+serde_json = "1.0"
+tracing = "0.1"
+tracing-subscriber = "0.3"
+clap = { version = "4.4", features = ["derive"] }
+common = { path = "crates/common" }
+"#;
+
+    #[test]
+    fn test_dependency_groups() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), MANIFEST).unwrap();
+        let deps = manifest_dependencies(dir.path()).unwrap();
+        assert_eq!(
+            deps,
+            ["anyhow", "clap", "serde", "serde_json", "tracing", "tracing-subscriber"]
+        );
+        assert_eq!(
+            dependency_groups(&deps),
+            vec![
+                ("serde".to_string(), vec!["serde".to_string(), "serde_json".to_string()]),
+                (
+                    "tracing".to_string(),
+                    vec!["tracing".to_string(), "tracing-subscriber".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependabot() {
+        let deps = vec!["anyhow".to_string(), "serde".to_string(), "serde_json".to_string()];
+        let config: serde_yaml::Value = serde_yaml::from_str(&dependabot(&deps)).unwrap();
+        let updates = config["updates"].as_sequence().unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0]["package-ecosystem"], "cargo");
+        let groups = updates[0]["groups"].as_mapping().unwrap();
+        let names = groups.keys().map(|k| k.as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(names, ["serde", "dependencies"]);
+        assert_eq!(
+            groups["serde"]["patterns"],
+            serde_yaml::from_str::<serde_yaml::Value>("[serde, serde_json]").unwrap()
+        );
+        assert_eq!(updates[1]["package-ecosystem"], "github-actions");
+    }
+
+    #[test]
+    fn test_create_renovate() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), MANIFEST).unwrap();
+        let mut report = Report::new("example", false);
+        create(dir.path(), UpdateBot::Renovate, false, Some(&mut report)).unwrap();
+        let config = std::fs::read_to_string(dir.path().join("renovate.json")).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&config).unwrap();
+        let rules = config["packageRules"].as_array().unwrap();
+        assert_eq!(rules.len(), 4);
+        assert_eq!(rules[0]["groupName"], "dependencies");
+        assert_eq!(rules[1]["matchPackageNames"], serde_json::json!(["serde", "serde_json"]));
+        assert_eq!(rules[3]["matchManagers"], serde_json::json!(["github-actions"]));
+
+        create(dir.path(), UpdateBot::Dependabot, false, Some(&mut report)).unwrap();
+        assert!(dir.path().join(".github").join("dependabot.yml").exists());
+    }
+}
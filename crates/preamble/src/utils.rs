@@ -1,7 +1,7 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
-use inquire::Confirm;
+use crate::error::{Error, Result};
+use inquire::{Confirm, InquireError};
 use tracing::instrument;
 
 /// Creates a directory if it doesn't exist and the provided `--dry-run` flag is not set.
@@ -20,50 +20,58 @@ pub use create_dir_gracefully;
 /// Checks if rust artifacts are present in the given directory.
 /// If `dry_run` is enabled, this method will not error if rust
 /// artifacts are found.
+///
+/// The user is prompted to proceed when a conflicting file is found,
+/// returning [Error::UserAborted] if they decline and [Error::Conflict]
+/// if they can't be prompted.
 #[instrument(name = "utils", skip(dir, ci, dry_run))]
 pub fn check_artifacts(dir: &Path, ci: bool, dry_run: bool) -> Result<()> {
     if dry_run {
         return Ok(());
     }
-    let mut prompted = false;
+    let ci_yml = dir.join(".github").join("workflows").join("ci.yml");
     if dir.join("Cargo.toml").exists() {
         tracing::warn!("Rust artifacts detected in the project directory");
-        if !Confirm::new("[WARNING] Found conflicting files. Are you sure you wish to proceed?")
-            .prompt()?
-        {
-            println!("Phew, close call... aborting");
-            anyhow::bail!("User aborted after detecting rust artifacts in the project directory");
-        }
-        prompted = true;
-    }
-    if !prompted && dir.join("LICENSE").exists() {
+        confirm_conflict(
+            dir.join("Cargo.toml"),
+            "[WARNING] Found conflicting files. Are you sure you wish to proceed?",
+            "User aborted after detecting rust artifacts in the project directory",
+        )
+    } else if dir.join("LICENSE").exists() {
         tracing::warn!("LICENSE detected in the project directory");
-        if !Confirm::new("[WARNING] Found conflicting files. Are you sure you wish to proceed?")
-            .prompt()?
-        {
-            println!("Phew, close call... aborting");
-            anyhow::bail!("User aborted after detecting existing license in the project directory");
-        }
-        prompted = true;
-    }
-    if !prompted && dir.join("README.md").exists() {
+        confirm_conflict(
+            dir.join("LICENSE"),
+            "[WARNING] Found conflicting files. Are you sure you wish to proceed?",
+            "User aborted after detecting existing license in the project directory",
+        )
+    } else if dir.join("README.md").exists() {
         tracing::warn!("README detected in the project directory");
-        if !Confirm::new("[WARNING] Found README.md in the project directory. Proceeding will overwrite this file. Are you sure you wish to proceed?")
-            .prompt()?
-        {
-            println!("Phew, close call... aborting");
-            anyhow::bail!("User aborted after detecting existing readme in the project directory");
-        }
-        prompted = true;
-    }
-    if !prompted && ci && dir.join(".github").join("workflows").join("ci.yml").exists() {
+        confirm_conflict(
+            dir.join("README.md"),
+            "[WARNING] Found README.md in the project directory. Proceeding will overwrite this file. Are you sure you wish to proceed?",
+            "User aborted after detecting existing readme in the project directory",
+        )
+    } else if ci && ci_yml.exists() {
         tracing::warn!("Rust artifacts detected in the project directory");
-        if !Confirm::new("[WARNING] Found conflicting files. Are you sure you wish to proceed?")
-            .prompt()?
-        {
+        confirm_conflict(
+            ci_yml,
+            "[WARNING] Found conflicting files. Are you sure you wish to proceed?",
+            "User aborted after detecting rust artifacts in the project directory",
+        )
+    } else {
+        Ok(())
+    }
+}
+
+/// Prompts the user to proceed despite the conflicting file at `path`.
+fn confirm_conflict(path: PathBuf, message: &str, reason: &str) -> Result<()> {
+    match Confirm::new(message).prompt() {
+        Ok(true) => Ok(()),
+        Ok(false) => {
             println!("Phew, close call... aborting");
-            anyhow::bail!("User aborted after detecting rust artifacts in the project directory");
+            Err(Error::UserAborted(reason.to_string()))
         }
+        Err(InquireError::NotTTY) => Err(Error::Conflict(path)),
+        Err(e) => Err(e.into()),
     }
-    Ok(())
 }
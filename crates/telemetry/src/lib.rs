@@ -0,0 +1,300 @@
+#![doc = include_str!("../README.md")]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/refcell/amble/main/etc/logo.png",
+    html_favicon_url = "https://raw.githubusercontent.com/refcell/amble/main/etc/favicon.ico",
+    issue_tracker_base_url = "https://github.com/refcell/amble/issues/"
+)]
+#![warn(missing_debug_implementations, missing_docs, unreachable_pub, rustdoc::all)]
+#![deny(unused_must_use, rust_2018_idioms)]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+use anyhow::Result;
+use std::path::PathBuf;
+use tracing::{level_filters::LevelFilter, Subscriber};
+use tracing_appender::rolling::RollingFileAppender;
+use tracing_subscriber::{
+    fmt::{format::FmtSpan, MakeWriter},
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    EnvFilter, Layer, Registry,
+};
+
+pub use tracing_appender::rolling::Rotation;
+
+/// The output format of emitted log lines.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The default human readable, single-line format.
+    #[default]
+    Full,
+    /// A condensed human readable, single-line format.
+    Compact,
+    /// A verbose, multi-line human readable format.
+    Pretty,
+    /// Newline-delimited JSON objects.
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "compact" => Ok(Self::Compact),
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => anyhow::bail!(
+                "Unknown log format \"{}\", expected one of full, compact, pretty or json",
+                s
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::Compact => write!(f, "compact"),
+            Self::Pretty => write!(f, "pretty"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Rotating log file output.
+#[derive(Debug, Clone)]
+pub struct FileOutput {
+    /// The directory log files are written to.
+    pub directory: PathBuf,
+    /// The log file name prefix.
+    pub prefix: String,
+    /// How often a new log file is started.
+    pub rotation: Rotation,
+}
+
+/// Telemetry configuration.
+///
+/// Configured through chained builder methods and installed as the
+/// global default subscriber with [Telemetry::init].
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    verbosity: u8,
+    directives: Option<String>,
+    format: LogFormat,
+    file: Option<FileOutput>,
+    span_timing: bool,
+    ansi: bool,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self {
+            verbosity: 0,
+            directives: None,
+            format: LogFormat::default(),
+            file: None,
+            span_timing: false,
+            ansi: true,
+        }
+    }
+}
+
+/// Maps a verbosity level to the maximum enabled [LevelFilter].
+///
+/// - 0: ERROR
+/// - 1: WARN
+/// - 2: INFO
+/// - 3: DEBUG
+/// - 4+: TRACE
+pub fn verbosity_level(verbosity_level: u8) -> LevelFilter {
+    match verbosity_level {
+        0 => LevelFilter::ERROR,
+        1 => LevelFilter::WARN,
+        2 => LevelFilter::INFO,
+        3 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+impl Telemetry {
+    /// Creates a new [Telemetry] configuration with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the verbosity level used as the default filter directive.
+    /// See [verbosity_level] for the level mapping.
+    pub fn verbosity(mut self, verbosity: u8) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Sets [EnvFilter] directives, e.g. `"my_crate=debug,hyper=warn"`.
+    /// Directives in the `RUST_LOG` environment variable take precedence.
+    pub fn directives(mut self, directives: impl Into<String>) -> Self {
+        self.directives = Some(directives.into());
+        self
+    }
+
+    /// Sets the [LogFormat].
+    pub fn format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Additionally writes logs to rotating files in the given directory.
+    pub fn file(
+        mut self,
+        directory: impl Into<PathBuf>,
+        prefix: impl Into<String>,
+        rotation: Rotation,
+    ) -> Self {
+        self.file =
+            Some(FileOutput { directory: directory.into(), prefix: prefix.into(), rotation });
+        self
+    }
+
+    /// Emits an event with busy and idle timings whenever a span closes.
+    pub fn span_timing(mut self, span_timing: bool) -> Self {
+        self.span_timing = span_timing;
+        self
+    }
+
+    /// Enables or disables ANSI colors on stdout. File output never uses colors.
+    pub fn ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// Builds the [EnvFilter], honoring the `RUST_LOG` environment variable.
+    pub fn env_filter(&self) -> Result<EnvFilter> {
+        self.env_filter_from(std::env::var(EnvFilter::DEFAULT_ENV).ok())
+    }
+
+    fn env_filter_from(&self, env: Option<String>) -> Result<EnvFilter> {
+        let builder =
+            EnvFilter::builder().with_default_directive(verbosity_level(self.verbosity).into());
+        let directives = match env {
+            Some(env) if !env.trim().is_empty() => env,
+            _ => self.directives.clone().unwrap_or_default(),
+        };
+        Ok(builder.parse(directives)?)
+    }
+
+    /// Builds the configured subscriber without installing it.
+    pub fn subscriber(&self) -> Result<impl Subscriber + Send + Sync> {
+        let file = match &self.file {
+            Some(f) => {
+                let appender = RollingFileAppender::builder()
+                    .rotation(f.rotation.clone())
+                    .filename_prefix(&f.prefix)
+                    .build(&f.directory)?;
+                Some(fmt_layer(self.format, self.span_timing, false, appender))
+            }
+            None => None,
+        };
+        let stdout = fmt_layer(self.format, self.span_timing, self.ansi, std::io::stdout);
+        Ok(Registry::default().with(self.env_filter()?).with(stdout).with(file))
+    }
+
+    /// Installs the configured subscriber as the global default.
+    pub fn init(self) -> Result<()> {
+        tracing::subscriber::set_global_default(self.subscriber()?).map_err(|e| anyhow::anyhow!(e))
+    }
+}
+
+/// Builds a boxed fmt [Layer] for the given format and writer.
+fn fmt_layer<S, W>(
+    format: LogFormat,
+    span_timing: bool,
+    ansi: bool,
+    writer: W,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let span_events = if span_timing { FmtSpan::CLOSE } else { FmtSpan::NONE };
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_span_events(span_events);
+    match format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_verbosity_level() {
+        assert_eq!(verbosity_level(0), LevelFilter::ERROR);
+        assert_eq!(verbosity_level(1), LevelFilter::WARN);
+        assert_eq!(verbosity_level(2), LevelFilter::INFO);
+        assert_eq!(verbosity_level(3), LevelFilter::DEBUG);
+        assert_eq!(verbosity_level(4), LevelFilter::TRACE);
+        assert_eq!(verbosity_level(u8::MAX), LevelFilter::TRACE);
+    }
+
+    #[test]
+    fn test_log_format_from_str() {
+        assert_eq!("full".parse::<LogFormat>().unwrap(), LogFormat::Full);
+        assert_eq!("Compact".parse::<LogFormat>().unwrap(), LogFormat::Compact);
+        assert_eq!("pretty".parse::<LogFormat>().unwrap(), LogFormat::Pretty);
+        assert_eq!("JSON".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("xml".parse::<LogFormat>().is_err());
+        assert_eq!(LogFormat::Json.to_string().parse::<LogFormat>().unwrap(), LogFormat::Json);
+    }
+
+    #[test]
+    fn test_env_filter_directives() {
+        let telemetry = Telemetry::new().verbosity(1);
+        let filter = telemetry.env_filter_from(None).unwrap();
+        assert_eq!(filter.max_level_hint(), Some(LevelFilter::WARN));
+
+        let telemetry = telemetry.directives("example=trace");
+        let filter = telemetry.env_filter_from(None).unwrap();
+        assert_eq!(filter.max_level_hint(), Some(LevelFilter::TRACE));
+
+        // The `RUST_LOG` directives take precedence over the configured ones.
+        let filter = telemetry.env_filter_from(Some("info".to_string())).unwrap();
+        assert_eq!(filter.max_level_hint(), Some(LevelFilter::INFO));
+    }
+
+    #[test]
+    fn test_env_filter_invalid_directives() {
+        let telemetry = Telemetry::new().directives("example=notalevel");
+        assert!(telemetry.env_filter_from(None).is_err());
+    }
+
+    #[test]
+    fn test_file_output() {
+        let dir = tempdir().unwrap();
+        let subscriber = Telemetry::new()
+            .verbosity(2)
+            .format(LogFormat::Json)
+            .span_timing(true)
+            .file(dir.path(), "test.log", Rotation::NEVER)
+            .subscriber()
+            .unwrap();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("work");
+            span.in_scope(|| tracing::info!("hello from the file output"));
+        });
+
+        let contents = std::fs::read_to_string(dir.path().join("test.log")).unwrap();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains(r#""message":"hello from the file output""#));
+        assert!(lines[1].contains("time.busy"));
+        assert!(!contents.contains('\u{1b}'));
+    }
+}